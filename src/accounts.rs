@@ -0,0 +1,1014 @@
+//! Accounts of each instruction
+//!
+//! Every instruction reads its accounts into a struct, in the order of the `NftCanvasInstruction` builders,
+//! and checks them against a table of constraints. Each struct lists the names of its accounts in that order
+//! in `ROLES`, which the decoder names accounts by. Checks that depend on account data (the pixel owner, the
+//! canvas admin, the pixel, trade pool and fee vault addresses whose bump seeds are stored in accounts, the
+//! config and stats accounts, only checked to be owned by the program here, ...) are left to the processor.
+
+use std::slice::Iter;
+
+use solana_program::account_info::{next_account_info, AccountInfo};
+use solana_program::entrypoint::ProgramResult;
+use solana_program::msg;
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+use solana_program::sysvar;
+
+use crate::config;
+use crate::error::NftCanvasError;
+use crate::error::NftCanvasError::*;
+use crate::token;
+
+use self::Constraint::*;
+
+pub enum Constraint<'k> {
+    /// The account is this address.
+    Address(&'k Pubkey, NftCanvasError),
+    /// The account address passes this check.
+    Key(fn(&Pubkey) -> bool, NftCanvasError),
+    /// The account is owned by this program.
+    Owner(&'k Pubkey, NftCanvasError),
+    Signer(NftCanvasError),
+    /// The account is a token account of this wallet for the token mint. Accounts that do not exist yet pass, as
+    /// the associated token account program checks their address when creating them, and the token program fails
+    /// transfers with them otherwise.
+    TokenAccount(&'k Pubkey, NftCanvasError),
+    Writable,
+}
+
+/// Checks each named account against its constraints, logging the name of the first account that fails.
+pub fn check_accounts(accounts: &[(&str, &AccountInfo, &[Constraint])]) -> ProgramResult {
+    for (name, account, constraints) in accounts {
+        for constraint in constraints.iter() {
+            let result = match constraint {
+                Address(address, err) => if account.key != *address { Err(*err) } else { Ok(()) },
+                Key(check, err) => if !check(account.key) { Err(*err) } else { Ok(()) },
+                Owner(owner, err) => if account.owner != *owner { Err(*err) } else { Ok(()) },
+                Signer(err) => if !account.is_signer { Err(*err) } else { Ok(()) },
+                TokenAccount(wallet, err) => if account.data_is_empty()
+                    || (token::is_token_program(account.owner) && token::is_token_account_of(&account.data.borrow(), wallet)) {
+                    Ok(())
+                } else {
+                    Err(*err)
+                },
+                Writable => if !account.is_writable { Err(AccountNotWritable) } else { Ok(()) },
+            };
+            if let Err(err) = result {
+                msg!("Invalid account {}: {}", name, err);
+                return Err(err.into());
+            }
+        }
+    }
+    Ok(())
+}
+
+pub struct MintPixelAccounts<'a, 'b> {
+    pub program: &'a AccountInfo<'b>,
+    pub canvas_config: &'a AccountInfo<'b>,
+    pub canvas_stats: &'a AccountInfo<'b>,
+    pub canvas_stats_shard: &'a AccountInfo<'b>,
+    pub system_program: &'a AccountInfo<'b>,
+    pub rent_sysvar: &'a AccountInfo<'b>,
+    pub fee_vault: &'a AccountInfo<'b>,
+    pub pixel_account: &'a AccountInfo<'b>,
+    pub owner_wallet: &'a AccountInfo<'b>,
+    /// Only during the presale.
+    pub presale_receipt: Option<&'a AccountInfo<'b>>,
+    pub pixel_account_bump_seed: u8,
+    pub presale_receipt_bump_seed: u8,
+}
+
+impl<'a, 'b> MintPixelAccounts<'a, 'b> {
+    pub const ROLES: &'static [&'static str] = &[
+        "program", "canvas_config", "canvas_stats", "canvas_stats_shard", "system_program", "rent_sysvar", "fee_vault",
+        "pixel_account", "owner_wallet",
+    ];
+    pub const OPTIONAL_ROLES: &'static [&'static str] = &["presale_receipt"];
+
+    pub fn load(program_id: &Pubkey, accounts: &'a [AccountInfo<'b>], index: u32) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let program = next_account_info(accounts_iter)?;
+        let canvas_config = next_account_info(accounts_iter)?;
+        let canvas_stats = next_account_info(accounts_iter)?;
+        let canvas_stats_shard = next_account_info(accounts_iter)?;
+        let system_program = next_account_info(accounts_iter)?;
+        let rent_sysvar = next_account_info(accounts_iter)?;
+        let fee_vault = next_account_info(accounts_iter)?;
+        let pixel_account = next_account_info(accounts_iter)?;
+        let owner_wallet = next_account_info(accounts_iter)?;
+        let presale_receipt = next_account_info(accounts_iter).ok();
+
+        // The one pixel address still found with `find_program_address`: minting runs once per pixel, and the
+        // account does not exist yet to store the bump seed in, which it is then created with. (Clients send
+        // `MintPixel` without a bump seed)
+        let (pixel_account_id, pixel_account_bump_seed) = config::get_pixel(program_id, index);
+        check_accounts(&[
+            ("program", program, &[Address(program_id, ProgramKeyMismatch)]),
+            ("canvas_config", canvas_config, &[Owner(program_id, CanvasConfigKeyMismatch)]),
+            ("canvas_stats", canvas_stats, &[Owner(program_id, CanvasStatsKeyMismatch), Writable]),
+            ("canvas_stats_shard", canvas_stats_shard, &[Owner(program_id, CanvasStatsKeyMismatch), Writable]),
+            ("system_program", system_program, &[Address(&system_program::id(), SystemProgramKeyMismatch)]),
+            ("rent_sysvar", rent_sysvar, &[Address(&sysvar::rent::id(), RentSysvarKeyMismatch)]),
+            ("fee_vault", fee_vault, &[Writable]),
+            ("pixel_account", pixel_account, &[Address(&pixel_account_id, PixelAccountKeyMismatch), Writable]),
+            ("owner_wallet", owner_wallet, &[Signer(FunderDidNotSign), Writable]),
+        ])?;
+        let mut presale_receipt_bump_seed = 0;
+        if let Some(presale_receipt) = presale_receipt {
+            let (presale_receipt_id, bump_seed) = config::get_presale_receipt(program_id, owner_wallet.key);
+            check_accounts(&[
+                ("presale_receipt", presale_receipt, &[Address(&presale_receipt_id, PresaleReceiptKeyMismatch), Writable]),
+            ])?;
+            presale_receipt_bump_seed = bump_seed;
+        }
+
+        Ok(MintPixelAccounts {
+            program,
+            canvas_config,
+            canvas_stats,
+            canvas_stats_shard,
+            system_program,
+            rent_sysvar,
+            fee_vault,
+            pixel_account,
+            owner_wallet,
+            presale_receipt,
+            pixel_account_bump_seed,
+            presale_receipt_bump_seed,
+        })
+    }
+}
+
+pub struct UpdatePixelColorAccounts<'a, 'b> {
+    pub program: &'a AccountInfo<'b>,
+    pub canvas_config: &'a AccountInfo<'b>,
+    pub pixel_account: &'a AccountInfo<'b>,
+    pub authority_wallet: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> UpdatePixelColorAccounts<'a, 'b> {
+    pub const ROLES: &'static [&'static str] = &["program", "canvas_config", "pixel_account", "authority_wallet"];
+
+    pub fn load(program_id: &Pubkey, accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let program = next_account_info(accounts_iter)?;
+        let canvas_config = next_account_info(accounts_iter)?;
+        let pixel_account = next_account_info(accounts_iter)?;
+        let authority_wallet = next_account_info(accounts_iter)?;
+
+        check_accounts(&[
+            ("program", program, &[Address(program_id, ProgramKeyMismatch)]),
+            ("canvas_config", canvas_config, &[Owner(program_id, CanvasConfigKeyMismatch)]),
+            ("pixel_account", pixel_account, &[Writable]),
+            ("authority_wallet", authority_wallet, &[Signer(ColorAuthorityDidNotSign)]),
+        ])?;
+
+        Ok(UpdatePixelColorAccounts { program, canvas_config, pixel_account, authority_wallet })
+    }
+}
+
+pub struct SetColorDelegateAccounts<'a, 'b> {
+    pub program: &'a AccountInfo<'b>,
+    pub canvas_config: &'a AccountInfo<'b>,
+    pub pixel_account: &'a AccountInfo<'b>,
+    pub owner_wallet: &'a AccountInfo<'b>,
+    /// No delegate account means the delegate is removed.
+    pub color_delegate: Option<&'a AccountInfo<'b>>,
+}
+
+impl<'a, 'b> SetColorDelegateAccounts<'a, 'b> {
+    pub const ROLES: &'static [&'static str] = &["program", "canvas_config", "pixel_account", "owner_wallet"];
+    pub const OPTIONAL_ROLES: &'static [&'static str] = &["color_delegate"];
+
+    pub fn load(program_id: &Pubkey, accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let program = next_account_info(accounts_iter)?;
+        let canvas_config = next_account_info(accounts_iter)?;
+        let pixel_account = next_account_info(accounts_iter)?;
+        let owner_wallet = next_account_info(accounts_iter)?;
+        let color_delegate = next_account_info(accounts_iter).ok();
+
+        check_accounts(&[
+            ("program", program, &[Address(program_id, ProgramKeyMismatch)]),
+            ("canvas_config", canvas_config, &[Owner(program_id, CanvasConfigKeyMismatch)]),
+            ("pixel_account", pixel_account, &[Writable]),
+            ("owner_wallet", owner_wallet, &[Signer(PixelOwnerDidNotSign)]),
+        ])?;
+
+        Ok(SetColorDelegateAccounts { program, canvas_config, pixel_account, owner_wallet, color_delegate })
+    }
+}
+
+/// Accounts of `AcceptBid` and of the deprecated `SellPixel`.
+pub struct AcceptBidAccounts<'a, 'b> {
+    pub program: &'a AccountInfo<'b>,
+    pub canvas_config: &'a AccountInfo<'b>,
+    pub canvas_stats_shard: &'a AccountInfo<'b>,
+    pub token_program: &'a AccountInfo<'b>,
+    pub token_mint: &'a AccountInfo<'b>,
+    pub system_program: &'a AccountInfo<'b>,
+    pub rent_sysvar: &'a AccountInfo<'b>,
+    pub associated_token_program: &'a AccountInfo<'b>,
+    pub pixel_account: &'a AccountInfo<'b>,
+    pub fee_vault: &'a AccountInfo<'b>,
+    pub fee_vault_token_account: &'a AccountInfo<'b>,
+    pub trade_pool: &'a AccountInfo<'b>,
+    pub trade_pool_token_account: &'a AccountInfo<'b>,
+    pub pixel_owner: &'a AccountInfo<'b>,
+    pub pixel_owner_token_account: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> AcceptBidAccounts<'a, 'b> {
+    pub const ROLES: &'static [&'static str] = &[
+        "program", "canvas_config", "canvas_stats_shard", "token_program", "token_mint", "system_program",
+        "rent_sysvar", "associated_token_program", "pixel_account", "fee_vault", "fee_vault_token_account",
+        "trade_pool", "trade_pool_token_account", "pixel_owner", "pixel_owner_token_account",
+    ];
+
+    pub fn load(program_id: &Pubkey, accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let program = next_account_info(accounts_iter)?;
+        let canvas_config = next_account_info(accounts_iter)?;
+        let canvas_stats_shard = next_account_info(accounts_iter)?;
+        let token_program = next_account_info(accounts_iter)?;
+        let token_mint = next_account_info(accounts_iter)?;
+        let system_program = next_account_info(accounts_iter)?;
+        let rent_sysvar = next_account_info(accounts_iter)?;
+        let associated_token_program = next_account_info(accounts_iter)?;
+        let pixel_account = next_account_info(accounts_iter)?;
+        let fee_vault = next_account_info(accounts_iter)?;
+        let fee_vault_token_account = next_account_info(accounts_iter)?;
+        let trade_pool = next_account_info(accounts_iter)?;
+        let trade_pool_token_account = next_account_info(accounts_iter)?;
+        let pixel_owner = next_account_info(accounts_iter)?;
+        let pixel_owner_token_account = next_account_info(accounts_iter)?;
+
+        check_accounts(&[
+            ("program", program, &[Address(program_id, ProgramKeyMismatch)]),
+            ("canvas_config", canvas_config, &[Owner(program_id, CanvasConfigKeyMismatch)]),
+            ("canvas_stats_shard", canvas_stats_shard, &[Owner(program_id, CanvasStatsKeyMismatch), Writable]),
+            ("token_program", token_program, &[Key(token::is_token_program, TokenProgramKeyMismatch)]),
+            ("token_mint", token_mint, &[Address(&config::token_mint::id(), TokenMintKeyMismatch), Owner(token_program.key, TokenMintKeyMismatch)]),
+            ("system_program", system_program, &[Address(&system_program::id(), SystemProgramKeyMismatch)]),
+            ("rent_sysvar", rent_sysvar, &[Address(&sysvar::rent::id(), RentSysvarKeyMismatch)]),
+            ("associated_token_program", associated_token_program, &[Address(&spl_associated_token_account::id(), AssociatedTokenProgramKeyMismatch)]),
+            ("pixel_account", pixel_account, &[Writable]),
+            ("fee_vault_token_account", fee_vault_token_account, &[
+                TokenAccount(fee_vault.key, FeeVaultTokenAccountKeyMismatch), Writable]),
+            ("trade_pool_token_account", trade_pool_token_account, &[
+                TokenAccount(trade_pool.key, TradePoolTokenAccountKeyMismatch), Writable]),
+            ("pixel_owner", pixel_owner, &[Signer(PixelOwnerDidNotSign), Writable]),
+            ("pixel_owner_token_account", pixel_owner_token_account, &[
+                TokenAccount(pixel_owner.key, PixelOwnerTokenAccountKeyMismatch), Writable]),
+        ])?;
+
+        Ok(AcceptBidAccounts {
+            program,
+            canvas_config,
+            canvas_stats_shard,
+            token_program,
+            token_mint,
+            system_program,
+            rent_sysvar,
+            associated_token_program,
+            pixel_account,
+            fee_vault,
+            fee_vault_token_account,
+            trade_pool,
+            trade_pool_token_account,
+            pixel_owner,
+            pixel_owner_token_account,
+        })
+    }
+}
+
+/// `previous_buyer_token_account` and `previous_buyer_wallet` come after these, when outbidding.
+pub struct BuyPixelAccounts<'a, 'b> {
+    pub program: &'a AccountInfo<'b>,
+    pub canvas_config: &'a AccountInfo<'b>,
+    pub canvas_stats_shard: &'a AccountInfo<'b>,
+    pub token_program: &'a AccountInfo<'b>,
+    pub token_mint: &'a AccountInfo<'b>,
+    pub system_program: &'a AccountInfo<'b>,
+    pub rent_sysvar: &'a AccountInfo<'b>,
+    pub associated_token_program: &'a AccountInfo<'b>,
+    pub pixel_account: &'a AccountInfo<'b>,
+    pub fee_vault: &'a AccountInfo<'b>,
+    pub fee_vault_token_account: &'a AccountInfo<'b>,
+    pub trade_pool: &'a AccountInfo<'b>,
+    pub trade_pool_token_account: &'a AccountInfo<'b>,
+    pub pixel_owner: &'a AccountInfo<'b>,
+    pub pixel_owner_token_account: &'a AccountInfo<'b>,
+    pub buyer_wallet: &'a AccountInfo<'b>,
+    pub buyer_token_account: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> BuyPixelAccounts<'a, 'b> {
+    pub const ROLES: &'static [&'static str] = &[
+        "program", "canvas_config", "canvas_stats_shard", "token_program", "token_mint", "system_program",
+        "rent_sysvar", "associated_token_program", "pixel_account", "fee_vault", "fee_vault_token_account",
+        "trade_pool", "trade_pool_token_account", "pixel_owner", "pixel_owner_token_account", "buyer_wallet",
+        "buyer_token_account",
+    ];
+
+    /// Returns the remaining accounts too.
+    pub fn load(program_id: &Pubkey, accounts: &'a [AccountInfo<'b>]) -> Result<(Self, &'a [AccountInfo<'b>]), ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let program = next_account_info(accounts_iter)?;
+        let canvas_config = next_account_info(accounts_iter)?;
+        let canvas_stats_shard = next_account_info(accounts_iter)?;
+        let token_program = next_account_info(accounts_iter)?;
+        let token_mint = next_account_info(accounts_iter)?;
+        let system_program = next_account_info(accounts_iter)?;
+        let rent_sysvar = next_account_info(accounts_iter)?;
+        let associated_token_program = next_account_info(accounts_iter)?;
+        let pixel_account = next_account_info(accounts_iter)?;
+        let fee_vault = next_account_info(accounts_iter)?;
+        let fee_vault_token_account = next_account_info(accounts_iter)?;
+        let trade_pool = next_account_info(accounts_iter)?;
+        let trade_pool_token_account = next_account_info(accounts_iter)?;
+        let pixel_owner = next_account_info(accounts_iter)?;
+        let pixel_owner_token_account = next_account_info(accounts_iter)?;
+        let buyer_wallet = next_account_info(accounts_iter)?;
+        let buyer_token_account = next_account_info(accounts_iter)?;
+
+        check_accounts(&[
+            ("program", program, &[Address(program_id, ProgramKeyMismatch)]),
+            ("canvas_config", canvas_config, &[Owner(program_id, CanvasConfigKeyMismatch)]),
+            ("canvas_stats_shard", canvas_stats_shard, &[Owner(program_id, CanvasStatsKeyMismatch), Writable]),
+            ("token_program", token_program, &[Key(token::is_token_program, TokenProgramKeyMismatch)]),
+            ("token_mint", token_mint, &[Address(&config::token_mint::id(), TokenMintKeyMismatch), Owner(token_program.key, TokenMintKeyMismatch)]),
+            ("system_program", system_program, &[Address(&system_program::id(), SystemProgramKeyMismatch)]),
+            ("rent_sysvar", rent_sysvar, &[Address(&sysvar::rent::id(), RentSysvarKeyMismatch)]),
+            ("associated_token_program", associated_token_program, &[Address(&spl_associated_token_account::id(), AssociatedTokenProgramKeyMismatch)]),
+            ("pixel_account", pixel_account, &[Writable]),
+            ("fee_vault_token_account", fee_vault_token_account, &[
+                TokenAccount(fee_vault.key, FeeVaultTokenAccountKeyMismatch), Writable]),
+            ("trade_pool_token_account", trade_pool_token_account, &[
+                TokenAccount(trade_pool.key, TradePoolTokenAccountKeyMismatch), Writable]),
+            ("pixel_owner_token_account", pixel_owner_token_account, &[
+                TokenAccount(pixel_owner.key, PixelOwnerTokenAccountKeyMismatch), Writable]),
+            ("buyer_wallet", buyer_wallet, &[Signer(BuyerDidNotSign), Writable]),
+            ("buyer_token_account", buyer_token_account, &[
+                TokenAccount(buyer_wallet.key, BuyerTokenAccountKeyMismatch), Writable]),
+        ])?;
+
+        Ok((BuyPixelAccounts {
+            program,
+            canvas_config,
+            canvas_stats_shard,
+            token_program,
+            token_mint,
+            system_program,
+            rent_sysvar,
+            associated_token_program,
+            pixel_account,
+            fee_vault,
+            fee_vault_token_account,
+            trade_pool,
+            trade_pool_token_account,
+            pixel_owner,
+            pixel_owner_token_account,
+            buyer_wallet,
+            buyer_token_account,
+        }, accounts_iter.as_slice()))
+    }
+}
+
+/// The accounts of the bidders to refund come after these: `previous_buyer_token_account` and
+/// `previous_buyer_wallet` if the pixel has a token bid, then `previous_sol_buyer_wallet` if it has a SOL bid.
+pub struct ClosePixelAccounts<'a, 'b> {
+    pub program: &'a AccountInfo<'b>,
+    pub canvas_config: &'a AccountInfo<'b>,
+    pub canvas_stats: &'a AccountInfo<'b>,
+    pub canvas_stats_shard: &'a AccountInfo<'b>,
+    pub system_program: &'a AccountInfo<'b>,
+    pub token_program: &'a AccountInfo<'b>,
+    pub token_mint: &'a AccountInfo<'b>,
+    pub rent_sysvar: &'a AccountInfo<'b>,
+    pub associated_token_program: &'a AccountInfo<'b>,
+    pub pixel_account: &'a AccountInfo<'b>,
+    pub trade_pool: &'a AccountInfo<'b>,
+    pub trade_pool_token_account: &'a AccountInfo<'b>,
+    pub pixel_owner: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> ClosePixelAccounts<'a, 'b> {
+    pub const ROLES: &'static [&'static str] = &[
+        "program", "canvas_config", "canvas_stats", "canvas_stats_shard", "system_program", "token_program",
+        "token_mint", "rent_sysvar", "associated_token_program", "pixel_account", "trade_pool",
+        "trade_pool_token_account", "pixel_owner",
+    ];
+
+    /// Returns the remaining accounts too.
+    pub fn load(program_id: &Pubkey, accounts: &'a [AccountInfo<'b>]) -> Result<(Self, &'a [AccountInfo<'b>]), ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let program = next_account_info(accounts_iter)?;
+        let canvas_config = next_account_info(accounts_iter)?;
+        let canvas_stats = next_account_info(accounts_iter)?;
+        let canvas_stats_shard = next_account_info(accounts_iter)?;
+        let system_program = next_account_info(accounts_iter)?;
+        let token_program = next_account_info(accounts_iter)?;
+        let token_mint = next_account_info(accounts_iter)?;
+        let rent_sysvar = next_account_info(accounts_iter)?;
+        let associated_token_program = next_account_info(accounts_iter)?;
+        let pixel_account = next_account_info(accounts_iter)?;
+        let trade_pool = next_account_info(accounts_iter)?;
+        let trade_pool_token_account = next_account_info(accounts_iter)?;
+        let pixel_owner = next_account_info(accounts_iter)?;
+
+        check_accounts(&[
+            ("program", program, &[Address(program_id, ProgramKeyMismatch)]),
+            ("canvas_config", canvas_config, &[Owner(program_id, CanvasConfigKeyMismatch)]),
+            ("canvas_stats", canvas_stats, &[Owner(program_id, CanvasStatsKeyMismatch), Writable]),
+            ("canvas_stats_shard", canvas_stats_shard, &[Owner(program_id, CanvasStatsKeyMismatch), Writable]),
+            ("system_program", system_program, &[Address(&system_program::id(), SystemProgramKeyMismatch)]),
+            ("token_program", token_program, &[Key(token::is_token_program, TokenProgramKeyMismatch)]),
+            ("token_mint", token_mint, &[Address(&config::token_mint::id(), TokenMintKeyMismatch), Owner(token_program.key, TokenMintKeyMismatch)]),
+            ("rent_sysvar", rent_sysvar, &[Address(&sysvar::rent::id(), RentSysvarKeyMismatch)]),
+            ("associated_token_program", associated_token_program, &[Address(&spl_associated_token_account::id(), AssociatedTokenProgramKeyMismatch)]),
+            ("pixel_account", pixel_account, &[Writable]),
+            ("trade_pool", trade_pool, &[Writable]),
+            ("trade_pool_token_account", trade_pool_token_account, &[
+                TokenAccount(trade_pool.key, TradePoolTokenAccountKeyMismatch), Writable]),
+            ("pixel_owner", pixel_owner, &[Signer(PixelOwnerDidNotSign), Writable]),
+        ])?;
+
+        Ok((ClosePixelAccounts {
+            program,
+            canvas_config,
+            canvas_stats,
+            canvas_stats_shard,
+            system_program,
+            token_program,
+            token_mint,
+            rent_sysvar,
+            associated_token_program,
+            pixel_account,
+            trade_pool,
+            trade_pool_token_account,
+            pixel_owner,
+        }, accounts_iter.as_slice()))
+    }
+}
+
+/// Accounts of `AcceptSolBid` and of the deprecated `SellPixelSol`.
+pub struct AcceptSolBidAccounts<'a, 'b> {
+    pub program: &'a AccountInfo<'b>,
+    pub canvas_config: &'a AccountInfo<'b>,
+    pub canvas_stats_shard: &'a AccountInfo<'b>,
+    pub system_program: &'a AccountInfo<'b>,
+    pub pixel_account: &'a AccountInfo<'b>,
+    pub fee_vault: &'a AccountInfo<'b>,
+    pub trade_pool: &'a AccountInfo<'b>,
+    pub pixel_owner: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> AcceptSolBidAccounts<'a, 'b> {
+    pub const ROLES: &'static [&'static str] = &[
+        "program", "canvas_config", "canvas_stats_shard", "system_program", "pixel_account", "fee_vault", "trade_pool",
+        "pixel_owner",
+    ];
+
+    pub fn load(program_id: &Pubkey, accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let program = next_account_info(accounts_iter)?;
+        let canvas_config = next_account_info(accounts_iter)?;
+        let canvas_stats_shard = next_account_info(accounts_iter)?;
+        let system_program = next_account_info(accounts_iter)?;
+        let pixel_account = next_account_info(accounts_iter)?;
+        let fee_vault = next_account_info(accounts_iter)?;
+        let trade_pool = next_account_info(accounts_iter)?;
+        let pixel_owner = next_account_info(accounts_iter)?;
+
+        check_accounts(&[
+            ("program", program, &[Address(program_id, ProgramKeyMismatch)]),
+            ("canvas_config", canvas_config, &[Owner(program_id, CanvasConfigKeyMismatch)]),
+            ("canvas_stats_shard", canvas_stats_shard, &[Owner(program_id, CanvasStatsKeyMismatch), Writable]),
+            ("system_program", system_program, &[Address(&system_program::id(), SystemProgramKeyMismatch)]),
+            ("pixel_account", pixel_account, &[Writable]),
+            ("fee_vault", fee_vault, &[Writable]),
+            ("trade_pool", trade_pool, &[Writable]),
+            ("pixel_owner", pixel_owner, &[Signer(PixelOwnerDidNotSign), Writable]),
+        ])?;
+
+        Ok(AcceptSolBidAccounts {
+            program,
+            canvas_config,
+            canvas_stats_shard,
+            system_program,
+            pixel_account,
+            fee_vault,
+            trade_pool,
+            pixel_owner,
+        })
+    }
+}
+
+/// `previous_sol_buyer_wallet` comes after these, when outbidding.
+pub struct BuyPixelSolAccounts<'a, 'b> {
+    pub program: &'a AccountInfo<'b>,
+    pub canvas_config: &'a AccountInfo<'b>,
+    pub canvas_stats_shard: &'a AccountInfo<'b>,
+    pub system_program: &'a AccountInfo<'b>,
+    pub pixel_account: &'a AccountInfo<'b>,
+    pub fee_vault: &'a AccountInfo<'b>,
+    pub trade_pool: &'a AccountInfo<'b>,
+    pub pixel_owner: &'a AccountInfo<'b>,
+    pub buyer_wallet: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> BuyPixelSolAccounts<'a, 'b> {
+    pub const ROLES: &'static [&'static str] = &[
+        "program", "canvas_config", "canvas_stats_shard", "system_program", "pixel_account", "fee_vault", "trade_pool",
+        "pixel_owner", "buyer_wallet",
+    ];
+
+    /// Returns the remaining accounts too.
+    pub fn load(program_id: &Pubkey, accounts: &'a [AccountInfo<'b>]) -> Result<(Self, &'a [AccountInfo<'b>]), ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let program = next_account_info(accounts_iter)?;
+        let canvas_config = next_account_info(accounts_iter)?;
+        let canvas_stats_shard = next_account_info(accounts_iter)?;
+        let system_program = next_account_info(accounts_iter)?;
+        let pixel_account = next_account_info(accounts_iter)?;
+        let fee_vault = next_account_info(accounts_iter)?;
+        let trade_pool = next_account_info(accounts_iter)?;
+        let pixel_owner = next_account_info(accounts_iter)?;
+        let buyer_wallet = next_account_info(accounts_iter)?;
+
+        check_accounts(&[
+            ("program", program, &[Address(program_id, ProgramKeyMismatch)]),
+            ("canvas_config", canvas_config, &[Owner(program_id, CanvasConfigKeyMismatch)]),
+            ("canvas_stats_shard", canvas_stats_shard, &[Owner(program_id, CanvasStatsKeyMismatch), Writable]),
+            ("system_program", system_program, &[Address(&system_program::id(), SystemProgramKeyMismatch)]),
+            ("pixel_account", pixel_account, &[Writable]),
+            ("fee_vault", fee_vault, &[Writable]),
+            ("trade_pool", trade_pool, &[Writable]),
+            ("pixel_owner", pixel_owner, &[Writable]),
+            ("buyer_wallet", buyer_wallet, &[Signer(BuyerDidNotSign), Writable]),
+        ])?;
+
+        Ok((BuyPixelSolAccounts {
+            program,
+            canvas_config,
+            canvas_stats_shard,
+            system_program,
+            pixel_account,
+            fee_vault,
+            trade_pool,
+            pixel_owner,
+            buyer_wallet,
+        }, accounts_iter.as_slice()))
+    }
+}
+
+/// Accounts of `ListPixel`, `UnlistPixel`, `ListPixelSol` and `UnlistPixelSol`.
+pub struct ListPixelAccounts<'a, 'b> {
+    pub program: &'a AccountInfo<'b>,
+    pub canvas_config: &'a AccountInfo<'b>,
+    pub canvas_stats_shard: &'a AccountInfo<'b>,
+    pub pixel_account: &'a AccountInfo<'b>,
+    pub pixel_owner: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> ListPixelAccounts<'a, 'b> {
+    pub const ROLES: &'static [&'static str] = &[
+        "program", "canvas_config", "canvas_stats_shard", "pixel_account", "pixel_owner",
+    ];
+
+    pub fn load(program_id: &Pubkey, accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let program = next_account_info(accounts_iter)?;
+        let canvas_config = next_account_info(accounts_iter)?;
+        let canvas_stats_shard = next_account_info(accounts_iter)?;
+        let pixel_account = next_account_info(accounts_iter)?;
+        let pixel_owner = next_account_info(accounts_iter)?;
+
+        check_accounts(&[
+            ("program", program, &[Address(program_id, ProgramKeyMismatch)]),
+            ("canvas_config", canvas_config, &[Owner(program_id, CanvasConfigKeyMismatch)]),
+            ("canvas_stats_shard", canvas_stats_shard, &[Owner(program_id, CanvasStatsKeyMismatch), Writable]),
+            ("pixel_account", pixel_account, &[Writable]),
+            ("pixel_owner", pixel_owner, &[Signer(PixelOwnerDidNotSign)]),
+        ])?;
+
+        Ok(ListPixelAccounts { program, canvas_config, canvas_stats_shard, pixel_account, pixel_owner })
+    }
+}
+
+pub struct CancelBidAccounts<'a, 'b> {
+    pub program: &'a AccountInfo<'b>,
+    pub canvas_config: &'a AccountInfo<'b>,
+    pub canvas_stats_shard: &'a AccountInfo<'b>,
+    pub token_program: &'a AccountInfo<'b>,
+    pub token_mint: &'a AccountInfo<'b>,
+    pub system_program: &'a AccountInfo<'b>,
+    pub rent_sysvar: &'a AccountInfo<'b>,
+    pub associated_token_program: &'a AccountInfo<'b>,
+    pub pixel_account: &'a AccountInfo<'b>,
+    pub trade_pool: &'a AccountInfo<'b>,
+    pub trade_pool_token_account: &'a AccountInfo<'b>,
+    pub buyer_wallet: &'a AccountInfo<'b>,
+    pub buyer_token_account: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> CancelBidAccounts<'a, 'b> {
+    pub const ROLES: &'static [&'static str] = &[
+        "program", "canvas_config", "canvas_stats_shard", "token_program", "token_mint", "system_program",
+        "rent_sysvar", "associated_token_program", "pixel_account", "trade_pool", "trade_pool_token_account",
+        "buyer_wallet", "buyer_token_account",
+    ];
+
+    pub fn load(program_id: &Pubkey, accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let program = next_account_info(accounts_iter)?;
+        let canvas_config = next_account_info(accounts_iter)?;
+        let canvas_stats_shard = next_account_info(accounts_iter)?;
+        let token_program = next_account_info(accounts_iter)?;
+        let token_mint = next_account_info(accounts_iter)?;
+        let system_program = next_account_info(accounts_iter)?;
+        let rent_sysvar = next_account_info(accounts_iter)?;
+        let associated_token_program = next_account_info(accounts_iter)?;
+        let pixel_account = next_account_info(accounts_iter)?;
+        let trade_pool = next_account_info(accounts_iter)?;
+        let trade_pool_token_account = next_account_info(accounts_iter)?;
+        let buyer_wallet = next_account_info(accounts_iter)?;
+        let buyer_token_account = next_account_info(accounts_iter)?;
+
+        check_accounts(&[
+            ("program", program, &[Address(program_id, ProgramKeyMismatch)]),
+            ("canvas_config", canvas_config, &[Owner(program_id, CanvasConfigKeyMismatch)]),
+            ("canvas_stats_shard", canvas_stats_shard, &[Owner(program_id, CanvasStatsKeyMismatch), Writable]),
+            ("token_program", token_program, &[Key(token::is_token_program, TokenProgramKeyMismatch)]),
+            ("token_mint", token_mint, &[Address(&config::token_mint::id(), TokenMintKeyMismatch), Owner(token_program.key, TokenMintKeyMismatch)]),
+            ("system_program", system_program, &[Address(&system_program::id(), SystemProgramKeyMismatch)]),
+            ("rent_sysvar", rent_sysvar, &[Address(&sysvar::rent::id(), RentSysvarKeyMismatch)]),
+            ("associated_token_program", associated_token_program, &[Address(&spl_associated_token_account::id(), AssociatedTokenProgramKeyMismatch)]),
+            ("pixel_account", pixel_account, &[Writable]),
+            ("trade_pool_token_account", trade_pool_token_account, &[
+                TokenAccount(trade_pool.key, TradePoolTokenAccountKeyMismatch), Writable]),
+            ("buyer_wallet", buyer_wallet, &[Signer(BidderDidNotSign), Writable]),
+            ("buyer_token_account", buyer_token_account, &[
+                TokenAccount(buyer_wallet.key, BuyerTokenAccountKeyMismatch), Writable]),
+        ])?;
+
+        Ok(CancelBidAccounts {
+            program,
+            canvas_config,
+            canvas_stats_shard,
+            token_program,
+            token_mint,
+            system_program,
+            rent_sysvar,
+            associated_token_program,
+            pixel_account,
+            trade_pool,
+            trade_pool_token_account,
+            buyer_wallet,
+            buyer_token_account,
+        })
+    }
+}
+
+pub struct CancelSolBidAccounts<'a, 'b> {
+    pub program: &'a AccountInfo<'b>,
+    pub canvas_config: &'a AccountInfo<'b>,
+    pub canvas_stats_shard: &'a AccountInfo<'b>,
+    pub system_program: &'a AccountInfo<'b>,
+    pub pixel_account: &'a AccountInfo<'b>,
+    pub trade_pool: &'a AccountInfo<'b>,
+    pub buyer_wallet: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> CancelSolBidAccounts<'a, 'b> {
+    pub const ROLES: &'static [&'static str] = &[
+        "program", "canvas_config", "canvas_stats_shard", "system_program", "pixel_account", "trade_pool",
+        "buyer_wallet",
+    ];
+
+    pub fn load(program_id: &Pubkey, accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let program = next_account_info(accounts_iter)?;
+        let canvas_config = next_account_info(accounts_iter)?;
+        let canvas_stats_shard = next_account_info(accounts_iter)?;
+        let system_program = next_account_info(accounts_iter)?;
+        let pixel_account = next_account_info(accounts_iter)?;
+        let trade_pool = next_account_info(accounts_iter)?;
+        let buyer_wallet = next_account_info(accounts_iter)?;
+
+        check_accounts(&[
+            ("program", program, &[Address(program_id, ProgramKeyMismatch)]),
+            ("canvas_config", canvas_config, &[Owner(program_id, CanvasConfigKeyMismatch)]),
+            ("canvas_stats_shard", canvas_stats_shard, &[Owner(program_id, CanvasStatsKeyMismatch), Writable]),
+            ("system_program", system_program, &[Address(&system_program::id(), SystemProgramKeyMismatch)]),
+            ("pixel_account", pixel_account, &[Writable]),
+            ("trade_pool", trade_pool, &[Writable]),
+            ("buyer_wallet", buyer_wallet, &[Signer(BidderDidNotSign), Writable]),
+        ])?;
+
+        Ok(CancelSolBidAccounts {
+            program,
+            canvas_config,
+            canvas_stats_shard,
+            system_program,
+            pixel_account,
+            trade_pool,
+            buyer_wallet,
+        })
+    }
+}
+
+pub struct InitializeConfigAccounts<'a, 'b> {
+    pub program: &'a AccountInfo<'b>,
+    pub system_program: &'a AccountInfo<'b>,
+    pub rent_sysvar: &'a AccountInfo<'b>,
+    pub canvas_config: &'a AccountInfo<'b>,
+    pub canvas_stats: &'a AccountInfo<'b>,
+    pub fee_vault: &'a AccountInfo<'b>,
+    pub trade_pool: &'a AccountInfo<'b>,
+    pub admin_wallet: &'a AccountInfo<'b>,
+    pub canvas_config_bump_seed: u8,
+    pub canvas_stats_bump_seed: u8,
+    pub trade_pool_bump_seed: u8,
+    pub fee_vault_bump_seed: u8,
+}
+
+impl<'a, 'b> InitializeConfigAccounts<'a, 'b> {
+    pub const ROLES: &'static [&'static str] = &[
+        "program", "system_program", "rent_sysvar", "canvas_config", "canvas_stats", "fee_vault", "trade_pool",
+        "admin_wallet",
+    ];
+
+    pub fn load(program_id: &Pubkey, accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let program = next_account_info(accounts_iter)?;
+        let system_program = next_account_info(accounts_iter)?;
+        let rent_sysvar = next_account_info(accounts_iter)?;
+        let canvas_config = next_account_info(accounts_iter)?;
+        let canvas_stats = next_account_info(accounts_iter)?;
+        let fee_vault = next_account_info(accounts_iter)?;
+        let trade_pool = next_account_info(accounts_iter)?;
+        let admin_wallet = next_account_info(accounts_iter)?;
+
+        let (canvas_config_id, canvas_config_bump_seed) = config::get_canvas_config(program_id);
+        let (canvas_stats_id, canvas_stats_bump_seed) = config::get_canvas_stats(program_id);
+        // (Stored in the canvas config, the other instructions check the trade pool and fee vault addresses with them)
+        let (trade_pool_id, trade_pool_bump_seed) = config::get_trade_pool(program_id);
+        let (fee_vault_id, fee_vault_bump_seed) = config::get_fee_vault(program_id);
+        check_accounts(&[
+            ("program", program, &[Address(program_id, ProgramKeyMismatch)]),
+            ("system_program", system_program, &[Address(&system_program::id(), SystemProgramKeyMismatch)]),
+            ("rent_sysvar", rent_sysvar, &[Address(&sysvar::rent::id(), RentSysvarKeyMismatch)]),
+            ("canvas_config", canvas_config, &[Address(&canvas_config_id, CanvasConfigKeyMismatch), Writable]),
+            ("canvas_stats", canvas_stats, &[Address(&canvas_stats_id, CanvasStatsKeyMismatch), Writable]),
+            ("fee_vault", fee_vault, &[Address(&fee_vault_id, FeeVaultKeyMismatch), Writable]),
+            ("trade_pool", trade_pool, &[Address(&trade_pool_id, TradePoolKeyMismatch), Writable]),
+            ("admin_wallet", admin_wallet, &[Address(&config::admin_wallet::id(), AdminKeyMismatch), Signer(AdminDidNotSign), Writable]),
+        ])?;
+
+        Ok(InitializeConfigAccounts {
+            program,
+            system_program,
+            rent_sysvar,
+            canvas_config,
+            canvas_stats,
+            fee_vault,
+            trade_pool,
+            admin_wallet,
+            canvas_config_bump_seed,
+            canvas_stats_bump_seed,
+            trade_pool_bump_seed,
+            fee_vault_bump_seed,
+        })
+    }
+}
+
+/// Accounts of `InitializeStats`, for canvases initialized before the stats account existed.
+pub struct InitializeStatsAccounts<'a, 'b> {
+    pub program: &'a AccountInfo<'b>,
+    pub system_program: &'a AccountInfo<'b>,
+    pub rent_sysvar: &'a AccountInfo<'b>,
+    pub canvas_config: &'a AccountInfo<'b>,
+    pub canvas_stats: &'a AccountInfo<'b>,
+    pub admin_wallet: &'a AccountInfo<'b>,
+    pub canvas_stats_bump_seed: u8,
+}
+
+impl<'a, 'b> InitializeStatsAccounts<'a, 'b> {
+    pub const ROLES: &'static [&'static str] = &[
+        "program", "system_program", "rent_sysvar", "canvas_config", "canvas_stats", "admin_wallet",
+    ];
+
+    pub fn load(program_id: &Pubkey, accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let program = next_account_info(accounts_iter)?;
+        let system_program = next_account_info(accounts_iter)?;
+        let rent_sysvar = next_account_info(accounts_iter)?;
+        let canvas_config = next_account_info(accounts_iter)?;
+        let canvas_stats = next_account_info(accounts_iter)?;
+        let admin_wallet = next_account_info(accounts_iter)?;
+
+        let (canvas_stats_id, canvas_stats_bump_seed) = config::get_canvas_stats(program_id);
+        check_accounts(&[
+            ("program", program, &[Address(program_id, ProgramKeyMismatch)]),
+            ("system_program", system_program, &[Address(&system_program::id(), SystemProgramKeyMismatch)]),
+            ("rent_sysvar", rent_sysvar, &[Address(&sysvar::rent::id(), RentSysvarKeyMismatch)]),
+            ("canvas_config", canvas_config, &[Owner(program_id, CanvasConfigKeyMismatch)]),
+            ("canvas_stats", canvas_stats, &[Address(&canvas_stats_id, CanvasStatsKeyMismatch), Writable]),
+            ("admin_wallet", admin_wallet, &[Signer(AdminDidNotSign), Writable]),
+        ])?;
+
+        Ok(InitializeStatsAccounts {
+            program,
+            system_program,
+            rent_sysvar,
+            canvas_config,
+            canvas_stats,
+            admin_wallet,
+            canvas_stats_bump_seed,
+        })
+    }
+}
+
+pub struct InitializeStatsShardAccounts<'a, 'b> {
+    pub program: &'a AccountInfo<'b>,
+    pub system_program: &'a AccountInfo<'b>,
+    pub rent_sysvar: &'a AccountInfo<'b>,
+    pub canvas_config: &'a AccountInfo<'b>,
+    pub canvas_stats_shard: &'a AccountInfo<'b>,
+    pub admin_wallet: &'a AccountInfo<'b>,
+    pub canvas_stats_shard_bump_seed: u8,
+}
+
+impl<'a, 'b> InitializeStatsShardAccounts<'a, 'b> {
+    pub const ROLES: &'static [&'static str] = &[
+        "program", "system_program", "rent_sysvar", "canvas_config", "canvas_stats_shard", "admin_wallet",
+    ];
+
+    pub fn load(program_id: &Pubkey, accounts: &'a [AccountInfo<'b>], shard: u8) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let program = next_account_info(accounts_iter)?;
+        let system_program = next_account_info(accounts_iter)?;
+        let rent_sysvar = next_account_info(accounts_iter)?;
+        let canvas_config = next_account_info(accounts_iter)?;
+        let canvas_stats_shard = next_account_info(accounts_iter)?;
+        let admin_wallet = next_account_info(accounts_iter)?;
+
+        let (canvas_stats_shard_id, canvas_stats_shard_bump_seed) = config::get_canvas_stats_shard(program_id, shard);
+        check_accounts(&[
+            ("program", program, &[Address(program_id, ProgramKeyMismatch)]),
+            ("system_program", system_program, &[Address(&system_program::id(), SystemProgramKeyMismatch)]),
+            ("rent_sysvar", rent_sysvar, &[Address(&sysvar::rent::id(), RentSysvarKeyMismatch)]),
+            ("canvas_config", canvas_config, &[Owner(program_id, CanvasConfigKeyMismatch)]),
+            ("canvas_stats_shard", canvas_stats_shard, &[Address(&canvas_stats_shard_id, CanvasStatsKeyMismatch), Writable]),
+            ("admin_wallet", admin_wallet, &[Signer(AdminDidNotSign), Writable]),
+        ])?;
+
+        Ok(InitializeStatsShardAccounts {
+            program,
+            system_program,
+            rent_sysvar,
+            canvas_config,
+            canvas_stats_shard,
+            admin_wallet,
+            canvas_stats_shard_bump_seed,
+        })
+    }
+}
+
+/// Accounts of the admin instructions that only update the canvas config.
+pub struct SetConfigAccounts<'a, 'b> {
+    pub program: &'a AccountInfo<'b>,
+    pub canvas_config: &'a AccountInfo<'b>,
+    pub admin_wallet: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> SetConfigAccounts<'a, 'b> {
+    pub const ROLES: &'static [&'static str] = &["program", "canvas_config", "admin_wallet"];
+
+    pub fn load(program_id: &Pubkey, accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let program = next_account_info(accounts_iter)?;
+        let canvas_config = next_account_info(accounts_iter)?;
+        let admin_wallet = next_account_info(accounts_iter)?;
+
+        check_accounts(&[
+            ("program", program, &[Address(program_id, ProgramKeyMismatch)]),
+            ("canvas_config", canvas_config, &[Owner(program_id, CanvasConfigKeyMismatch), Writable]),
+            ("admin_wallet", admin_wallet, &[Signer(AdminDidNotSign)]),
+        ])?;
+
+        Ok(SetConfigAccounts { program, canvas_config, admin_wallet })
+    }
+}
+
+pub struct WithdrawFeesAccounts<'a, 'b> {
+    pub program: &'a AccountInfo<'b>,
+    pub canvas_config: &'a AccountInfo<'b>,
+    pub system_program: &'a AccountInfo<'b>,
+    pub token_program: &'a AccountInfo<'b>,
+    pub token_mint: &'a AccountInfo<'b>,
+    pub fee_vault: &'a AccountInfo<'b>,
+    pub fee_vault_token_account: &'a AccountInfo<'b>,
+    pub admin_wallet: &'a AccountInfo<'b>,
+    pub destination_wallet: &'a AccountInfo<'b>,
+    pub destination_token_account: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> WithdrawFeesAccounts<'a, 'b> {
+    pub const ROLES: &'static [&'static str] = &[
+        "program", "canvas_config", "system_program", "token_program", "token_mint", "fee_vault",
+        "fee_vault_token_account", "admin_wallet", "destination_wallet", "destination_token_account",
+    ];
+
+    pub fn load(program_id: &Pubkey, accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let program = next_account_info(accounts_iter)?;
+        let canvas_config = next_account_info(accounts_iter)?;
+        let system_program = next_account_info(accounts_iter)?;
+        let token_program = next_account_info(accounts_iter)?;
+        let token_mint = next_account_info(accounts_iter)?;
+        let fee_vault = next_account_info(accounts_iter)?;
+        let fee_vault_token_account = next_account_info(accounts_iter)?;
+        let admin_wallet = next_account_info(accounts_iter)?;
+        let destination_wallet = next_account_info(accounts_iter)?;
+        let destination_token_account = next_account_info(accounts_iter)?;
+
+        check_accounts(&[
+            ("program", program, &[Address(program_id, ProgramKeyMismatch)]),
+            ("canvas_config", canvas_config, &[Owner(program_id, CanvasConfigKeyMismatch)]),
+            ("system_program", system_program, &[Address(&system_program::id(), SystemProgramKeyMismatch)]),
+            ("token_program", token_program, &[Key(token::is_token_program, TokenProgramKeyMismatch)]),
+            ("token_mint", token_mint, &[Address(&config::token_mint::id(), TokenMintKeyMismatch), Owner(token_program.key, TokenMintKeyMismatch)]),
+            ("fee_vault", fee_vault, &[Writable]),
+            ("fee_vault_token_account", fee_vault_token_account, &[
+                TokenAccount(fee_vault.key, FeeVaultTokenAccountKeyMismatch), Writable]),
+            ("admin_wallet", admin_wallet, &[Signer(AdminDidNotSign)]),
+            ("destination_wallet", destination_wallet, &[Writable]),
+            ("destination_token_account", destination_token_account, &[Writable]),
+        ])?;
+
+        Ok(WithdrawFeesAccounts {
+            program,
+            canvas_config,
+            system_program,
+            token_program,
+            token_mint,
+            fee_vault,
+            fee_vault_token_account,
+            admin_wallet,
+            destination_wallet,
+            destination_token_account,
+        })
+    }
+}
+
+/// Accounts of the token bidder to refund, read after the accounts of the instruction.
+pub struct PreviousBuyerAccounts<'a, 'b> {
+    pub previous_buyer_token_account: &'a AccountInfo<'b>,
+    pub previous_buyer_wallet: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> PreviousBuyerAccounts<'a, 'b> {
+    pub const ROLES: &'static [&'static str] = &["previous_buyer_token_account", "previous_buyer_wallet"];
+
+    pub fn load(accounts_iter: &mut Iter<'a, AccountInfo<'b>>, buyer_wallet: &Pubkey) -> Result<Self, ProgramError> {
+        let previous_buyer_token_account = next_account_info(accounts_iter)?;
+        let previous_buyer_wallet = next_account_info(accounts_iter)?;
+
+        check_accounts(&[
+            ("previous_buyer_wallet", previous_buyer_wallet, &[Address(buyer_wallet, PreviousBuyerKeyMismatch)]),
+            ("previous_buyer_token_account", previous_buyer_token_account, &[
+                TokenAccount(buyer_wallet, PreviousBuyerTokenAccountKeyMismatch), Writable]),
+        ])?;
+
+        Ok(PreviousBuyerAccounts {
+            previous_buyer_token_account,
+            previous_buyer_wallet,
+        })
+    }
+}
+
+/// Account of the SOL bidder to refund, read after the accounts of the instruction.
+pub struct PreviousSolBuyerAccounts<'a, 'b> {
+    pub previous_sol_buyer_wallet: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> PreviousSolBuyerAccounts<'a, 'b> {
+    pub const ROLES: &'static [&'static str] = &["previous_sol_buyer_wallet"];
+
+    pub fn load(accounts_iter: &mut Iter<'a, AccountInfo<'b>>, buyer_wallet: &Pubkey) -> Result<Self, ProgramError> {
+        let previous_sol_buyer_wallet = next_account_info(accounts_iter)?;
+
+        check_accounts(&[
+            ("previous_sol_buyer_wallet", previous_sol_buyer_wallet, &[Address(buyer_wallet, PreviousBuyerKeyMismatch), Writable]),
+        ])?;
+
+        Ok(PreviousSolBuyerAccounts {
+            previous_sol_buyer_wallet,
+        })
+    }
+}
@@ -0,0 +1,100 @@
+//! Merkle tree of the wallets allowed to mint during the presale
+//!
+//! Leaves are the hashes of the wallets. Pairs are hashed in sorted order,
+//! so a proof is just the list of sibling hashes from the leaf up to the root.
+
+use solana_program::hash::hashv;
+use solana_program::pubkey::Pubkey;
+
+use crate::error::NftCanvasError;
+
+pub const HASH_LEN: usize = 32;
+
+// (Prefixes keep a leaf from being passed off as an inner node)
+const LEAF_PREFIX: &[u8] = &[0];
+const NODE_PREFIX: &[u8] = &[1];
+
+pub fn leaf(wallet: &Pubkey) -> [u8; HASH_LEN] {
+    hashv(&[LEAF_PREFIX, wallet.as_ref()]).to_bytes()
+}
+
+fn hash_pair(a: &[u8; HASH_LEN], b: &[u8; HASH_LEN]) -> [u8; HASH_LEN] {
+    if a <= b {
+        hashv(&[NODE_PREFIX, a, b]).to_bytes()
+    } else {
+        hashv(&[NODE_PREFIX, b, a]).to_bytes()
+    }
+}
+
+pub fn verify(root: &[u8; HASH_LEN], wallet: &Pubkey, proof: &[[u8; HASH_LEN]]) -> bool {
+    let mut hash = leaf(wallet);
+    for sibling in proof {
+        hash = hash_pair(&hash, sibling);
+    }
+    &hash == root
+}
+
+/// Splits the hashes appended after an instruction.
+pub fn unpack_hashes(data: &[u8]) -> Result<Vec<[u8; HASH_LEN]>, NftCanvasError> {
+    let chunks = data.chunks_exact(HASH_LEN);
+    if !chunks.remainder().is_empty() {
+        return Err(NftCanvasError::FailedToUnpackData);
+    }
+    Ok(chunks
+        .map(|chunk| {
+            let mut hash = [0u8; HASH_LEN];
+            hash.copy_from_slice(chunk);
+            hash
+        })
+        .collect())
+}
+
+pub fn pack_hashes(hashes: &[[u8; HASH_LEN]]) -> Vec<u8> {
+    hashes.iter().flat_map(|hash| hash.iter().copied()).collect()
+}
+
+/// Client side tree, to compute the root stored in the canvas config and the proofs passed to `MintPixel`.
+pub struct AllowlistTree {
+    // (Leaves first, root last)
+    layers: Vec<Vec<[u8; HASH_LEN]>>,
+}
+
+impl AllowlistTree {
+    pub fn new(wallets: &[Pubkey]) -> AllowlistTree {
+        let mut leaves: Vec<[u8; HASH_LEN]> = wallets.iter().map(leaf).collect();
+        leaves.sort_unstable();
+        leaves.dedup();
+
+        let mut layers = vec![leaves];
+        while layers.last().unwrap().len() > 1 {
+            let layer = layers.last().unwrap();
+            let parents = layer.chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => hash_pair(a, b),
+                    // (An odd node out moves up unchanged)
+                    _ => pair[0],
+                })
+                .collect();
+            layers.push(parents);
+        }
+        AllowlistTree { layers }
+    }
+
+    /// All zeros for an empty allowlist, which no proof verifies against.
+    pub fn root(&self) -> [u8; HASH_LEN] {
+        self.layers.last().unwrap().first().copied().unwrap_or([0u8; HASH_LEN])
+    }
+
+    /// `None` if `wallet` is not in the allowlist.
+    pub fn proof(&self, wallet: &Pubkey) -> Option<Vec<[u8; HASH_LEN]>> {
+        let mut position = self.layers[0].binary_search(&leaf(wallet)).ok()?;
+        let mut proof = Vec::new();
+        for layer in &self.layers[..self.layers.len() - 1] {
+            if let Some(sibling) = layer.get(position ^ 1) {
+                proof.push(*sibling);
+            }
+            position /= 2;
+        }
+        Some(proof)
+    }
+}
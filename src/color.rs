@@ -0,0 +1,61 @@
+//! Pixel colors and canvas color modes
+//!
+//! Pixels store an RGBA color whatever the mode. Mint and paint instructions have a variant per
+//! kind of `PixelColor`, which `state::CanvasConfig::resolve_color` checks against the canvas color
+//! mode and resolves to RGBA.
+//! The palette of `ColorMode::Palette` follows the `SetColorMode` instruction, 3 bytes per color.
+
+use borsh::BorshDeserialize;
+use borsh::BorshSchema;
+use borsh::BorshSerialize;
+
+use crate::error::NftCanvasError;
+
+/// Most colors a palette can have.
+pub const PALETTE_SIZE: usize = 32;
+pub const OPAQUE: u8 = 255;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, BorshSerialize, BorshDeserialize, BorshSchema)]
+pub enum ColorMode {
+    /// Opaque colors.
+    Rgb,
+    /// Colors with transparency, for overlays. Opaque colors are accepted too.
+    Rgba,
+    /// Only the colors of the canvas palette, by index.
+    Palette,
+}
+
+// (Not derived: `#[default]` variants are too recent for the BPF toolchain)
+#[allow(clippy::derivable_impls)]
+impl Default for ColorMode {
+    fn default() -> Self {
+        ColorMode::Rgb
+    }
+}
+
+/// The color of a mint or paint instruction, whose variant tells the color mode it is meant for.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PixelColor {
+    Rgb([u8; 3]),
+    Rgba([u8; 4]),
+    Palette(u8),
+}
+
+/// Adds the opaque alpha to an RGB color.
+pub fn opaque(rgb: [u8; 3]) -> [u8; 4] {
+    [rgb[0], rgb[1], rgb[2], OPAQUE]
+}
+
+pub fn unpack_palette(data: &[u8]) -> Result<Vec<[u8; 3]>, NftCanvasError> {
+    let chunks = data.chunks_exact(3);
+    if !chunks.remainder().is_empty() {
+        return Err(NftCanvasError::FailedToUnpackData);
+    }
+    Ok(chunks.map(|chunk| [chunk[0], chunk[1], chunk[2]]).collect())
+}
+
+pub fn pack_palette(palette: &[[u8; 3]]) -> Vec<u8> {
+    palette.iter().flat_map(|color| color.iter().copied()).collect()
+}
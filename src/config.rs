@@ -1,8 +1,12 @@
-use solana_program::pubkey::Pubkey;
+use solana_program::pubkey::{Pubkey, PubkeyError};
 use spl_associated_token_account;
 
-pub const PIXEL_COUNT: u32 = 1000 * 1000; // width * height
-pub const MINT_COST: u64 = 1_000_000; // 0.001 Sol
+use crate::token::TransferFee;
+
+pub const CANVAS_WIDTH: u32 = 1000;
+pub const CANVAS_HEIGHT: u32 = 1000;
+pub const PIXEL_COUNT: u32 = CANVAS_WIDTH * CANVAS_HEIGHT; // (Pixels are indexed row by row)
+pub const MINT_COST: u64 = 1_000_000; // 0.001 Sol, base mint price of a new canvas config
 pub const TAX_DIV: u64 = 100; // 1%
 
 pub mod token_mint {
@@ -11,18 +15,118 @@ pub mod token_mint {
     declare_id!("8s9FCz99Wcr3dHpiauFRi6bLXzshXfcGTfgQE7UEopVx");
 }
 
-pub mod mint_pool_wallet {
+pub mod token_2022_program {
     use solana_program::declare_id;
 
-    declare_id!("ARamwbZzoaRjiEnHM2oVmD5bqPpGPNuxUuXWRzsacgaz");
+    declare_id!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
 }
 
-pub mod team_token_account {
+/// Allowed to initialize the canvas config account, which then holds the admin.
+pub mod admin_wallet {
     use solana_program::declare_id;
 
-    declare_id!("Esi6Z7reZt9NjZ2TeTFRXcTez1XA7764dE9bZoKCdjTb");
+    declare_id!("ARamwbZzoaRjiEnHM2oVmD5bqPpGPNuxUuXWRzsacgaz");
+}
+
+#[inline(always)]
+pub fn get_canvas_config(program: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[
+        &program.to_bytes(),
+        "config".as_bytes(),
+    ], program)
+}
+
+#[macro_export]
+macro_rules! canvas_config_seeds {
+    ($program:expr, $bump_seed:expr) => {
+        &[
+            $program.as_ref(),
+            "config".as_bytes(),
+            &[$bump_seed],
+        ]
+    };
+}
+
+#[inline(always)]
+pub fn get_canvas_stats(program: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[
+        &program.to_bytes(),
+        "stats".as_bytes(),
+    ], program)
+}
+
+#[macro_export]
+macro_rules! canvas_stats_seeds {
+    ($program:expr, $bump_seed:expr) => {
+        &[
+            $program.as_ref(),
+            "stats".as_bytes(),
+            &[$bump_seed],
+        ]
+    };
+}
+
+/// Number of `get_canvas_stats_shard` accounts.
+pub const STATS_SHARDS: u32 = 16;
+
+/// The stats shard counting the pixel at `index`.
+pub fn stats_shard(index: u32) -> u8 {
+    (index % STATS_SHARDS) as u8
 }
 
+/// Market stats of the pixels of one `stats_shard`, created by `InitializeStatsShard`.
+#[inline(always)]
+pub fn get_canvas_stats_shard(program: &Pubkey, shard: u8) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[
+        &program.to_bytes(),
+        "stats".as_bytes(),
+        &[shard],
+    ], program)
+}
+
+#[macro_export]
+macro_rules! canvas_stats_shard_seeds {
+    ($program:expr, $shard:expr, $bump_seed:expr) => {
+        &[
+            $program.as_ref(),
+            "stats".as_bytes(),
+            &[$shard],
+            &[$bump_seed],
+        ]
+    };
+}
+
+/// Collects mint fees and SOL trade fees, while its token account collects token trade fees.
+/// `InitializeConfig` funds it with the rent exempt minimum of an empty account, which `WithdrawFees` leaves in it.
+/// (Canvases initialized before that need the reserve sent to it with a plain transfer)
+#[inline(always)]
+pub fn get_fee_vault(program: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[
+        &program.to_bytes(),
+        "fee_vault".as_bytes(),
+    ], program)
+}
+
+#[macro_export]
+macro_rules! fee_vault_seeds {
+    ($program:expr, $bump_seed:expr) => {
+        &[
+            $program.as_ref(),
+            "fee_vault".as_bytes(),
+            &[$bump_seed],
+        ]
+    };
+}
+
+/// Same as `get_fee_vault`, from the bump seed stored in the canvas config.
+#[inline(always)]
+pub fn create_fee_vault_address(program: &Pubkey, bump_seed: u8) -> Result<Pubkey, PubkeyError> {
+    Pubkey::create_program_address(fee_vault_seeds!(program, bump_seed), program)
+}
+
+/// The trade pool also escrows SOL bids directly as lamports, on top of the rent exempt minimum of an empty
+/// account that `InitializeConfig` funds it with. Bids only ever pay out what they escrowed, so the reserve stays.
+/// (Canvases initialized before that need the reserve sent to it with a plain transfer)
 #[inline(always)]
 pub fn get_trade_pool(program: &Pubkey) -> (Pubkey, u8) {
     Pubkey::find_program_address(&[
@@ -42,6 +146,12 @@ macro_rules! trade_pool_seeds {
     };
 }
 
+/// Same as `get_trade_pool`, from the bump seed stored in the canvas config.
+#[inline(always)]
+pub fn create_trade_pool_address(program: &Pubkey, bump_seed: u8) -> Result<Pubkey, PubkeyError> {
+    Pubkey::create_program_address(trade_pool_seeds!(program, bump_seed), program)
+}
+
 #[inline(always)]
 pub fn get_pixel(program: &Pubkey, index: u32) -> (Pubkey, u8) {
     Pubkey::find_program_address(&[
@@ -63,8 +173,40 @@ macro_rules! pixel_seeds {
     };
 }
 
-pub fn get_token_account(owner: &Pubkey) -> Pubkey {
-    spl_associated_token_account::get_associated_token_address(owner, &token_mint::id())
+/// Same as `get_pixel`, from the bump seed stored in the pixel.
+#[inline(always)]
+pub fn create_pixel_address(program: &Pubkey, index: u32, bump_seed: u8) -> Result<Pubkey, PubkeyError> {
+    Pubkey::create_program_address(pixel_seeds!(program, index, bump_seed), program)
+}
+
+#[inline(always)]
+pub fn get_presale_receipt(program: &Pubkey, wallet: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[
+        &program.to_bytes(),
+        "presale".as_bytes(),
+        &wallet.to_bytes(),
+    ], program)
+}
+
+#[macro_export]
+macro_rules! presale_receipt_seeds {
+    ($program:expr, $wallet:expr, $bump_seed:expr) => {
+        &[
+            $program.as_ref(),
+            "presale".as_bytes(),
+            $wallet.as_ref(),
+            &[$bump_seed],
+        ]
+    };
+}
+
+/// Associated token account of `owner` for the token mint, under either token program.
+pub fn get_token_account(owner: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[
+        &owner.to_bytes(),
+        &token_program.to_bytes(),
+        &token_mint::id().to_bytes(),
+    ], &spl_associated_token_account::id()).0
 }
 
 pub struct TradeAmountSplit {
@@ -80,4 +222,14 @@ impl TradeAmountSplit {
             to_team,
         }
     }
+
+    /// Same as `split`, but the team share is grossed up so the team still receives its full tax
+    /// once the token's transfer fee is withheld. The seller's share absorbs the difference.
+    pub fn split_with_transfer_fee(amount: u64, transfer_fee: &TransferFee) -> TradeAmountSplit {
+        let to_team = transfer_fee.gross_up(amount / TAX_DIV).min(amount);
+        TradeAmountSplit {
+            to_seller: amount - to_team,
+            to_team,
+        }
+    }
 }
\ No newline at end of file
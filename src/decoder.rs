@@ -0,0 +1,122 @@
+//! Decodes program instructions for explorers and indexers
+//!
+//! Accounts are named by the `ROLES` of the `accounts` structs the processor loads them into.
+
+use solana_program::instruction::CompiledInstruction;
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+
+use crate::accounts::{
+    AcceptBidAccounts, AcceptSolBidAccounts, BuyPixelAccounts, BuyPixelSolAccounts, CancelBidAccounts, CancelSolBidAccounts,
+    ClosePixelAccounts, InitializeConfigAccounts, InitializeStatsAccounts, InitializeStatsShardAccounts, ListPixelAccounts,
+    MintPixelAccounts, PreviousBuyerAccounts, PreviousSolBuyerAccounts, SetColorDelegateAccounts, SetConfigAccounts,
+    UpdatePixelColorAccounts, WithdrawFeesAccounts,
+};
+use crate::allowlist;
+use crate::color;
+use crate::error::NftCanvasError;
+use crate::instruction::NftCanvasInstruction;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedAccount {
+    pub role: &'static str,
+    pub pubkey: Pubkey,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedInstruction {
+    pub program_id: Pubkey,
+    pub instruction: NftCanvasInstruction,
+    /// In instruction order. Accounts past the ones the instruction reads are named `unused`.
+    pub accounts: Vec<DecodedAccount>,
+    /// The allowlist proof of a `MintPixel`, or the allowlist root of a `SetPresale`.
+    pub hashes: Vec<[u8; allowlist::HASH_LEN]>,
+    /// The palette of a `SetColorMode`.
+    pub palette: Vec<[u8; 3]>,
+}
+
+impl DecodedInstruction {
+    pub fn account(&self, role: &str) -> Option<&Pubkey> {
+        self.accounts.iter().find(|account| account.role == role).map(|account| &account.pubkey)
+    }
+}
+
+/// The required accounts of `instruction`, and the optional accounts given `optional` more accounts.
+fn roles(instruction: &NftCanvasInstruction, optional: usize) -> (&'static [&'static str], Vec<&'static str>) {
+    let (required, optional): (_, &[&[&str]]) = match instruction {
+        NftCanvasInstruction::MintPixel { .. }
+        | NftCanvasInstruction::MintPixelRgba { .. }
+        | NftCanvasInstruction::MintPixelPalette { .. } => (MintPixelAccounts::ROLES, &[MintPixelAccounts::OPTIONAL_ROLES]),
+        NftCanvasInstruction::UpdatePixelColor { .. }
+        | NftCanvasInstruction::UpdatePixelColorRgba { .. }
+        | NftCanvasInstruction::UpdatePixelColorPalette { .. } => (UpdatePixelColorAccounts::ROLES, &[]),
+        NftCanvasInstruction::SellPixel { .. } | NftCanvasInstruction::AcceptBid { .. } => (AcceptBidAccounts::ROLES, &[]),
+        NftCanvasInstruction::BuyPixel { .. } => (BuyPixelAccounts::ROLES, &[PreviousBuyerAccounts::ROLES]),
+        NftCanvasInstruction::SetColorDelegate { .. } => {
+            (SetColorDelegateAccounts::ROLES, &[SetColorDelegateAccounts::OPTIONAL_ROLES])
+        }
+        // (The processor reads the accounts of the bids the pixel has, token bid first)
+        NftCanvasInstruction::ClosePixel { .. } => (ClosePixelAccounts::ROLES, match optional {
+            1 => &[PreviousSolBuyerAccounts::ROLES],
+            2 => &[PreviousBuyerAccounts::ROLES],
+            _ => &[PreviousBuyerAccounts::ROLES, PreviousSolBuyerAccounts::ROLES],
+        }),
+        NftCanvasInstruction::SellPixelSol { .. } | NftCanvasInstruction::AcceptSolBid { .. } => {
+            (AcceptSolBidAccounts::ROLES, &[])
+        }
+        NftCanvasInstruction::BuyPixelSol { .. } => (BuyPixelSolAccounts::ROLES, &[PreviousSolBuyerAccounts::ROLES]),
+        NftCanvasInstruction::ListPixel { .. }
+        | NftCanvasInstruction::UnlistPixel { .. }
+        | NftCanvasInstruction::ListPixelSol { .. }
+        | NftCanvasInstruction::UnlistPixelSol { .. } => (ListPixelAccounts::ROLES, &[]),
+        NftCanvasInstruction::CancelBid { .. } => (CancelBidAccounts::ROLES, &[]),
+        NftCanvasInstruction::CancelSolBid { .. } => (CancelSolBidAccounts::ROLES, &[]),
+        NftCanvasInstruction::InitializeConfig => (InitializeConfigAccounts::ROLES, &[]),
+        NftCanvasInstruction::SetPaused { .. }
+        | NftCanvasInstruction::SetPresale { .. }
+        | NftCanvasInstruction::SetMintCurve { .. }
+        | NftCanvasInstruction::SetColorMode { .. } => (SetConfigAccounts::ROLES, &[]),
+        NftCanvasInstruction::WithdrawFees => (WithdrawFeesAccounts::ROLES, &[]),
+        NftCanvasInstruction::InitializeStats { .. } => (InitializeStatsAccounts::ROLES, &[]),
+        NftCanvasInstruction::InitializeStatsShard { .. } => (InitializeStatsShardAccounts::ROLES, &[]),
+    };
+    (required, optional.concat())
+}
+
+/// Decodes an instruction the way `processor::process_instruction` reads it.
+pub fn decode(program_id: &Pubkey, accounts: &[Pubkey], data: &[u8]) -> Result<DecodedInstruction, ProgramError> {
+    let (instruction, trailing_data) = NftCanvasInstruction::unpack_from(data)?;
+    let (hashes, palette) = match instruction {
+        NftCanvasInstruction::MintPixel { .. }
+        | NftCanvasInstruction::MintPixelRgba { .. }
+        | NftCanvasInstruction::MintPixelPalette { .. }
+        | NftCanvasInstruction::SetPresale { .. } => {
+            (allowlist::unpack_hashes(trailing_data)?, Vec::new())
+        }
+        NftCanvasInstruction::SetColorMode { .. } => (Vec::new(), color::unpack_palette(trailing_data)?),
+        _ => if trailing_data.is_empty() {
+            (Vec::new(), Vec::new())
+        } else {
+            return Err(NftCanvasError::FailedToUnpackData.into());
+        }
+    };
+
+    let (required, _) = roles(&instruction, 0);
+    if accounts.len() < required.len() {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    let (_, optional) = roles(&instruction, accounts.len() - required.len());
+    let accounts = accounts.iter()
+        .zip(required.iter().chain(&optional).copied().chain(std::iter::repeat("unused")))
+        .map(|(pubkey, role)| DecodedAccount { role, pubkey: *pubkey })
+        .collect();
+    Ok(DecodedInstruction { program_id: *program_id, instruction, accounts, hashes, palette })
+}
+
+/// Decodes an instruction of a transaction message, whose accounts index into `account_keys`.
+pub fn decode_compiled(account_keys: &[Pubkey], instruction: &CompiledInstruction) -> Result<DecodedInstruction, ProgramError> {
+    let key = |index: u8| account_keys.get(index as usize).copied().ok_or(ProgramError::NotEnoughAccountKeys);
+    let program_id = key(instruction.program_id_index)?;
+    let accounts = instruction.accounts.iter().map(|index| key(*index)).collect::<Result<Vec<_>, _>>()?;
+    decode(&program_id, &accounts, &instruction.data)
+}
@@ -5,8 +5,10 @@
 use solana_program::account_info::AccountInfo;
 use solana_program::entrypoint;
 use solana_program::entrypoint::ProgramResult;
+use solana_program::program_error::PrintProgramError;
 use solana_program::pubkey::Pubkey;
 
+use crate::error::NftCanvasError;
 use crate::processor;
 
 entrypoint!(process_instruction);
@@ -16,5 +18,9 @@ fn process_instruction(
     accounts: &[AccountInfo],
     instruction_data: &[u8],
 ) -> ProgramResult {
-    processor::process_instruction(program_id, accounts, instruction_data)
+    if let Err(error) = processor::process_instruction(program_id, accounts, instruction_data) {
+        error.print::<NftCanvasError>();
+        return Err(error);
+    }
+    Ok(())
 }
\ No newline at end of file
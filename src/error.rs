@@ -1,53 +1,133 @@
 //! Error types
 
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
 use solana_program::decode_error::DecodeError;
-use solana_program::program_error::ProgramError;
+use solana_program::msg;
+use solana_program::program_error::{PrintProgramError, ProgramError};
 use thiserror::Error;
 
 /// Errors that may be returned by the program.
-#[derive(Clone, Debug, Eq, Error, PartialEq)]
+///
+/// The discriminants are the custom program error codes clients see, so they must never change:
+/// new variants take the next free code.
+#[derive(Clone, Copy, Debug, Eq, Error, FromPrimitive, PartialEq)]
 pub enum NftCanvasError {
-    #[error("ProgramKeyMismatch")]
-    ProgramKeyMismatch,
-    #[error("RentSysvarKeyMismatch")]
-    RentSysvarKeyMismatch,
-    #[error("TokenProgramKeyMismatch")]
-    TokenProgramKeyMismatch,
-    #[error("PixelAccountKeyMismatch")]
-    PixelAccountKeyMismatch,
-    #[error("MintPoolWalletKeyMismatch")]
-    MintPoolWalletKeyMismatch,
-    #[error("TeamWalletKeyMismatch")]
-    TeamWalletKeyMismatch,
-    #[error("TeamTokenAccountKeyMismatch")]
-    TeamTokenAccountKeyMismatch,
-    #[error("TradePoolKeyMismatch")]
-    TradePoolKeyMismatch,
-    #[error("TradePoolTokenAccountKeyMismatch")]
-    TradePoolTokenAccountKeyMismatch,
-    #[error("PixelOwnerKeyMismatch")]
-    PixelOwnerKeyMismatch,
-    #[error("BuyerTokenAccountKeyMismatch")]
-    BuyerTokenAccountKeyMismatch,
-
-    #[error("FunderDidNotSign")]
-    FunderDidNotSign,
-    #[error("PixelOwnerDidNotSign")]
-    PixelOwnerDidNotSign,
-
-    #[error("PixelIndexOutOfBounds")]
-    PixelIndexOutOfBounds,
-    #[error("PixelUninitialized")]
-    PixelUninitialized,
-    #[error("BuyPriceTooLow")]
-    BuyPriceTooLow,
-    #[error("CouldNotDirectBuy")]
-    CouldNotDirectBuy,
-
-    #[error("FailedToPackData")]
-    FailedToPackData,
-    #[error("FailedToUnpackData")]
-    FailedToUnpackData,
+    #[error("Program account does not match the program id")]
+    ProgramKeyMismatch = 0,
+    #[error("Rent sysvar account mismatch")]
+    RentSysvarKeyMismatch = 1,
+    #[error("Token program account mismatch")]
+    TokenProgramKeyMismatch = 2,
+    #[error("Pixel account does not match the pixel index")]
+    PixelAccountKeyMismatch = 3,
+    #[error("Mint pool wallet mismatch")]
+    MintPoolWalletKeyMismatch = 4,
+    #[error("Team wallet mismatch")]
+    TeamWalletKeyMismatch = 5,
+    #[error("Team token account mismatch")]
+    TeamTokenAccountKeyMismatch = 6,
+    #[error("Trade pool account mismatch")]
+    TradePoolKeyMismatch = 7,
+    #[error("Trade pool token account mismatch")]
+    TradePoolTokenAccountKeyMismatch = 8,
+    #[error("Wallet is not the pixel owner")]
+    PixelOwnerKeyMismatch = 9,
+    #[error("Buyer token account mismatch")]
+    BuyerTokenAccountKeyMismatch = 10,
+
+    #[error("Wallet paying for the new accounts did not sign")]
+    FunderDidNotSign = 11,
+    #[error("Pixel owner did not sign")]
+    PixelOwnerDidNotSign = 12,
+
+    #[error("Pixel index is outside of the canvas")]
+    PixelIndexOutOfBounds = 13,
+    #[error("Pixel is not initialized")]
+    PixelUninitialized = 14,
+    #[error("Price is lower than the best bid")]
+    BuyPriceTooLow = 15,
+    #[error("Pixel is not listed at or below the price")]
+    CouldNotDirectBuy = 16,
+
+    #[error("Failed to pack account data")]
+    FailedToPackData = 17,
+    #[error("Failed to unpack account or instruction data")]
+    FailedToUnpackData = 18,
+
+    #[error("Wallet is neither the pixel owner nor its color delegate")]
+    ColorAuthorityKeyMismatch = 19,
+    #[error("Color authority did not sign")]
+    ColorAuthorityDidNotSign = 20,
+    #[error("Previous buyer does not match the best bid")]
+    PreviousBuyerKeyMismatch = 21,
+    #[error("Token mint mismatch")]
+    TokenMintKeyMismatch = 22,
+    #[error("Associated token program mismatch")]
+    AssociatedTokenProgramKeyMismatch = 23,
+    #[error("List price is zero")]
+    ListPriceZero = 24,
+    #[error("No bid at or above the minimum price")]
+    NoAcceptableBid = 25,
+    #[error("Canvas config account mismatch")]
+    CanvasConfigKeyMismatch = 26,
+    #[error("Canvas config is not initialized")]
+    CanvasConfigUninitialized = 27,
+    #[error("Wallet is not the canvas admin")]
+    AdminKeyMismatch = 28,
+    #[error("Admin did not sign")]
+    AdminDidNotSign = 29,
+    #[error("Canvas is paused")]
+    Paused = 30,
+    #[error("Painting is paused")]
+    PaintingPaused = 31,
+    #[error("Pixel has no open bid")]
+    NoOpenBid = 32,
+    #[error("Wallet is not the bidder")]
+    BidderKeyMismatch = 33,
+    #[error("Bidder did not sign")]
+    BidderDidNotSign = 34,
+    #[error("Fee vault account mismatch")]
+    FeeVaultKeyMismatch = 35,
+    #[error("Fee vault token account mismatch")]
+    FeeVaultTokenAccountKeyMismatch = 36,
+    #[error("Presale receipt account mismatch")]
+    PresaleReceiptKeyMismatch = 37,
+    #[error("Wallet is not on the presale allowlist")]
+    NotOnAllowlist = 38,
+    #[error("Presale quota of the wallet is used up")]
+    PresaleQuotaExceeded = 39,
+    /// No longer returned: mints are priced from the canvas stats.
+    #[error("Mint counter account mismatch")]
+    MintCounterKeyMismatch = 40,
+    #[error("Canvas stats account mismatch")]
+    CanvasStatsKeyMismatch = 41,
+    #[error("Canvas stats are not initialized")]
+    CanvasStatsUninitialized = 42,
+    #[error("System program account mismatch")]
+    SystemProgramKeyMismatch = 43,
+    #[error("Pixel owner token account mismatch")]
+    PixelOwnerTokenAccountKeyMismatch = 44,
+    #[error("Buyer did not sign")]
+    BuyerDidNotSign = 45,
+    #[error("Account is not writable")]
+    AccountNotWritable = 46,
+    #[error("Pixel is not minted")]
+    PixelNotMinted = 47,
+    #[error("Pixel account is not owned by the program")]
+    PixelAccountOwnerMismatch = 48,
+    #[error("Pixel is already minted")]
+    PixelAlreadyMinted = 49,
+    #[error("Color does not match the canvas color mode")]
+    ColorModeMismatch = 50,
+    #[error("Color is not in the canvas palette")]
+    ColorNotInPalette = 51,
+    #[error("Palette does not match the color mode")]
+    InvalidPalette = 52,
+    #[error("Pixel account has the legacy layout, which cannot hold this: close and mint the pixel again")]
+    LegacyPixelLayout = 53,
+    #[error("Previous buyer token account mismatch")]
+    PreviousBuyerTokenAccountKeyMismatch = 54,
 }
 
 impl From<NftCanvasError> for ProgramError {
@@ -58,4 +138,20 @@ impl From<NftCanvasError> for ProgramError {
 
 impl<T> DecodeError<T> for NftCanvasError {
     fn type_of() -> &'static str { "NftCanvasError" }
-}
\ No newline at end of file
+}
+
+impl PrintProgramError for NftCanvasError {
+    fn print<E>(&self)
+    where
+        E: 'static + std::error::Error + DecodeError<E> + PrintProgramError + FromPrimitive,
+    {
+        msg!("Error: {} ({:?})", self, self);
+    }
+}
+
+impl NftCanvasError {
+    /// The error behind `ProgramError::Custom(code)`, if the code is one of ours.
+    pub fn from_code(code: u32) -> Option<NftCanvasError> {
+        NftCanvasError::from_u32(code)
+    }
+}
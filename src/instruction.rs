@@ -8,37 +8,117 @@ use solana_program::instruction::AccountMeta;
 use solana_program::instruction::Instruction;
 use solana_program::pubkey::Pubkey;
 
+use crate::allowlist;
+use crate::color;
+use crate::color::{ColorMode, PixelColor};
+use crate::config;
+use crate::error::NftCanvasError;
 use crate::packable::Packable;
 
+/// Instructions pack to `PACKED_SIZE` bytes, padded with zeros, except the few added since
+/// that need more: they pack to their own length. The mint instructions and `SetPresale` can be
+/// followed by 32 byte hashes: the allowlist proof and the allowlist root respectively.
+/// `SetColorMode` is followed by the 3 byte colors of the palette.
 #[repr(C)]
 #[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize, BorshSchema)]
 pub enum NftCanvasInstruction {
+    /// Mints with an opaque RGB color. See `MintPixelRgba` and `MintPixelPalette` for the other color modes.
     MintPixel { index: u32, color: [u8; 3], sell_price: u64 },
+    /// Paints with an opaque RGB color. See `UpdatePixelColorRgba` and `UpdatePixelColorPalette` for the other color modes.
     UpdatePixelColor { index: u32, color: [u8; 3] },
+    /// Deprecated: use `ListPixel`, `UnlistPixel` or `AcceptBid`.
     SellPixel { index: u32, price: u64 },
+    /// Buys at the listed price if it is at most `price`, otherwise bids `price` unless `direct_only` is set.
     BuyPixel { index: u32, price: u64, direct_only: u8 },
+    SetColorDelegate { index: u32 },
+    ClosePixel { index: u32 },
+    /// Deprecated: use `ListPixelSol`, `UnlistPixelSol` or `AcceptSolBid`.
+    SellPixelSol { index: u32, price: u64 },
+    /// Same as `BuyPixel`, for SOL listings and bids.
+    BuyPixelSol { index: u32, price: u64, direct_only: u8 },
+    ListPixel { index: u32, price: u64 },
+    UnlistPixel { index: u32 },
+    AcceptBid { index: u32, min_price: u64 },
+    ListPixelSol { index: u32, price: u64 },
+    UnlistPixelSol { index: u32 },
+    AcceptSolBid { index: u32, min_price: u64 },
+    CancelBid { index: u32 },
+    CancelSolBid { index: u32 },
+    InitializeConfig,
+    SetPaused { paused: u8, painting_paused: u8 },
+    WithdrawFees,
+    SetPresale { presale: u8, presale_price: u64, presale_quota: u32 },
+    SetMintCurve { mint_base_price: u64, mint_price_increment: u32 },
+    SetColorMode { color_mode: ColorMode },
+    /// Creates the stats account of a canvas initialized before it existed, counting the `minted` pixels.
+    InitializeStats { minted: u32 },
+    /// Creates a stats shard account, counting the listings and bids of its pixels already on the canvas.
+    InitializeStatsShard { shard: u8, listings: u32, sol_listings: u32, open_bids: u32, open_sol_bids: u32 },
+    MintPixelRgba { index: u32, color: [u8; 4], sell_price: u64 },
+    /// Mints with the color at `color` in the canvas palette.
+    MintPixelPalette { index: u32, color: u8, sell_price: u64 },
+    UpdatePixelColorRgba { index: u32, color: [u8; 4] },
+    UpdatePixelColorPalette { index: u32, color: u8 },
 }
 
+// (Builders take one argument per instruction account)
+#[allow(clippy::too_many_arguments)]
 impl NftCanvasInstruction {
+    /// The mint instruction of `color`.
+    pub fn mint_pixel_variant(index: u32, color: PixelColor, sell_price: u64) -> NftCanvasInstruction {
+        match color {
+            PixelColor::Rgb(color) => NftCanvasInstruction::MintPixel { index, color, sell_price },
+            PixelColor::Rgba(color) => NftCanvasInstruction::MintPixelRgba { index, color, sell_price },
+            PixelColor::Palette(color) => NftCanvasInstruction::MintPixelPalette { index, color, sell_price },
+        }
+    }
+
+    /// The paint instruction of `color`.
+    pub fn update_pixel_color_variant(index: u32, color: PixelColor) -> NftCanvasInstruction {
+        match color {
+            PixelColor::Rgb(color) => NftCanvasInstruction::UpdatePixelColor { index, color },
+            PixelColor::Rgba(color) => NftCanvasInstruction::UpdatePixelColorRgba { index, color },
+            PixelColor::Palette(color) => NftCanvasInstruction::UpdatePixelColorPalette { index, color },
+        }
+    }
+
+    /// Unpacks the instruction at the start of `data`, returning the trailing data that follows it.
+    pub fn unpack_from(data: &[u8]) -> Result<(NftCanvasInstruction, &[u8]), NftCanvasError> {
+        let mut instruction_data = data;
+        let instruction = NftCanvasInstruction::deserialize(&mut instruction_data)
+            .map_err(|_| NftCanvasError::FailedToUnpackData)?;
+        let packed_len = (data.len() - instruction_data.len()).max(NftCanvasInstruction::PACKED_SIZE);
+        if data.len() < packed_len {
+            return Err(NftCanvasError::FailedToUnpackData);
+        }
+        Ok((instruction, &data[packed_len..]))
+    }
+
     pub fn mint_pixel(
         program: Pubkey,
+        canvas_config: Pubkey,
+        canvas_stats: Pubkey,
+        canvas_stats_shard: Pubkey,
         system_program: Pubkey,
         rent_sysvar: Pubkey,
-        mint_pool_wallet: Pubkey,
+        fee_vault: Pubkey,
         pixel_account: Pubkey,
         owner_wallet: Pubkey,
         index: u32,
-        color: [u8; 3],
+        color: PixelColor,
         sell_price: u64,
     ) -> Instruction {
-        let object = NftCanvasInstruction::MintPixel { index, color, sell_price };
+        let object = NftCanvasInstruction::mint_pixel_variant(index, color, sell_price);
         let data: Vec<u8> = object.pack();
 
         let accounts = vec![
             AccountMeta::new_readonly(program, false),
+            AccountMeta::new_readonly(canvas_config, false),
+            AccountMeta::new(canvas_stats, false),
+            AccountMeta::new(canvas_stats_shard, false),
             AccountMeta::new_readonly(system_program, false),
             AccountMeta::new_readonly(rent_sysvar, false),
-            AccountMeta::new(mint_pool_wallet, false),
+            AccountMeta::new(fee_vault, false),
             AccountMeta::new(pixel_account, false),
             AccountMeta::new(owner_wallet, true),
         ];
@@ -46,30 +126,102 @@ impl NftCanvasInstruction {
         Instruction::new_with_bytes(program, &data, accounts)
     }
 
-    pub fn update_pixel_color(
+    /// Mints during the presale, with the proof from `allowlist::AllowlistTree::proof`.
+    pub fn presale_mint_pixel(
         program: Pubkey,
+        canvas_config: Pubkey,
+        canvas_stats: Pubkey,
+        canvas_stats_shard: Pubkey,
+        system_program: Pubkey,
+        rent_sysvar: Pubkey,
+        fee_vault: Pubkey,
         pixel_account: Pubkey,
         owner_wallet: Pubkey,
+        presale_receipt: Pubkey,
         index: u32,
-        color: [u8; 3],
+        color: PixelColor,
+        sell_price: u64,
+        proof: &[[u8; 32]],
     ) -> Instruction {
-        let object = NftCanvasInstruction::UpdatePixelColor { index, color };
+        let mut instruction = Self::mint_pixel(
+            program,
+            canvas_config,
+            canvas_stats,
+            canvas_stats_shard,
+            system_program,
+            rent_sysvar,
+            fee_vault,
+            pixel_account,
+            owner_wallet,
+            index,
+            color,
+            sell_price,
+        );
+        instruction.accounts.push(AccountMeta::new(presale_receipt, false));
+        instruction.data.extend(allowlist::pack_hashes(proof));
+        instruction
+    }
+
+    /// `authority_wallet` is either the pixel owner or its color delegate.
+    pub fn update_pixel_color(
+        program: Pubkey,
+        canvas_config: Pubkey,
+        pixel_account: Pubkey,
+        authority_wallet: Pubkey,
+        index: u32,
+        color: PixelColor,
+    ) -> Instruction {
+        let object = NftCanvasInstruction::update_pixel_color_variant(index, color);
         let data: Vec<u8> = object.pack();
 
         let accounts = vec![
             AccountMeta::new_readonly(program, false),
+            AccountMeta::new_readonly(canvas_config, false),
             AccountMeta::new(pixel_account, false),
-            AccountMeta::new(owner_wallet, true),
+            AccountMeta::new(authority_wallet, true),
         ];
 
         Instruction::new_with_bytes(program, &data, accounts)
     }
 
+    /// Passing `None` as `color_delegate` removes the current delegate.
+    pub fn set_color_delegate(
+        program: Pubkey,
+        canvas_config: Pubkey,
+        pixel_account: Pubkey,
+        owner_wallet: Pubkey,
+        color_delegate: Option<Pubkey>,
+        index: u32,
+    ) -> Instruction {
+        let object = NftCanvasInstruction::SetColorDelegate { index };
+        let data: Vec<u8> = object.pack();
+
+        let mut accounts = vec![
+            AccountMeta::new_readonly(program, false),
+            AccountMeta::new_readonly(canvas_config, false),
+            AccountMeta::new(pixel_account, false),
+            AccountMeta::new_readonly(owner_wallet, true),
+        ];
+        if let Some(color_delegate) = color_delegate {
+            accounts.push(AccountMeta::new_readonly(color_delegate, false));
+        }
+
+        Instruction::new_with_bytes(program, &data, accounts)
+    }
+
+    #[deprecated(note = "use list_pixel, unlist_pixel or accept_bid")]
     pub fn sell_pixel(
         program: Pubkey,
+        canvas_config: Pubkey,
+        canvas_stats_shard: Pubkey,
         token_program: Pubkey,
+        token_mint: Pubkey,
+        system_program: Pubkey,
+        rent_sysvar: Pubkey,
+        associated_token_program: Pubkey,
         pixel_account: Pubkey,
-        team_token_account: Pubkey,
+        fee_vault: Pubkey,
+        fee_vault_token_account: Pubkey,
         trade_pool: Pubkey,
         trade_pool_token_account: Pubkey,
         pixel_owner: Pubkey,
@@ -77,14 +229,63 @@ impl NftCanvasInstruction {
         index: u32,
         price: u64,
     ) -> Instruction {
-        let object = NftCanvasInstruction::SellPixel { index, price };
+        let mut instruction = Self::accept_bid(
+            program,
+            canvas_config,
+            canvas_stats_shard,
+            token_program,
+            token_mint,
+            system_program,
+            rent_sysvar,
+            associated_token_program,
+            pixel_account,
+            fee_vault,
+            fee_vault_token_account,
+            trade_pool,
+            trade_pool_token_account,
+            pixel_owner,
+            pixel_owner_token_account,
+            index,
+            price,
+        );
+        instruction.data = NftCanvasInstruction::SellPixel { index, price }.pack();
+        instruction
+    }
+
+    pub fn accept_bid(
+        program: Pubkey,
+        canvas_config: Pubkey,
+        canvas_stats_shard: Pubkey,
+        token_program: Pubkey,
+        token_mint: Pubkey,
+        system_program: Pubkey,
+        rent_sysvar: Pubkey,
+        associated_token_program: Pubkey,
+        pixel_account: Pubkey,
+        fee_vault: Pubkey,
+        fee_vault_token_account: Pubkey,
+        trade_pool: Pubkey,
+        trade_pool_token_account: Pubkey,
+        pixel_owner: Pubkey,
+        pixel_owner_token_account: Pubkey,
+        index: u32,
+        min_price: u64,
+    ) -> Instruction {
+        let object = NftCanvasInstruction::AcceptBid { index, min_price };
         let data: Vec<u8> = object.pack();
 
         let accounts = vec![
             AccountMeta::new_readonly(program, false),
+            AccountMeta::new_readonly(canvas_config, false),
+            AccountMeta::new(canvas_stats_shard, false),
             AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(token_mint, false),
+            AccountMeta::new_readonly(system_program, false),
+            AccountMeta::new_readonly(rent_sysvar, false),
+            AccountMeta::new_readonly(associated_token_program, false),
             AccountMeta::new(pixel_account, false),
-            AccountMeta::new(team_token_account, false),
+            AccountMeta::new_readonly(fee_vault, false),
+            AccountMeta::new(fee_vault_token_account, false),
             AccountMeta::new(trade_pool, false),
             AccountMeta::new(trade_pool_token_account, false),
             AccountMeta::new(pixel_owner, true),
@@ -94,17 +295,26 @@ impl NftCanvasInstruction {
         Instruction::new_with_bytes(program, &data, accounts)
     }
 
+    /// `previous_buyer_wallet` is required when outbidding an existing bid, so it can be refunded.
     pub fn buy_pixel(
         program: Pubkey,
+        canvas_config: Pubkey,
+        canvas_stats_shard: Pubkey,
         token_program: Pubkey,
+        token_mint: Pubkey,
+        system_program: Pubkey,
+        rent_sysvar: Pubkey,
+        associated_token_program: Pubkey,
         pixel_account: Pubkey,
-        team_token_account: Pubkey,
+        fee_vault: Pubkey,
+        fee_vault_token_account: Pubkey,
         trade_pool: Pubkey,
         trade_pool_token_account: Pubkey,
         pixel_owner: Pubkey,
         pixel_owner_token_account: Pubkey,
         buyer_wallet: Pubkey,
         buyer_token_account: Pubkey,
+        previous_buyer_wallet: Option<Pubkey>,
         index: u32,
         price: u64,
         direct_only: u8,
@@ -112,11 +322,18 @@ impl NftCanvasInstruction {
         let object = NftCanvasInstruction::BuyPixel { index, price, direct_only };
         let data: Vec<u8> = object.pack();
 
-        let accounts = vec![
+        let mut accounts = vec![
             AccountMeta::new_readonly(program, false),
+            AccountMeta::new_readonly(canvas_config, false),
+            AccountMeta::new(canvas_stats_shard, false),
             AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(token_mint, false),
+            AccountMeta::new_readonly(system_program, false),
+            AccountMeta::new_readonly(rent_sysvar, false),
+            AccountMeta::new_readonly(associated_token_program, false),
             AccountMeta::new(pixel_account, false),
-            AccountMeta::new(team_token_account, false),
+            AccountMeta::new_readonly(fee_vault, false),
+            AccountMeta::new(fee_vault_token_account, false),
             AccountMeta::new(trade_pool, false),
             AccountMeta::new(trade_pool_token_account, false),
             AccountMeta::new(pixel_owner, false),
@@ -124,9 +341,534 @@ impl NftCanvasInstruction {
             AccountMeta::new(buyer_wallet, true),
             AccountMeta::new(buyer_token_account, false),
         ];
+        if let Some(previous_buyer_wallet) = previous_buyer_wallet {
+            let previous_buyer_token_account = config::get_token_account(&previous_buyer_wallet, &token_program);
+            accounts.push(AccountMeta::new(previous_buyer_token_account, false));
+            accounts.push(AccountMeta::new_readonly(previous_buyer_wallet, false));
+        }
+
+        Instruction::new_with_bytes(program, &data, accounts)
+    }
+
+    /// `previous_buyer_wallet` and `previous_sol_buyer_wallet` are required
+    /// if the pixel has an open token or SOL bid respectively, so it can be refunded.
+    pub fn close_pixel(
+        program: Pubkey,
+        canvas_config: Pubkey,
+        canvas_stats: Pubkey,
+        canvas_stats_shard: Pubkey,
+        system_program: Pubkey,
+        token_program: Pubkey,
+        token_mint: Pubkey,
+        rent_sysvar: Pubkey,
+        associated_token_program: Pubkey,
+        pixel_account: Pubkey,
+        trade_pool: Pubkey,
+        trade_pool_token_account: Pubkey,
+        pixel_owner: Pubkey,
+        previous_buyer_wallet: Option<Pubkey>,
+        previous_sol_buyer_wallet: Option<Pubkey>,
+        index: u32,
+    ) -> Instruction {
+        let object = NftCanvasInstruction::ClosePixel { index };
+        let data: Vec<u8> = object.pack();
+
+        let mut accounts = vec![
+            AccountMeta::new_readonly(program, false),
+            AccountMeta::new_readonly(canvas_config, false),
+            AccountMeta::new(canvas_stats, false),
+            AccountMeta::new(canvas_stats_shard, false),
+            AccountMeta::new_readonly(system_program, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(token_mint, false),
+            AccountMeta::new_readonly(rent_sysvar, false),
+            AccountMeta::new_readonly(associated_token_program, false),
+            AccountMeta::new(pixel_account, false),
+            AccountMeta::new(trade_pool, false),
+            AccountMeta::new(trade_pool_token_account, false),
+            AccountMeta::new(pixel_owner, true),
+        ];
+        if let Some(previous_buyer_wallet) = previous_buyer_wallet {
+            let previous_buyer_token_account = config::get_token_account(&previous_buyer_wallet, &token_program);
+            accounts.push(AccountMeta::new(previous_buyer_token_account, false));
+            accounts.push(AccountMeta::new_readonly(previous_buyer_wallet, false));
+        }
+        if let Some(previous_sol_buyer_wallet) = previous_sol_buyer_wallet {
+            accounts.push(AccountMeta::new(previous_sol_buyer_wallet, false));
+        }
+
+        Instruction::new_with_bytes(program, &data, accounts)
+    }
+
+    #[deprecated(note = "use list_pixel_sol, unlist_pixel_sol or accept_sol_bid")]
+    pub fn sell_pixel_sol(
+        program: Pubkey,
+        canvas_config: Pubkey,
+        canvas_stats_shard: Pubkey,
+        system_program: Pubkey,
+        pixel_account: Pubkey,
+        fee_vault: Pubkey,
+        trade_pool: Pubkey,
+        pixel_owner: Pubkey,
+        index: u32,
+        price: u64,
+    ) -> Instruction {
+        let mut instruction = Self::accept_sol_bid(
+            program,
+            canvas_config,
+            canvas_stats_shard,
+            system_program,
+            pixel_account,
+            fee_vault,
+            trade_pool,
+            pixel_owner,
+            index,
+            price,
+        );
+        instruction.data = NftCanvasInstruction::SellPixelSol { index, price }.pack();
+        instruction
+    }
+
+    pub fn accept_sol_bid(
+        program: Pubkey,
+        canvas_config: Pubkey,
+        canvas_stats_shard: Pubkey,
+        system_program: Pubkey,
+        pixel_account: Pubkey,
+        fee_vault: Pubkey,
+        trade_pool: Pubkey,
+        pixel_owner: Pubkey,
+        index: u32,
+        min_price: u64,
+    ) -> Instruction {
+        let object = NftCanvasInstruction::AcceptSolBid { index, min_price };
+        let data: Vec<u8> = object.pack();
+
+        let accounts = vec![
+            AccountMeta::new_readonly(program, false),
+            AccountMeta::new_readonly(canvas_config, false),
+            AccountMeta::new(canvas_stats_shard, false),
+            AccountMeta::new_readonly(system_program, false),
+            AccountMeta::new(pixel_account, false),
+            AccountMeta::new(fee_vault, false),
+            AccountMeta::new(trade_pool, false),
+            AccountMeta::new(pixel_owner, true),
+        ];
+
+        Instruction::new_with_bytes(program, &data, accounts)
+    }
+
+    /// `previous_sol_buyer_wallet` is required when outbidding an existing SOL bid, so it can be refunded.
+    pub fn buy_pixel_sol(
+        program: Pubkey,
+        canvas_config: Pubkey,
+        canvas_stats_shard: Pubkey,
+        system_program: Pubkey,
+        pixel_account: Pubkey,
+        fee_vault: Pubkey,
+        trade_pool: Pubkey,
+        pixel_owner: Pubkey,
+        buyer_wallet: Pubkey,
+        previous_sol_buyer_wallet: Option<Pubkey>,
+        index: u32,
+        price: u64,
+        direct_only: u8,
+    ) -> Instruction {
+        let object = NftCanvasInstruction::BuyPixelSol { index, price, direct_only };
+        let data: Vec<u8> = object.pack();
+
+        let mut accounts = vec![
+            AccountMeta::new_readonly(program, false),
+            AccountMeta::new_readonly(canvas_config, false),
+            AccountMeta::new(canvas_stats_shard, false),
+            AccountMeta::new_readonly(system_program, false),
+            AccountMeta::new(pixel_account, false),
+            AccountMeta::new(fee_vault, false),
+            AccountMeta::new(trade_pool, false),
+            AccountMeta::new(pixel_owner, false),
+            AccountMeta::new(buyer_wallet, true),
+        ];
+        if let Some(previous_sol_buyer_wallet) = previous_sol_buyer_wallet {
+            accounts.push(AccountMeta::new(previous_sol_buyer_wallet, false));
+        }
+
+        Instruction::new_with_bytes(program, &data, accounts)
+    }
+
+    pub fn list_pixel(
+        program: Pubkey,
+        canvas_config: Pubkey,
+        canvas_stats_shard: Pubkey,
+        pixel_account: Pubkey,
+        pixel_owner: Pubkey,
+        index: u32,
+        price: u64,
+    ) -> Instruction {
+        let object = NftCanvasInstruction::ListPixel { index, price };
+        let data: Vec<u8> = object.pack();
+
+        let accounts = vec![
+            AccountMeta::new_readonly(program, false),
+            AccountMeta::new_readonly(canvas_config, false),
+            AccountMeta::new(canvas_stats_shard, false),
+            AccountMeta::new(pixel_account, false),
+            AccountMeta::new_readonly(pixel_owner, true),
+        ];
+
+        Instruction::new_with_bytes(program, &data, accounts)
+    }
+
+    pub fn unlist_pixel(
+        program: Pubkey,
+        canvas_config: Pubkey,
+        canvas_stats_shard: Pubkey,
+        pixel_account: Pubkey,
+        pixel_owner: Pubkey,
+        index: u32,
+    ) -> Instruction {
+        let object = NftCanvasInstruction::UnlistPixel { index };
+        let data: Vec<u8> = object.pack();
+
+        let accounts = vec![
+            AccountMeta::new_readonly(program, false),
+            AccountMeta::new_readonly(canvas_config, false),
+            AccountMeta::new(canvas_stats_shard, false),
+            AccountMeta::new(pixel_account, false),
+            AccountMeta::new_readonly(pixel_owner, true),
+        ];
+
+        Instruction::new_with_bytes(program, &data, accounts)
+    }
+
+    pub fn list_pixel_sol(
+        program: Pubkey,
+        canvas_config: Pubkey,
+        canvas_stats_shard: Pubkey,
+        pixel_account: Pubkey,
+        pixel_owner: Pubkey,
+        index: u32,
+        price: u64,
+    ) -> Instruction {
+        let object = NftCanvasInstruction::ListPixelSol { index, price };
+        let data: Vec<u8> = object.pack();
+
+        let accounts = vec![
+            AccountMeta::new_readonly(program, false),
+            AccountMeta::new_readonly(canvas_config, false),
+            AccountMeta::new(canvas_stats_shard, false),
+            AccountMeta::new(pixel_account, false),
+            AccountMeta::new_readonly(pixel_owner, true),
+        ];
+
+        Instruction::new_with_bytes(program, &data, accounts)
+    }
+
+    pub fn unlist_pixel_sol(
+        program: Pubkey,
+        canvas_config: Pubkey,
+        canvas_stats_shard: Pubkey,
+        pixel_account: Pubkey,
+        pixel_owner: Pubkey,
+        index: u32,
+    ) -> Instruction {
+        let object = NftCanvasInstruction::UnlistPixelSol { index };
+        let data: Vec<u8> = object.pack();
+
+        let accounts = vec![
+            AccountMeta::new_readonly(program, false),
+            AccountMeta::new_readonly(canvas_config, false),
+            AccountMeta::new(canvas_stats_shard, false),
+            AccountMeta::new(pixel_account, false),
+            AccountMeta::new_readonly(pixel_owner, true),
+        ];
+
+        Instruction::new_with_bytes(program, &data, accounts)
+    }
+
+    pub fn cancel_bid(
+        program: Pubkey,
+        canvas_config: Pubkey,
+        canvas_stats_shard: Pubkey,
+        token_program: Pubkey,
+        token_mint: Pubkey,
+        system_program: Pubkey,
+        rent_sysvar: Pubkey,
+        associated_token_program: Pubkey,
+        pixel_account: Pubkey,
+        trade_pool: Pubkey,
+        trade_pool_token_account: Pubkey,
+        buyer_wallet: Pubkey,
+        buyer_token_account: Pubkey,
+        index: u32,
+    ) -> Instruction {
+        let object = NftCanvasInstruction::CancelBid { index };
+        let data: Vec<u8> = object.pack();
+
+        let accounts = vec![
+            AccountMeta::new_readonly(program, false),
+            AccountMeta::new_readonly(canvas_config, false),
+            AccountMeta::new(canvas_stats_shard, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(token_mint, false),
+            AccountMeta::new_readonly(system_program, false),
+            AccountMeta::new_readonly(rent_sysvar, false),
+            AccountMeta::new_readonly(associated_token_program, false),
+            AccountMeta::new(pixel_account, false),
+            AccountMeta::new(trade_pool, false),
+            AccountMeta::new(trade_pool_token_account, false),
+            AccountMeta::new(buyer_wallet, true),
+            AccountMeta::new(buyer_token_account, false),
+        ];
+
+        Instruction::new_with_bytes(program, &data, accounts)
+    }
+
+    pub fn cancel_sol_bid(
+        program: Pubkey,
+        canvas_config: Pubkey,
+        canvas_stats_shard: Pubkey,
+        system_program: Pubkey,
+        pixel_account: Pubkey,
+        trade_pool: Pubkey,
+        buyer_wallet: Pubkey,
+        index: u32,
+    ) -> Instruction {
+        let object = NftCanvasInstruction::CancelSolBid { index };
+        let data: Vec<u8> = object.pack();
+
+        let accounts = vec![
+            AccountMeta::new_readonly(program, false),
+            AccountMeta::new_readonly(canvas_config, false),
+            AccountMeta::new(canvas_stats_shard, false),
+            AccountMeta::new_readonly(system_program, false),
+            AccountMeta::new(pixel_account, false),
+            AccountMeta::new(trade_pool, false),
+            AccountMeta::new(buyer_wallet, true),
+        ];
+
+        Instruction::new_with_bytes(program, &data, accounts)
+    }
+
+    pub fn initialize_config(
+        program: Pubkey,
+        system_program: Pubkey,
+        rent_sysvar: Pubkey,
+        canvas_config: Pubkey,
+        canvas_stats: Pubkey,
+        fee_vault: Pubkey,
+        trade_pool: Pubkey,
+        admin_wallet: Pubkey,
+    ) -> Instruction {
+        let object = NftCanvasInstruction::InitializeConfig;
+        let data: Vec<u8> = object.pack();
+
+        let accounts = vec![
+            AccountMeta::new_readonly(program, false),
+            AccountMeta::new_readonly(system_program, false),
+            AccountMeta::new_readonly(rent_sysvar, false),
+            AccountMeta::new(canvas_config, false),
+            AccountMeta::new(canvas_stats, false),
+            AccountMeta::new(fee_vault, false),
+            AccountMeta::new(trade_pool, false),
+            AccountMeta::new(admin_wallet, true),
+        ];
+
+        Instruction::new_with_bytes(program, &data, accounts)
+    }
+
+    /// Only for canvases initialized before the stats account existed: `initialize_config` creates it.
+    pub fn initialize_stats(
+        program: Pubkey,
+        system_program: Pubkey,
+        rent_sysvar: Pubkey,
+        canvas_config: Pubkey,
+        canvas_stats: Pubkey,
+        admin_wallet: Pubkey,
+        minted: u32,
+    ) -> Instruction {
+        let object = NftCanvasInstruction::InitializeStats { minted };
+        let data: Vec<u8> = object.pack();
+
+        let accounts = vec![
+            AccountMeta::new_readonly(program, false),
+            AccountMeta::new_readonly(system_program, false),
+            AccountMeta::new_readonly(rent_sysvar, false),
+            AccountMeta::new_readonly(canvas_config, false),
+            AccountMeta::new(canvas_stats, false),
+            AccountMeta::new(admin_wallet, true),
+        ];
+
+        Instruction::new_with_bytes(program, &data, accounts)
+    }
+
+    /// Every shard below `config::STATS_SHARDS` needs to be initialized before its pixels are minted or traded.
+    pub fn initialize_stats_shard(
+        program: Pubkey,
+        system_program: Pubkey,
+        rent_sysvar: Pubkey,
+        canvas_config: Pubkey,
+        canvas_stats_shard: Pubkey,
+        admin_wallet: Pubkey,
+        shard: u8,
+        listings: u32,
+        sol_listings: u32,
+        open_bids: u32,
+        open_sol_bids: u32,
+    ) -> Instruction {
+        let object = NftCanvasInstruction::InitializeStatsShard { shard, listings, sol_listings, open_bids, open_sol_bids };
+        let data: Vec<u8> = object.pack();
+
+        let accounts = vec![
+            AccountMeta::new_readonly(program, false),
+            AccountMeta::new_readonly(system_program, false),
+            AccountMeta::new_readonly(rent_sysvar, false),
+            AccountMeta::new_readonly(canvas_config, false),
+            AccountMeta::new(canvas_stats_shard, false),
+            AccountMeta::new(admin_wallet, true),
+        ];
+
+        Instruction::new_with_bytes(program, &data, accounts)
+    }
+
+    pub fn set_paused(
+        program: Pubkey,
+        canvas_config: Pubkey,
+        admin_wallet: Pubkey,
+        paused: u8,
+        painting_paused: u8,
+    ) -> Instruction {
+        let object = NftCanvasInstruction::SetPaused { paused, painting_paused };
+        let data: Vec<u8> = object.pack();
+
+        let accounts = vec![
+            AccountMeta::new_readonly(program, false),
+            AccountMeta::new(canvas_config, false),
+            AccountMeta::new_readonly(admin_wallet, true),
+        ];
+
+        Instruction::new_with_bytes(program, &data, accounts)
+    }
+
+    pub fn set_presale(
+        program: Pubkey,
+        canvas_config: Pubkey,
+        admin_wallet: Pubkey,
+        presale: u8,
+        presale_price: u64,
+        presale_quota: u32,
+        allowlist_root: [u8; 32],
+    ) -> Instruction {
+        let object = NftCanvasInstruction::SetPresale { presale, presale_price, presale_quota };
+        let mut data: Vec<u8> = object.pack();
+        data.extend_from_slice(&allowlist_root);
+
+        let accounts = vec![
+            AccountMeta::new_readonly(program, false),
+            AccountMeta::new(canvas_config, false),
+            AccountMeta::new_readonly(admin_wallet, true),
+        ];
+
+        Instruction::new_with_bytes(program, &data, accounts)
+    }
+
+    pub fn set_mint_curve(
+        program: Pubkey,
+        canvas_config: Pubkey,
+        admin_wallet: Pubkey,
+        mint_base_price: u64,
+        mint_price_increment: u32,
+    ) -> Instruction {
+        let object = NftCanvasInstruction::SetMintCurve { mint_base_price, mint_price_increment };
+        let data: Vec<u8> = object.pack();
+
+        let accounts = vec![
+            AccountMeta::new_readonly(program, false),
+            AccountMeta::new(canvas_config, false),
+            AccountMeta::new_readonly(admin_wallet, true),
+        ];
+
+        Instruction::new_with_bytes(program, &data, accounts)
+    }
+
+    /// `palette` must be empty unless `color_mode` is `ColorMode::Palette`.
+    pub fn set_color_mode(
+        program: Pubkey,
+        canvas_config: Pubkey,
+        admin_wallet: Pubkey,
+        color_mode: ColorMode,
+        palette: &[[u8; 3]],
+    ) -> Instruction {
+        let object = NftCanvasInstruction::SetColorMode { color_mode };
+        let mut data: Vec<u8> = object.pack();
+        data.extend(color::pack_palette(palette));
+
+        let accounts = vec![
+            AccountMeta::new_readonly(program, false),
+            AccountMeta::new(canvas_config, false),
+            AccountMeta::new_readonly(admin_wallet, true),
+        ];
+
+        Instruction::new_with_bytes(program, &data, accounts)
+    }
+
+    /// Sweeps every collected fee from the fee vault to `destination_wallet` and `destination_token_account`.
+    pub fn withdraw_fees(
+        program: Pubkey,
+        canvas_config: Pubkey,
+        system_program: Pubkey,
+        token_program: Pubkey,
+        token_mint: Pubkey,
+        fee_vault: Pubkey,
+        fee_vault_token_account: Pubkey,
+        admin_wallet: Pubkey,
+        destination_wallet: Pubkey,
+        destination_token_account: Pubkey,
+    ) -> Instruction {
+        let object = NftCanvasInstruction::WithdrawFees;
+        let data: Vec<u8> = object.pack();
+
+        let accounts = vec![
+            AccountMeta::new_readonly(program, false),
+            AccountMeta::new_readonly(canvas_config, false),
+            AccountMeta::new_readonly(system_program, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(token_mint, false),
+            AccountMeta::new(fee_vault, false),
+            AccountMeta::new(fee_vault_token_account, false),
+            AccountMeta::new_readonly(admin_wallet, true),
+            AccountMeta::new(destination_wallet, false),
+            AccountMeta::new(destination_token_account, false),
+        ];
 
         Instruction::new_with_bytes(program, &data, accounts)
     }
 }
 
-implement_packable!(NftCanvasInstruction, 16);
+impl Packable for NftCanvasInstruction {
+    const PACKED_SIZE: usize = 16;
+
+    fn unpack(data: &[u8]) -> Result<Self, NftCanvasError> {
+        let (instruction, trailing_data) = NftCanvasInstruction::unpack_from(data)?;
+        if !trailing_data.is_empty() {
+            return Err(NftCanvasError::FailedToUnpackData);
+        }
+        Ok(instruction)
+    }
+
+    fn pack(&self) -> Vec<u8> {
+        let mut result = borsh::BorshSerialize::try_to_vec(self).unwrap();
+        if result.len() < Self::PACKED_SIZE {
+            result.resize(Self::PACKED_SIZE, 0);
+        }
+        result
+    }
+
+    fn pack_into(&self, data: &mut [u8]) -> Result<(), NftCanvasError> {
+        let packed = self.pack();
+        if data.len() != packed.len() {
+            return Err(NftCanvasError::FailedToPackData);
+        }
+        data.copy_from_slice(&packed);
+        Ok(())
+    }
+}
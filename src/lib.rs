@@ -8,6 +8,11 @@ pub mod packable;
 pub mod config;
 pub mod instruction;
 pub mod state;
+pub mod token;
+pub mod accounts;
+pub mod allowlist;
+pub mod color;
+pub mod decoder;
 pub mod entrypoint;
 pub mod processor;
 pub mod error;
\ No newline at end of file
@@ -12,14 +12,14 @@ macro_rules! implement_packable {
         impl Packable for $for_type {
             const PACKED_SIZE: usize = $packed_size;
 
-            fn unpack(mut data: &[u8]) -> Result<Self, crate::error::NftCanvasError> {
+            fn unpack(mut data: &[u8]) -> Result<Self, $crate::error::NftCanvasError> {
                 if data.len() != Self::PACKED_SIZE {
                     // panic!("Failed to unpack type {}, len={}, expected={}", stringify!($for_type), data.len(), Self::PACKED_SIZE);
-                    return Err(crate::error::NftCanvasError::FailedToUnpackData);
+                    return Err($crate::error::NftCanvasError::FailedToUnpackData);
                 }
                 assert_eq!(data.len(), Self::PACKED_SIZE);
                 borsh::BorshDeserialize::deserialize(&mut data)
-                    .map_err(|_| crate::error::NftCanvasError::FailedToUnpackData)
+                    .map_err(|_| $crate::error::NftCanvasError::FailedToUnpackData)
             }
 
             fn pack(&self) -> Vec<u8> {
@@ -28,10 +28,10 @@ macro_rules! implement_packable {
                 result
             }
 
-            fn pack_into(&self, data: &mut [u8]) -> Result<(), crate::error::NftCanvasError> {
+            fn pack_into(&self, data: &mut [u8]) -> Result<(), $crate::error::NftCanvasError> {
                 if data.len() != Self::PACKED_SIZE {
                     // panic!("Failed to pack_into type {}, len={}, expected={}", stringify!($for_type), data.len(), Self::PACKED_SIZE);
-                    return Err(crate::error::NftCanvasError::FailedToPackData);
+                    return Err($crate::error::NftCanvasError::FailedToPackData);
                 }
                 data.copy_from_slice(&self.pack());
                 Ok(())
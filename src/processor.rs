@@ -1,20 +1,31 @@
 use solana_program;
 use solana_program::account_info::AccountInfo;
-use solana_program::account_info::next_account_info;
+use solana_program::clock::Clock;
 use solana_program::entrypoint::ProgramResult;
+use solana_program::msg;
 use solana_program::program::{invoke, invoke_signed};
+use solana_program::program_error::ProgramError;
 use solana_program::pubkey::Pubkey;
 use solana_program::rent::Rent;
 use solana_program::system_instruction;
-use solana_program::system_program;
 use solana_program::sysvar::Sysvar;
-use spl_token;
 
+use crate::accounts::{
+    AcceptBidAccounts, AcceptSolBidAccounts, BuyPixelAccounts, BuyPixelSolAccounts, CancelBidAccounts, CancelSolBidAccounts,
+    ClosePixelAccounts, InitializeConfigAccounts, InitializeStatsAccounts, InitializeStatsShardAccounts, ListPixelAccounts,
+    MintPixelAccounts, PreviousBuyerAccounts, PreviousSolBuyerAccounts, SetColorDelegateAccounts, SetConfigAccounts,
+    UpdatePixelColorAccounts, WithdrawFeesAccounts,
+};
+use crate::allowlist;
+use crate::color;
+use crate::color::{ColorMode, PixelColor};
 use crate::config;
 use crate::error::NftCanvasError;
 use crate::instruction::NftCanvasInstruction;
 use crate::packable::Packable;
-use crate::state::{Pixel, PixelBuyInfo};
+use crate::state::{CanvasConfig, CanvasStats, CanvasStatsShard, LegacyPixel, Pixel, PixelBuyInfo, PresaleReceipt};
+use crate::token;
+use crate::token::MintInfo;
 
 pub fn process_instruction(
     program_id: &Pubkey,
@@ -22,13 +33,35 @@ pub fn process_instruction(
     instruction_data: &[u8],
 ) -> ProgramResult {
     // assert_eq!(instruction_data.len(), NftCanvasInstruction::PACKED_SIZE);
-    let instruction: NftCanvasInstruction = NftCanvasInstruction::unpack(instruction_data)?;
+    let (instruction, trailing_data) = NftCanvasInstruction::unpack_from(instruction_data)?;
+    match instruction {
+        NftCanvasInstruction::MintPixel { .. }
+        | NftCanvasInstruction::MintPixelRgba { .. }
+        | NftCanvasInstruction::MintPixelPalette { .. }
+        | NftCanvasInstruction::SetPresale { .. }
+        | NftCanvasInstruction::SetColorMode { .. } => {}
+        _ => if !trailing_data.is_empty() {
+            return Err(NftCanvasError::FailedToUnpackData.into());
+        }
+    }
     match instruction {
         NftCanvasInstruction::MintPixel { index, color, sell_price } => {
-            process_mint_pixel(program_id, accounts, index, color, sell_price)
+            process_mint_pixel(program_id, accounts, index, PixelColor::Rgb(color), sell_price, &allowlist::unpack_hashes(trailing_data)?)
+        }
+        NftCanvasInstruction::MintPixelRgba { index, color, sell_price } => {
+            process_mint_pixel(program_id, accounts, index, PixelColor::Rgba(color), sell_price, &allowlist::unpack_hashes(trailing_data)?)
+        }
+        NftCanvasInstruction::MintPixelPalette { index, color, sell_price } => {
+            process_mint_pixel(program_id, accounts, index, PixelColor::Palette(color), sell_price, &allowlist::unpack_hashes(trailing_data)?)
         }
         NftCanvasInstruction::UpdatePixelColor { index, color } => {
-            process_update_pixel_color(program_id, accounts, index, color)
+            process_update_pixel_color(program_id, accounts, index, PixelColor::Rgb(color))
+        }
+        NftCanvasInstruction::UpdatePixelColorRgba { index, color } => {
+            process_update_pixel_color(program_id, accounts, index, PixelColor::Rgba(color))
+        }
+        NftCanvasInstruction::UpdatePixelColorPalette { index, color } => {
+            process_update_pixel_color(program_id, accounts, index, PixelColor::Palette(color))
         }
         NftCanvasInstruction::SellPixel { index, price } => {
             process_sell_pixel(program_id, accounts, index, price)
@@ -36,6 +69,66 @@ pub fn process_instruction(
         NftCanvasInstruction::BuyPixel { index, price, direct_only } => {
             process_buy_pixel(program_id, accounts, index, price, direct_only)
         }
+        NftCanvasInstruction::SetColorDelegate { index } => {
+            process_set_color_delegate(program_id, accounts, index)
+        }
+        NftCanvasInstruction::ClosePixel { index } => {
+            process_close_pixel(program_id, accounts, index)
+        }
+        NftCanvasInstruction::SellPixelSol { index, price } => {
+            process_sell_pixel_sol(program_id, accounts, index, price)
+        }
+        NftCanvasInstruction::BuyPixelSol { index, price, direct_only } => {
+            process_buy_pixel_sol(program_id, accounts, index, price, direct_only)
+        }
+        NftCanvasInstruction::ListPixel { index, price } => {
+            process_list_pixel(program_id, accounts, index, price)
+        }
+        NftCanvasInstruction::UnlistPixel { index } => {
+            process_unlist_pixel(program_id, accounts, index)
+        }
+        NftCanvasInstruction::AcceptBid { index, min_price } => {
+            process_accept_bid(program_id, accounts, index, min_price)
+        }
+        NftCanvasInstruction::ListPixelSol { index, price } => {
+            process_list_pixel_sol(program_id, accounts, index, price)
+        }
+        NftCanvasInstruction::UnlistPixelSol { index } => {
+            process_unlist_pixel_sol(program_id, accounts, index)
+        }
+        NftCanvasInstruction::AcceptSolBid { index, min_price } => {
+            process_accept_sol_bid(program_id, accounts, index, min_price)
+        }
+        NftCanvasInstruction::CancelBid { index } => {
+            process_cancel_bid(program_id, accounts, index)
+        }
+        NftCanvasInstruction::CancelSolBid { index } => {
+            process_cancel_sol_bid(program_id, accounts, index)
+        }
+        NftCanvasInstruction::InitializeConfig => {
+            process_initialize_config(program_id, accounts)
+        }
+        NftCanvasInstruction::SetPaused { paused, painting_paused } => {
+            process_set_paused(program_id, accounts, paused, painting_paused)
+        }
+        NftCanvasInstruction::WithdrawFees => {
+            process_withdraw_fees(program_id, accounts)
+        }
+        NftCanvasInstruction::SetPresale { presale, presale_price, presale_quota } => {
+            process_set_presale(program_id, accounts, presale, presale_price, presale_quota, &allowlist::unpack_hashes(trailing_data)?)
+        }
+        NftCanvasInstruction::SetMintCurve { mint_base_price, mint_price_increment } => {
+            process_set_mint_curve(program_id, accounts, mint_base_price, mint_price_increment)
+        }
+        NftCanvasInstruction::SetColorMode { color_mode } => {
+            process_set_color_mode(program_id, accounts, color_mode, &color::unpack_palette(trailing_data)?)
+        }
+        NftCanvasInstruction::InitializeStats { minted } => {
+            process_initialize_stats(program_id, accounts, minted)
+        }
+        NftCanvasInstruction::InitializeStatsShard { shard, listings, sol_listings, open_bids, open_sol_bids } => {
+            process_initialize_stats_shard(program_id, accounts, shard, listings, sol_listings, open_bids, open_sol_bids)
+        }
     }
 }
 
@@ -43,82 +136,104 @@ pub fn process_mint_pixel(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
     index: u32,
-    color: [u8; 3],
+    color: PixelColor,
     sell_price: u64,
+    allowlist_proof: &[[u8; 32]],
 ) -> ProgramResult {
     check_pixel_index(index)?;
 
-    let accounts_iter = &mut accounts.iter();
-
-    let program = next_account_info(accounts_iter)?;
-    let system_program_sysvar = next_account_info(accounts_iter)?;
-    let rent_sysvar = next_account_info(accounts_iter)?;
-    let mint_pool_wallet = next_account_info(accounts_iter)?;
-    let pixel_account = next_account_info(accounts_iter)?;
-    let owner_wallet = next_account_info(accounts_iter)?;
+    let MintPixelAccounts {
+        program,
+        canvas_config,
+        canvas_stats,
+        canvas_stats_shard,
+        system_program: system_program_sysvar,
+        rent_sysvar,
+        fee_vault,
+        pixel_account,
+        owner_wallet,
+        presale_receipt,
+        pixel_account_bump_seed,
+        presale_receipt_bump_seed,
+    } = MintPixelAccounts::load(program_id, accounts, index)?;
 
     // println!("process_mint_pixel: program={}, (owner={})", program.key, program.owner);
+    // println!("process_mint_pixel: canvas_config={}, (owner={})", canvas_config.key, canvas_config.owner);
+    // println!("process_mint_pixel: canvas_stats={}, (owner={})", canvas_stats.key, canvas_stats.owner);
+    // println!("process_mint_pixel: canvas_stats_shard={}, (owner={})", canvas_stats_shard.key, canvas_stats_shard.owner);
     // println!("process_mint_pixel: system_program_sysvar={}, (owner={})", system_program_sysvar.key, system_program_sysvar.owner);
     // println!("process_mint_pixel: rent_sysvar={}, (owner={})", rent_sysvar.key, rent_sysvar.owner);
-    // println!("process_mint_pixel: mint_pool_wallet={}, (owner={})", mint_pool_wallet.key, mint_pool_wallet.owner);
+    // println!("process_mint_pixel: fee_vault={}, (owner={})", fee_vault.key, fee_vault.owner);
     // println!("process_mint_pixel: pixel_account={}, (owner={})", pixel_account.key, pixel_account.owner);
     // println!("process_mint_pixel: owner_wallet={}, (owner={})", owner_wallet.key, owner_wallet.owner);
 
     //
 
-    let (pixel_account_id, pixel_account_bump_seed) = config::get_pixel(program_id, index);
+    let canvas_config_state = load_canvas_config(canvas_config)?;
+    check_fee_vault(program_id, fee_vault, &canvas_config_state)?;
+    let mut canvas_stats_state = load_canvas_stats(canvas_stats)?;
+    let mut canvas_stats_shard_state = load_canvas_stats_shard(canvas_stats_shard, index)?;
     let rent_state = Rent::from_account_info(rent_sysvar)?;
 
     //
 
-    if program.key != program_id {
-        return Err(NftCanvasError::ProgramKeyMismatch.into());
+    canvas_config_state.check_not_paused()?;
+    if !pixel_account.data_is_empty() {
+        return Err(NftCanvasError::PixelAlreadyMinted.into());
     }
-    if system_program_sysvar.key != &system_program::id() {
-        return Err(NftCanvasError::RentSysvarKeyMismatch.into());
-    }
-    if rent_sysvar.key != &solana_program::sysvar::rent::id() {
-        return Err(NftCanvasError::RentSysvarKeyMismatch.into());
-    }
-    if mint_pool_wallet.key != &config::mint_pool_wallet::id() {
-        return Err(NftCanvasError::MintPoolWalletKeyMismatch.into());
-    }
-    if pixel_account.key != &pixel_account_id {
-        return Err(NftCanvasError::PixelAccountKeyMismatch.into());
+    let color = canvas_config_state.resolve_color(&color)?;
+
+    if canvas_config_state.presale {
+        let presale_receipt = presale_receipt.ok_or(NftCanvasError::PresaleReceiptKeyMismatch)?;
+        if !allowlist::verify(&canvas_config_state.allowlist_root, owner_wallet.key, allowlist_proof) {
+            return Err(NftCanvasError::NotOnAllowlist.into());
+        }
+
+        // - Step 1: Create presale receipt on the first presale mint of the wallet
+        if presale_receipt.data_is_empty() {
+            create_program_account(
+                owner_wallet, presale_receipt, system_program_sysvar, program.key, &rent_state, PresaleReceipt::PACKED_SIZE,
+                presale_receipt_seeds!(program.key, owner_wallet.key, presale_receipt_bump_seed),
+            )?;
+        }
+
+        // - Step 2: Count the mint against the quota
+        let mut presale_receipt_state = PresaleReceipt::unpack(&presale_receipt.data.borrow())?;
+        if presale_receipt_state.minted >= canvas_config_state.presale_quota {
+            return Err(NftCanvasError::PresaleQuotaExceeded.into());
+        }
+        presale_receipt_state.minted += 1;
+        presale_receipt_state.pack_into(&mut presale_receipt.data.borrow_mut())?;
     }
 
-    // Transfer sol to mint pool
+    // Priced by the pixels minted before it (closed pixels no longer count)
+    let mint_cost = canvas_config_state.mint_price(canvas_stats_state.minted);
+
+    // Transfer sol to fee vault
     invoke(
         &solana_program::system_instruction::transfer(
-            owner_wallet.key, mint_pool_wallet.key, config::MINT_COST),
+            owner_wallet.key, fee_vault.key, mint_cost),
         &[
             owner_wallet.clone(),
-            mint_pool_wallet.clone(),
+            fee_vault.clone(),
         ],
     )?;
 
     // Create pixel account
-    invoke_signed(
-        &system_instruction::create_account(
-            owner_wallet.key,
-            pixel_account.key,
-            rent_state.minimum_balance(Pixel::PACKED_SIZE).max(1),
-            Pixel::PACKED_SIZE as u64,
-            program.key,
-        ),
-        &[
-            owner_wallet.clone(),
-            pixel_account.clone(),
-            system_program_sysvar.clone(),
-        ],
-        &[
-            pixel_seeds!(program.key, index, pixel_account_bump_seed),
-        ],
+    create_program_account(
+        owner_wallet, pixel_account, system_program_sysvar, program.key, &rent_state, Pixel::PACKED_SIZE,
+        pixel_seeds!(program.key, index, pixel_account_bump_seed),
     )?;
 
     // Initialize pixel account
-    Pixel::new(index, color, owner_wallet.key.clone(), sell_price)
-        .pack_into(&mut &mut pixel_account.data.borrow_mut()[..])?;
+    let pixel_account_state = Pixel::new(index, color, *owner_wallet.key, sell_price, pixel_account_bump_seed);
+    pixel_account_state.pack_into_account(&mut pixel_account.data.borrow_mut())?;
+
+    // Update stats
+    canvas_stats_state.record_mint(mint_cost);
+    canvas_stats_state.pack_into(&mut canvas_stats.data.borrow_mut())?;
+    canvas_stats_shard_state.add_pixel(&pixel_account_state);
+    canvas_stats_shard_state.pack_into(&mut canvas_stats_shard.data.borrow_mut())?;
 
     Ok(())
 }
@@ -127,71 +242,142 @@ pub fn process_update_pixel_color(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
     index: u32,
-    color: [u8; 3],
+    color: PixelColor,
 ) -> ProgramResult {
     check_pixel_index(index)?;
 
-    let accounts_iter = &mut accounts.iter();
-
-    let program = next_account_info(accounts_iter)?;
-    let pixel_account = next_account_info(accounts_iter)?;
-    let pixel_owner_wallet = next_account_info(accounts_iter)?;
+    let UpdatePixelColorAccounts {
+        program: _,
+        canvas_config,
+        pixel_account,
+        authority_wallet,
+    } = UpdatePixelColorAccounts::load(program_id, accounts)?;
 
     // println!("process_update_pixel_color: program={}, (owner={})", program.key, program.owner);
+    // println!("process_update_pixel_color: canvas_config={}, (owner={})", canvas_config.key, canvas_config.owner);
     // println!("process_update_pixel_color: pixel_account={}, (owner={})", pixel_account.key, pixel_account.owner);
-    // println!("process_update_pixel_color: pixel_owner_wallet={}, (owner={})", pixel_owner_wallet.key, pixel_owner_wallet.owner);
+    // println!("process_update_pixel_color: authority_wallet={}, (owner={})", authority_wallet.key, authority_wallet.owner);
 
     //
 
-    let (pixel_account_id, _) = config::get_pixel(program_id, index);
-    let pixel_account_state = Pixel::unpack(*pixel_account.data.borrow())?;
+    let canvas_config_state = load_canvas_config(canvas_config)?;
+    let pixel_account_state = load_pixel(program_id, pixel_account, index)?;
 
     //
 
-    if program.key != program_id {
-        return Err(NftCanvasError::ProgramKeyMismatch.into());
+    canvas_config_state.check_painting_not_paused()?;
+    if !pixel_account_state.is_color_authority(authority_wallet.key) {
+        return Err(NftCanvasError::ColorAuthorityKeyMismatch.into());
     }
-    if pixel_account.key != &pixel_account_id {
-        return Err(NftCanvasError::PixelAccountKeyMismatch.into());
+    let color = canvas_config_state.resolve_color(&color)?;
+
+    // WOW such optimisation
+    if pixel_account.data_len() == LegacyPixel::PACKED_SIZE {
+        if color[3] != color::OPAQUE {
+            return Err(NftCanvasError::LegacyPixelLayout.into());
+        }
+        pixel_account.data.borrow_mut()[4..7].copy_from_slice(&color[..3]);
+    } else {
+        pixel_account.data.borrow_mut()[4..8].copy_from_slice(&color);
     }
+    // pixel_account_state.color = color;
+    // pixel_account_state.pack_into_account(&mut pixel_account.data.borrow_mut())?;
+
+    Ok(())
+}
+
+pub fn process_set_color_delegate(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    index: u32,
+) -> ProgramResult {
+    check_pixel_index(index)?;
+
+    let SetColorDelegateAccounts {
+        program: _,
+        canvas_config,
+        pixel_account,
+        owner_wallet: pixel_owner_wallet,
+        color_delegate,
+    } = SetColorDelegateAccounts::load(program_id, accounts)?;
+
+    //
+
+    // (Allowed while paused)
+    load_canvas_config(canvas_config)?;
+    let mut pixel_account_state = load_pixel(program_id, pixel_account, index)?;
+
+    //
+
     if pixel_owner_wallet.key != &pixel_account_state.owner_wallet {
         return Err(NftCanvasError::PixelOwnerKeyMismatch.into());
     }
-    if !pixel_owner_wallet.is_signer {
-        return Err(NftCanvasError::PixelOwnerDidNotSign.into());
-    }
 
-    // WOW such optimisation
-    pixel_account.data.borrow_mut()[4..7].copy_from_slice(&color);
-    // pixel_account_state.color = color;
-    // pixel_account_state.pack_into(&mut &mut pixel_account.data.borrow_mut()[..])?;
+    pixel_account_state.color_delegate = color_delegate.map(|color_delegate| *color_delegate.key);
+    pixel_account_state.pack_into_account(&mut pixel_account.data.borrow_mut())?;
 
     Ok(())
 }
 
+/// Deprecated: either fills the best bid or lists the pixel, depending on `price`.
+/// Use `process_list_pixel`, `process_unlist_pixel` or `process_accept_bid` instead.
 pub fn process_sell_pixel(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
     index: u32,
     price: u64,
 ) -> ProgramResult {
-    check_pixel_index(index)?;
+    sell_pixel(program_id, accounts, index, price, true)
+}
+
+pub fn process_accept_bid(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    index: u32,
+    min_price: u64,
+) -> ProgramResult {
+    sell_pixel(program_id, accounts, index, min_price, false)
+}
 
-    let accounts_iter = &mut accounts.iter();
+/// Fills the best bid if it is at least `price`. Otherwise, if `allow_listing` is set, lists the pixel at `price`
+/// (0 meaning unlisted) as the legacy `SellPixel` instruction does.
+fn sell_pixel(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    index: u32,
+    price: u64,
+    allow_listing: bool,
+) -> ProgramResult {
+    check_pixel_index(index)?;
 
-    let program = next_account_info(accounts_iter)?;
-    let token_program = next_account_info(accounts_iter)?;
-    let pixel_account = next_account_info(accounts_iter)?;
-    let team_token_account = next_account_info(accounts_iter)?;
-    let trade_pool = next_account_info(accounts_iter)?;
-    let trade_pool_token_account = next_account_info(accounts_iter)?;
-    let pixel_owner = next_account_info(accounts_iter)?;
-    let pixel_owner_token_account = next_account_info(accounts_iter)?;
+    let AcceptBidAccounts {
+        program,
+        canvas_config,
+        canvas_stats_shard,
+        token_program,
+        token_mint,
+        system_program: system_program_sysvar,
+        rent_sysvar,
+        associated_token_program,
+        pixel_account,
+        fee_vault,
+        fee_vault_token_account,
+        trade_pool,
+        trade_pool_token_account,
+        pixel_owner,
+        pixel_owner_token_account,
+    } = AcceptBidAccounts::load(program_id, accounts)?;
 
     // println!("process_sell_pixel: program={}, (owner={})", program.key, program.owner);
+    // println!("process_sell_pixel: canvas_config={}, (owner={})", canvas_config.key, canvas_config.owner);
     // println!("process_sell_pixel: token_program={}, (owner={})", token_program.key, token_program.owner);
+    // println!("process_sell_pixel: token_mint={}, (owner={})", token_mint.key, token_mint.owner);
+    // println!("process_sell_pixel: system_program_sysvar={}, (owner={})", system_program_sysvar.key, system_program_sysvar.owner);
+    // println!("process_sell_pixel: rent_sysvar={}, (owner={})", rent_sysvar.key, rent_sysvar.owner);
+    // println!("process_sell_pixel: associated_token_program={}, (owner={})", associated_token_program.key, associated_token_program.owner);
     // println!("process_sell_pixel: pixel_account={}, (owner={})", pixel_account.key, pixel_account.owner);
-    // println!("process_sell_pixel: team_token_account={}, (owner={})", team_token_account.key, team_token_account.owner);
+    // println!("process_sell_pixel: fee_vault={}, (owner={})", fee_vault.key, fee_vault.owner);
+    // println!("process_sell_pixel: fee_vault_token_account={}, (owner={})", fee_vault_token_account.key, fee_vault_token_account.owner);
     // println!("process_sell_pixel: trade_pool={}, (owner={})", trade_pool.key, trade_pool.owner);
     // println!("process_sell_pixel: trade_pool_token_account={}, (owner={})", trade_pool_token_account.key, trade_pool_token_account.owner);
     // println!("process_sell_pixel: pixel_owner={}, (owner={})", pixel_owner.key, pixel_owner.owner);
@@ -199,89 +385,83 @@ pub fn process_sell_pixel(
 
     //
 
-    let (pixel_account_id, _) = config::get_pixel(program_id, index);
-    let (trade_pool_id, trade_pool_bump_seed) = config::get_trade_pool(program_id);
-    let trade_pool_token_account_id = config::get_token_account(&trade_pool_id);
-    let mut pixel_account_state = Pixel::unpack(*pixel_account.data.borrow())?;
-    let pixel_owner_token_account_id = config::get_token_account(&pixel_account_state.owner_wallet);
+    let canvas_config_state = load_canvas_config(canvas_config)?;
+    check_trade_pool(program_id, trade_pool, &canvas_config_state)?;
+    check_fee_vault(program_id, fee_vault, &canvas_config_state)?;
+    let mut canvas_stats_shard_state = load_canvas_stats_shard(canvas_stats_shard, index)?;
+    let mint_info = MintInfo::unpack(&token_mint.data.borrow(), Clock::get()?.epoch)?;
+    let token_account_programs = TokenAccountPrograms {
+        token_mint,
+        system_program: system_program_sysvar,
+        token_program,
+        rent_sysvar,
+        associated_token_program,
+    };
+    let mut pixel_account_state = load_pixel(program_id, pixel_account, index)?;
+    canvas_stats_shard_state.remove_pixel(&pixel_account_state);
 
     //
 
-    if program.key != program_id {
-        return Err(NftCanvasError::ProgramKeyMismatch.into());
-    }
-    if token_program.key != &spl_token::id() {
-        return Err(NftCanvasError::TokenProgramKeyMismatch.into());
-    }
-    if pixel_account.key != &pixel_account_id {
-        return Err(NftCanvasError::PixelAccountKeyMismatch.into());
-    }
-    if team_token_account.key != &config::team_token_account::id() {
-        return Err(NftCanvasError::TeamTokenAccountKeyMismatch.into());
-    }
-    if trade_pool.key != &trade_pool_id {
-        return Err(NftCanvasError::TradePoolKeyMismatch.into());
-    }
-    if trade_pool_token_account.key != &trade_pool_token_account_id {
-        return Err(NftCanvasError::TradePoolTokenAccountKeyMismatch.into());
-    }
+    canvas_config_state.check_not_paused()?;
     if pixel_owner.key != &pixel_account_state.owner_wallet {
         return Err(NftCanvasError::PixelOwnerKeyMismatch.into());
     }
-    if pixel_owner_token_account.key != &pixel_owner_token_account_id {
-        return Err(NftCanvasError::PixelOwnerKeyMismatch.into());
-    }
-
-    if !pixel_owner.is_signer {
-        return Err(NftCanvasError::PixelOwnerDidNotSign.into());
-    }
 
     //
 
     if let Some(best_buy_info) = pixel_account_state.best_buy_info.as_ref()
-        .and_then(|best_buy_info| (price != 0 && best_buy_info.price >= price).then(|| best_buy_info)) {
+        .filter(|best_buy_info| (!allow_listing || price != 0) && best_buy_info.price >= price) {
         // Process sell :
-        let amount_split = config::TradeAmountSplit::split(best_buy_info.price);
+        let amount_split = config::TradeAmountSplit::split_with_transfer_fee(best_buy_info.price, &mint_info.transfer_fee);
+        log_trade_receipt(index, best_buy_info.price, &amount_split);
+        canvas_stats_shard_state.record_sale(best_buy_info.price, amount_split.to_team, Clock::get()?.slot);
+        create_token_account_if_missing(pixel_owner, pixel_owner_token_account, pixel_owner, &token_account_programs)?;
         // - Transfer sell_price - tax to seller
         invoke_signed(
-            &spl_token::instruction::transfer(
+            &token::transfer_checked(
                 token_program.key,
                 trade_pool_token_account.key,
+                token_mint.key,
                 pixel_owner_token_account.key,
                 trade_pool.key,
-                &[trade_pool.key],
                 amount_split.to_seller,
+                mint_info.decimals,
             )?,
-            &[trade_pool_token_account.clone(), pixel_owner_token_account.clone(), trade_pool.clone(), token_program.clone()],
+            &[trade_pool_token_account.clone(), token_mint.clone(), pixel_owner_token_account.clone(), trade_pool.clone(), token_program.clone()],
             &[
-                trade_pool_seeds!(program.key, trade_pool_bump_seed),
+                trade_pool_seeds!(program.key, canvas_config_state.trade_pool_bump_seed),
             ],
         )?;
-        // - Transfer tax to team
+        // - Transfer tax to fee vault
+        create_token_account_if_missing(pixel_owner, fee_vault_token_account, fee_vault, &token_account_programs)?;
         invoke_signed(
-            &spl_token::instruction::transfer(
+            &token::transfer_checked(
                 token_program.key,
                 trade_pool_token_account.key,
-                team_token_account.key,
+                token_mint.key,
+                fee_vault_token_account.key,
                 trade_pool.key,
-                &[trade_pool.key],
                 amount_split.to_team,
+                mint_info.decimals,
             )?,
-            &[trade_pool_token_account.clone(), team_token_account.clone(), trade_pool.clone(), token_program.clone()],
+            &[trade_pool_token_account.clone(), token_mint.clone(), fee_vault_token_account.clone(), trade_pool.clone(), token_program.clone()],
             &[
-                trade_pool_seeds!(program.key, trade_pool_bump_seed),
+                trade_pool_seeds!(program.key, canvas_config_state.trade_pool_bump_seed),
             ],
         )?;
         // - Update pixel owner
-        pixel_account_state.owner_wallet = best_buy_info.buyer_wallet;
-        pixel_account_state.sell_price = 0;
+        pixel_account_state.transfer_ownership(best_buy_info.buyer_wallet);
         pixel_account_state.best_buy_info = None;
-    } else {
+    } else if allow_listing {
         // Set sell price :
         // - Update pixel sell price
         pixel_account_state.sell_price = price;
+    } else {
+        return Err(NftCanvasError::NoAcceptableBid.into());
     }
-    pixel_account_state.pack_into(&mut &mut pixel_account.data.borrow_mut()[..])?;
+    canvas_stats_shard_state.add_pixel(&pixel_account_state);
+    pixel_account_state.pack_into_account(&mut pixel_account.data.borrow_mut())?;
+    canvas_stats_shard_state.pack_into(&mut canvas_stats_shard.data.borrow_mut())?;
 
     Ok(())
 }
@@ -295,23 +475,38 @@ pub fn process_buy_pixel(
 ) -> ProgramResult {
     check_pixel_index(index)?;
 
-    let accounts_iter = &mut accounts.iter();
-
-    let program = next_account_info(accounts_iter)?;
-    let token_program = next_account_info(accounts_iter)?;
-    let pixel_account = next_account_info(accounts_iter)?;
-    let team_token_account = next_account_info(accounts_iter)?;
-    let trade_pool = next_account_info(accounts_iter)?;
-    let trade_pool_token_account = next_account_info(accounts_iter)?;
-    let pixel_owner = next_account_info(accounts_iter)?;
-    let pixel_owner_token_account = next_account_info(accounts_iter)?;
-    let buyer_wallet = next_account_info(accounts_iter)?;
-    let buyer_token_account = next_account_info(accounts_iter)?;
+    let (BuyPixelAccounts {
+        program,
+        canvas_config,
+        canvas_stats_shard,
+        token_program,
+        token_mint,
+        system_program: system_program_sysvar,
+        rent_sysvar,
+        associated_token_program,
+        pixel_account,
+        fee_vault,
+        fee_vault_token_account,
+        trade_pool,
+        trade_pool_token_account,
+        pixel_owner,
+        pixel_owner_token_account,
+        buyer_wallet,
+        buyer_token_account,
+    }, previous_buyer_accounts) = BuyPixelAccounts::load(program_id, accounts)?;
+    let accounts_iter = &mut previous_buyer_accounts.iter();
 
     // println!("process_buy_pixel: program={}, (owner={})", program.key, program.owner);
+    // println!("process_buy_pixel: canvas_config={}, (owner={})", canvas_config.key, canvas_config.owner);
+    // println!("process_buy_pixel: canvas_stats_shard={}, (owner={})", canvas_stats_shard.key, canvas_stats_shard.owner);
     // println!("process_buy_pixel: token_program={}, (owner={})", token_program.key, token_program.owner);
+    // println!("process_buy_pixel: token_mint={}, (owner={})", token_mint.key, token_mint.owner);
+    // println!("process_buy_pixel: system_program_sysvar={}, (owner={})", system_program_sysvar.key, system_program_sysvar.owner);
+    // println!("process_buy_pixel: rent_sysvar={}, (owner={})", rent_sysvar.key, rent_sysvar.owner);
+    // println!("process_buy_pixel: associated_token_program={}, (owner={})", associated_token_program.key, associated_token_program.owner);
     // println!("process_buy_pixel: pixel_account={}, (owner={})", pixel_account.key, pixel_account.owner);
-    // println!("process_buy_pixel: team_token_account={}, (owner={})", team_token_account.key, team_token_account.owner);
+    // println!("process_buy_pixel: fee_vault={}, (owner={})", fee_vault.key, fee_vault.owner);
+    // println!("process_buy_pixel: fee_vault_token_account={}, (owner={})", fee_vault_token_account.key, fee_vault_token_account.owner);
     // println!("process_buy_pixel: trade_pool={}, (owner={})", trade_pool.key, trade_pool.owner);
     // println!("process_buy_pixel: trade_pool_token_account={}, (owner={})", trade_pool_token_account.key, trade_pool_token_account.owner);
     // println!("process_buy_pixel: pixel_owner={}, (owner={})", pixel_owner.key, pixel_owner.owner);
@@ -321,138 +516,1181 @@ pub fn process_buy_pixel(
 
     //
 
-    let (pixel_account_id, _) = config::get_pixel(program_id, index);
-    let (trade_pool_id, trade_pool_bump_seed) = config::get_trade_pool(program_id);
-    let trade_pool_token_account_id = config::get_token_account(&trade_pool_id);
-
-    let mut pixel_account_state = Pixel::unpack(*pixel_account.data.borrow())?;
-    let pixel_owner_token_account_id = config::get_token_account(&pixel_account_state.owner_wallet);
+    let canvas_config_state = load_canvas_config(canvas_config)?;
+    check_trade_pool(program_id, trade_pool, &canvas_config_state)?;
+    check_fee_vault(program_id, fee_vault, &canvas_config_state)?;
+    let mut canvas_stats_shard_state = load_canvas_stats_shard(canvas_stats_shard, index)?;
+    let mint_info = MintInfo::unpack(&token_mint.data.borrow(), Clock::get()?.epoch)?;
+    let token_account_programs = TokenAccountPrograms {
+        token_mint,
+        system_program: system_program_sysvar,
+        token_program,
+        rent_sysvar,
+        associated_token_program,
+    };
 
-    let buyer_token_account_id = config::get_token_account(buyer_wallet.key);
+    let mut pixel_account_state = load_pixel(program_id, pixel_account, index)?;
+    canvas_stats_shard_state.remove_pixel(&pixel_account_state);
 
     //
 
-    if program.key != program_id {
-        return Err(NftCanvasError::ProgramKeyMismatch.into());
-    }
-    if token_program.key != &spl_token::id() {
-        return Err(NftCanvasError::TokenProgramKeyMismatch.into());
-    }
-    if pixel_account.key != &pixel_account_id {
-        return Err(NftCanvasError::PixelAccountKeyMismatch.into());
-    }
-    if team_token_account.key != &config::team_token_account::id() {
-        return Err(NftCanvasError::TeamTokenAccountKeyMismatch.into());
-    }
-    if trade_pool.key != &trade_pool_id {
-        return Err(NftCanvasError::TradePoolKeyMismatch.into());
-    }
-    if trade_pool_token_account.key != &trade_pool_token_account_id {
-        return Err(NftCanvasError::TradePoolTokenAccountKeyMismatch.into());
-    }
+    canvas_config_state.check_not_paused()?;
     if pixel_owner.key != &pixel_account_state.owner_wallet {
         return Err(NftCanvasError::PixelOwnerKeyMismatch.into());
     }
-    if pixel_owner_token_account.key != &pixel_owner_token_account_id {
-        return Err(NftCanvasError::PixelOwnerKeyMismatch.into());
-    }
-    if buyer_token_account.key != &buyer_token_account_id {
-        return Err(NftCanvasError::BuyerTokenAccountKeyMismatch.into());
-    }
-
-    if !buyer_wallet.is_signer {
-        return Err(NftCanvasError::PixelOwnerDidNotSign.into());
-    }
 
     //
 
+    // (Bids record what the trade pool actually receives, net of the token's transfer fee)
+    let escrow_amount = price - mint_info.transfer_fee.calculate(price);
+
     if pixel_account_state.sell_price != 0 && price >= pixel_account_state.sell_price {
-        // Process buy :
-        let amount_split = config::TradeAmountSplit::split(price);
+        // Process buy (at the listed price, `price` only caps it) :
+        let amount_split = config::TradeAmountSplit::split_with_transfer_fee(pixel_account_state.sell_price, &mint_info.transfer_fee);
+        log_trade_receipt(index, pixel_account_state.sell_price, &amount_split);
+        canvas_stats_shard_state.record_sale(pixel_account_state.sell_price, amount_split.to_team, Clock::get()?.slot);
+        create_token_account_if_missing(buyer_wallet, pixel_owner_token_account, pixel_owner, &token_account_programs)?;
         // - Transfer sell_price - tax to seller
         invoke(
-            &spl_token::instruction::transfer(
+            &token::transfer_checked(
                 token_program.key,
                 buyer_token_account.key,
+                token_mint.key,
                 pixel_owner_token_account.key,
                 buyer_wallet.key,
-                &[buyer_wallet.key],
                 amount_split.to_seller,
+                mint_info.decimals,
             )?,
-            &[buyer_token_account.clone(), pixel_owner_token_account.clone(), buyer_wallet.clone(), token_program.clone()],
+            &[buyer_token_account.clone(), token_mint.clone(), pixel_owner_token_account.clone(), buyer_wallet.clone(), token_program.clone()],
         )?;
-        // - Transfer tax to team
-        invoke_signed(
-            &spl_token::instruction::transfer(
+        // - Transfer tax to fee vault
+        create_token_account_if_missing(buyer_wallet, fee_vault_token_account, fee_vault, &token_account_programs)?;
+        invoke(
+            &token::transfer_checked(
                 token_program.key,
                 buyer_token_account.key,
-                team_token_account.key,
+                token_mint.key,
+                fee_vault_token_account.key,
                 buyer_wallet.key,
-                &[buyer_wallet.key],
                 amount_split.to_team,
+                mint_info.decimals,
             )?,
-            &[buyer_token_account.clone(), team_token_account.clone(), buyer_wallet.clone(), token_program.clone()],
-            &[
-                trade_pool_seeds!(program.key, trade_pool_bump_seed),
-            ],
+            &[buyer_token_account.clone(), token_mint.clone(), fee_vault_token_account.clone(), buyer_wallet.clone(), token_program.clone()],
         )?;
         // - Update pixel
-        pixel_account_state.owner_wallet = buyer_wallet.key.clone();
-        pixel_account_state.sell_price = 0;
-        // (If last best buyer is current buyer, remove order)
-        if pixel_account_state.best_buy_info.as_ref().map(|best_buy_info| &best_buy_info.buyer_wallet == buyer_wallet.key).unwrap_or(false) {
+        pixel_account_state.transfer_ownership(*buyer_wallet.key);
+        // (If last best buyer is current buyer, refund and remove order)
+        if let Some(previous_buy_info) = pixel_account_state.best_buy_info.as_ref()
+            .filter(|best_buy_info| &best_buy_info.buyer_wallet == buyer_wallet.key) {
+            invoke_signed(
+                &token::transfer_checked(
+                    token_program.key,
+                    trade_pool_token_account.key,
+                    token_mint.key,
+                    buyer_token_account.key,
+                    trade_pool.key,
+                    previous_buy_info.price,
+                    mint_info.decimals,
+                )?,
+                &[trade_pool_token_account.clone(), token_mint.clone(), buyer_token_account.clone(), trade_pool.clone(), token_program.clone()],
+                &[
+                    trade_pool_seeds!(program.key, canvas_config_state.trade_pool_bump_seed),
+                ],
+            )?;
             pixel_account_state.best_buy_info = None;
         }
-    } else if pixel_account_state.best_buy_info.as_ref().map(|previous_buy_info| price > previous_buy_info.price).unwrap_or(true) {
+    } else if pixel_account_state.best_buy_info.as_ref().map(|previous_buy_info| escrow_amount > previous_buy_info.price).unwrap_or(true) {
         if direct_only != 0 {
             return Err(NftCanvasError::CouldNotDirectBuy.into());
         }
         // Is best buyer :
         // - Refund previous best buyer
         if let Some(previous_buy_info) = pixel_account_state.best_buy_info {
-            let previous_buyer_token_account = next_account_info(accounts_iter)?;
+            let PreviousBuyerAccounts {
+                previous_buyer_token_account,
+                previous_buyer_wallet,
+            } = PreviousBuyerAccounts::load(accounts_iter, &previous_buy_info.buyer_wallet)?;
+            create_token_account_if_missing(buyer_wallet, previous_buyer_token_account, previous_buyer_wallet, &token_account_programs)?;
             invoke_signed(
-                &spl_token::instruction::transfer(
+                &token::transfer_checked(
                     token_program.key,
                     trade_pool_token_account.key,
+                    token_mint.key,
                     previous_buyer_token_account.key,
                     trade_pool.key,
-                    &[trade_pool.key],
                     previous_buy_info.price,
+                    mint_info.decimals,
                 )?,
-                &[trade_pool_token_account.clone(), previous_buyer_token_account.clone(), trade_pool.clone(), token_program.clone()],
+                &[trade_pool_token_account.clone(), token_mint.clone(), previous_buyer_token_account.clone(), trade_pool.clone(), token_program.clone()],
                 &[
-                    trade_pool_seeds!(program.key, trade_pool_bump_seed),
+                    trade_pool_seeds!(program.key, canvas_config_state.trade_pool_bump_seed),
                 ],
             )?;
         }
         // - Transfer buy_price to trade pool
         invoke(
-            &spl_token::instruction::transfer(
+            &token::transfer_checked(
                 token_program.key,
                 buyer_token_account.key,
+                token_mint.key,
                 trade_pool_token_account.key,
                 buyer_wallet.key,
-                &[buyer_wallet.key],
                 price,
+                mint_info.decimals,
             )?,
-            &[buyer_token_account.clone(), trade_pool_token_account.clone(), buyer_wallet.clone(), token_program.clone()],
+            &[buyer_token_account.clone(), token_mint.clone(), trade_pool_token_account.clone(), buyer_wallet.clone(), token_program.clone()],
         )?;
         // - Update pixel
         pixel_account_state.best_buy_info = Some(PixelBuyInfo {
-            price,
-            buyer_wallet: buyer_wallet.key.clone(),
+            price: escrow_amount,
+            buyer_wallet: *buyer_wallet.key,
         });
     } else {
         return Err(NftCanvasError::BuyPriceTooLow.into());
     }
 
-    pixel_account_state.pack_into(&mut &mut pixel_account.data.borrow_mut()[..])?;
+    canvas_stats_shard_state.add_pixel(&pixel_account_state);
+    pixel_account_state.pack_into_account(&mut pixel_account.data.borrow_mut())?;
+    canvas_stats_shard_state.pack_into(&mut canvas_stats_shard.data.borrow_mut())?;
 
     Ok(())
 }
 
-// Misc
+pub fn process_list_pixel(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    index: u32,
+    price: u64,
+) -> ProgramResult {
+    if price == 0 {
+        return Err(NftCanvasError::ListPriceZero.into());
+    }
+    update_listing(program_id, accounts, index, false, |pixel_account_state| pixel_account_state.sell_price = price)
+}
+
+pub fn process_unlist_pixel(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    index: u32,
+) -> ProgramResult {
+    update_listing(program_id, accounts, index, true, |pixel_account_state| pixel_account_state.sell_price = 0)
+}
+
+pub fn process_list_pixel_sol(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    index: u32,
+    price: u64,
+) -> ProgramResult {
+    if price == 0 {
+        return Err(NftCanvasError::ListPriceZero.into());
+    }
+    update_listing(program_id, accounts, index, false, |pixel_account_state| pixel_account_state.sol_sell_price = price)
+}
+
+pub fn process_unlist_pixel_sol(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    index: u32,
+) -> ProgramResult {
+    update_listing(program_id, accounts, index, true, |pixel_account_state| pixel_account_state.sol_sell_price = 0)
+}
+
+fn update_listing<F: FnOnce(&mut Pixel)>(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    index: u32,
+    allowed_while_paused: bool,
+    update: F,
+) -> ProgramResult {
+    check_pixel_index(index)?;
+
+    let ListPixelAccounts {
+        program: _,
+        canvas_config,
+        canvas_stats_shard,
+        pixel_account,
+        pixel_owner,
+    } = ListPixelAccounts::load(program_id, accounts)?;
+
+    //
+
+    let canvas_config_state = load_canvas_config(canvas_config)?;
+    let mut canvas_stats_shard_state = load_canvas_stats_shard(canvas_stats_shard, index)?;
+    let mut pixel_account_state = load_pixel(program_id, pixel_account, index)?;
+    canvas_stats_shard_state.remove_pixel(&pixel_account_state);
+
+    //
+
+    if !allowed_while_paused {
+        canvas_config_state.check_not_paused()?;
+    }
+    if pixel_owner.key != &pixel_account_state.owner_wallet {
+        return Err(NftCanvasError::PixelOwnerKeyMismatch.into());
+    }
+
+    update(&mut pixel_account_state);
+    canvas_stats_shard_state.add_pixel(&pixel_account_state);
+    pixel_account_state.pack_into_account(&mut pixel_account.data.borrow_mut())?;
+    canvas_stats_shard_state.pack_into(&mut canvas_stats_shard.data.borrow_mut())?;
+
+    Ok(())
+}
+
+pub fn process_close_pixel(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    index: u32,
+) -> ProgramResult {
+    check_pixel_index(index)?;
+
+    let (ClosePixelAccounts {
+        program,
+        canvas_config,
+        canvas_stats,
+        canvas_stats_shard,
+        system_program: system_program_sysvar,
+        token_program,
+        token_mint,
+        rent_sysvar,
+        associated_token_program,
+        pixel_account,
+        trade_pool,
+        trade_pool_token_account,
+        pixel_owner,
+    }, previous_buyer_accounts) = ClosePixelAccounts::load(program_id, accounts)?;
+    let accounts_iter = &mut previous_buyer_accounts.iter();
+
+    //
+
+    // (Allowed while paused)
+    let canvas_config_state = load_canvas_config(canvas_config)?;
+    check_trade_pool(program_id, trade_pool, &canvas_config_state)?;
+    let mut canvas_stats_state = load_canvas_stats(canvas_stats)?;
+    let mut canvas_stats_shard_state = load_canvas_stats_shard(canvas_stats_shard, index)?;
+    let mint_info = MintInfo::unpack(&token_mint.data.borrow(), Clock::get()?.epoch)?;
+    let token_account_programs = TokenAccountPrograms {
+        token_mint,
+        system_program: system_program_sysvar,
+        token_program,
+        rent_sysvar,
+        associated_token_program,
+    };
+    let pixel_account_state = load_pixel(program_id, pixel_account, index)?;
+    canvas_stats_state.record_close();
+    canvas_stats_shard_state.remove_pixel(&pixel_account_state);
+
+    //
+
+    if pixel_owner.key != &pixel_account_state.owner_wallet {
+        return Err(NftCanvasError::PixelOwnerKeyMismatch.into());
+    }
+
+    //
+
+    // Refund best buyers
+    if let Some(previous_buy_info) = pixel_account_state.best_buy_info {
+        let PreviousBuyerAccounts {
+            previous_buyer_token_account,
+            previous_buyer_wallet,
+        } = PreviousBuyerAccounts::load(accounts_iter, &previous_buy_info.buyer_wallet)?;
+        create_token_account_if_missing(pixel_owner, previous_buyer_token_account, previous_buyer_wallet, &token_account_programs)?;
+        invoke_signed(
+            &token::transfer_checked(
+                token_program.key,
+                trade_pool_token_account.key,
+                token_mint.key,
+                previous_buyer_token_account.key,
+                trade_pool.key,
+                previous_buy_info.price,
+                mint_info.decimals,
+            )?,
+            &[trade_pool_token_account.clone(), token_mint.clone(), previous_buyer_token_account.clone(), trade_pool.clone(), token_program.clone()],
+            &[
+                trade_pool_seeds!(program.key, canvas_config_state.trade_pool_bump_seed),
+            ],
+        )?;
+    }
+    if let Some(previous_sol_buy_info) = pixel_account_state.best_sol_buy_info {
+        let PreviousSolBuyerAccounts {
+            previous_sol_buyer_wallet: previous_buyer_wallet,
+        } = PreviousSolBuyerAccounts::load(accounts_iter, &previous_sol_buy_info.buyer_wallet)?;
+        invoke_signed(
+            &system_instruction::transfer(
+                trade_pool.key, previous_buyer_wallet.key, previous_sol_buy_info.price),
+            &[trade_pool.clone(), previous_buyer_wallet.clone(), system_program_sysvar.clone()],
+            &[
+                trade_pool_seeds!(program.key, canvas_config_state.trade_pool_bump_seed),
+            ],
+        )?;
+    }
+
+    // Update stats
+    canvas_stats_state.pack_into(&mut canvas_stats.data.borrow_mut())?;
+    canvas_stats_shard_state.pack_into(&mut canvas_stats_shard.data.borrow_mut())?;
+
+    // Zero pixel account
+    pixel_account.data.borrow_mut().fill(0);
+
+    // Return rent to owner (the runtime purges the account once it has no lamports left,
+    // so the index can be minted again in a later transaction)
+    let pixel_account_lamports = pixel_account.lamports();
+    **pixel_owner.lamports.borrow_mut() = pixel_owner.lamports()
+        .checked_add(pixel_account_lamports)
+        .ok_or(ProgramError::InvalidAccountData)?;
+    **pixel_account.lamports.borrow_mut() = 0;
+
+    Ok(())
+}
+
+/// Deprecated: either fills the best SOL bid or lists the pixel for SOL, depending on `price`.
+/// Use `process_list_pixel_sol`, `process_unlist_pixel_sol` or `process_accept_sol_bid` instead.
+pub fn process_sell_pixel_sol(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    index: u32,
+    price: u64,
+) -> ProgramResult {
+    sell_pixel_sol(program_id, accounts, index, price, true)
+}
+
+pub fn process_accept_sol_bid(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    index: u32,
+    min_price: u64,
+) -> ProgramResult {
+    sell_pixel_sol(program_id, accounts, index, min_price, false)
+}
+
+/// Same as `sell_pixel`, for SOL listings and bids.
+fn sell_pixel_sol(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    index: u32,
+    price: u64,
+    allow_listing: bool,
+) -> ProgramResult {
+    check_pixel_index(index)?;
+
+    let AcceptSolBidAccounts {
+        program,
+        canvas_config,
+        canvas_stats_shard,
+        system_program: system_program_sysvar,
+        pixel_account,
+        fee_vault,
+        trade_pool,
+        pixel_owner,
+    } = AcceptSolBidAccounts::load(program_id, accounts)?;
+
+    //
+
+    let canvas_config_state = load_canvas_config(canvas_config)?;
+    check_trade_pool(program_id, trade_pool, &canvas_config_state)?;
+    check_fee_vault(program_id, fee_vault, &canvas_config_state)?;
+    let mut canvas_stats_shard_state = load_canvas_stats_shard(canvas_stats_shard, index)?;
+    let mut pixel_account_state = load_pixel(program_id, pixel_account, index)?;
+    canvas_stats_shard_state.remove_pixel(&pixel_account_state);
+
+    //
+
+    canvas_config_state.check_not_paused()?;
+    if pixel_owner.key != &pixel_account_state.owner_wallet {
+        return Err(NftCanvasError::PixelOwnerKeyMismatch.into());
+    }
+
+    //
+
+    if let Some(best_buy_info) = pixel_account_state.best_sol_buy_info.as_ref()
+        .filter(|best_buy_info| (!allow_listing || price != 0) && best_buy_info.price >= price) {
+        // Process sell :
+        let amount_split = config::TradeAmountSplit::split(best_buy_info.price);
+        log_trade_receipt(index, best_buy_info.price, &amount_split);
+        canvas_stats_shard_state.record_sol_sale(best_buy_info.price, amount_split.to_team, Clock::get()?.slot);
+        // - Transfer sell_price - tax to seller
+        invoke_signed(
+            &system_instruction::transfer(
+                trade_pool.key, pixel_owner.key, amount_split.to_seller),
+            &[trade_pool.clone(), pixel_owner.clone(), system_program_sysvar.clone()],
+            &[
+                trade_pool_seeds!(program.key, canvas_config_state.trade_pool_bump_seed),
+            ],
+        )?;
+        // - Transfer tax to fee vault
+        invoke_signed(
+            &system_instruction::transfer(
+                trade_pool.key, fee_vault.key, amount_split.to_team),
+            &[trade_pool.clone(), fee_vault.clone(), system_program_sysvar.clone()],
+            &[
+                trade_pool_seeds!(program.key, canvas_config_state.trade_pool_bump_seed),
+            ],
+        )?;
+        // - Update pixel owner
+        pixel_account_state.transfer_ownership(best_buy_info.buyer_wallet);
+        pixel_account_state.best_sol_buy_info = None;
+    } else if allow_listing {
+        // Set sell price :
+        // - Update pixel sell price
+        pixel_account_state.sol_sell_price = price;
+    } else {
+        return Err(NftCanvasError::NoAcceptableBid.into());
+    }
+    canvas_stats_shard_state.add_pixel(&pixel_account_state);
+    pixel_account_state.pack_into_account(&mut pixel_account.data.borrow_mut())?;
+    canvas_stats_shard_state.pack_into(&mut canvas_stats_shard.data.borrow_mut())?;
+
+    Ok(())
+}
+
+pub fn process_buy_pixel_sol(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    index: u32,
+    price: u64,
+    direct_only: u8,
+) -> ProgramResult {
+    check_pixel_index(index)?;
+
+    let (BuyPixelSolAccounts {
+        program,
+        canvas_config,
+        canvas_stats_shard,
+        system_program: system_program_sysvar,
+        pixel_account,
+        fee_vault,
+        trade_pool,
+        pixel_owner,
+        buyer_wallet,
+    }, previous_buyer_accounts) = BuyPixelSolAccounts::load(program_id, accounts)?;
+    let accounts_iter = &mut previous_buyer_accounts.iter();
+
+    //
+
+    let canvas_config_state = load_canvas_config(canvas_config)?;
+    check_trade_pool(program_id, trade_pool, &canvas_config_state)?;
+    check_fee_vault(program_id, fee_vault, &canvas_config_state)?;
+    let mut canvas_stats_shard_state = load_canvas_stats_shard(canvas_stats_shard, index)?;
+    let mut pixel_account_state = load_pixel(program_id, pixel_account, index)?;
+    canvas_stats_shard_state.remove_pixel(&pixel_account_state);
+
+    //
+
+    canvas_config_state.check_not_paused()?;
+    if pixel_owner.key != &pixel_account_state.owner_wallet {
+        return Err(NftCanvasError::PixelOwnerKeyMismatch.into());
+    }
+
+    //
+
+    if pixel_account_state.sol_sell_price != 0 && price >= pixel_account_state.sol_sell_price {
+        // Process buy (at the listed price, `price` only caps it) :
+        let amount_split = config::TradeAmountSplit::split(pixel_account_state.sol_sell_price);
+        log_trade_receipt(index, pixel_account_state.sol_sell_price, &amount_split);
+        canvas_stats_shard_state.record_sol_sale(pixel_account_state.sol_sell_price, amount_split.to_team, Clock::get()?.slot);
+        // - Transfer sell_price - tax to seller
+        invoke(
+            &system_instruction::transfer(
+                buyer_wallet.key, pixel_owner.key, amount_split.to_seller),
+            &[buyer_wallet.clone(), pixel_owner.clone(), system_program_sysvar.clone()],
+        )?;
+        // - Transfer tax to fee vault
+        invoke(
+            &system_instruction::transfer(
+                buyer_wallet.key, fee_vault.key, amount_split.to_team),
+            &[buyer_wallet.clone(), fee_vault.clone(), system_program_sysvar.clone()],
+        )?;
+        // - Update pixel
+        pixel_account_state.transfer_ownership(*buyer_wallet.key);
+        // (If last best buyer is current buyer, refund and remove order)
+        if let Some(previous_buy_info) = pixel_account_state.best_sol_buy_info.as_ref()
+            .filter(|best_buy_info| &best_buy_info.buyer_wallet == buyer_wallet.key) {
+            invoke_signed(
+                &system_instruction::transfer(
+                    trade_pool.key, buyer_wallet.key, previous_buy_info.price),
+                &[trade_pool.clone(), buyer_wallet.clone(), system_program_sysvar.clone()],
+                &[
+                    trade_pool_seeds!(program.key, canvas_config_state.trade_pool_bump_seed),
+                ],
+            )?;
+            pixel_account_state.best_sol_buy_info = None;
+        }
+    } else if pixel_account_state.best_sol_buy_info.as_ref().map(|previous_buy_info| price > previous_buy_info.price).unwrap_or(true) {
+        if direct_only != 0 {
+            return Err(NftCanvasError::CouldNotDirectBuy.into());
+        }
+        // Is best buyer :
+        // - Refund previous best buyer
+        if let Some(previous_buy_info) = pixel_account_state.best_sol_buy_info {
+            let PreviousSolBuyerAccounts {
+                previous_sol_buyer_wallet: previous_buyer_wallet,
+            } = PreviousSolBuyerAccounts::load(accounts_iter, &previous_buy_info.buyer_wallet)?;
+            invoke_signed(
+                &system_instruction::transfer(
+                    trade_pool.key, previous_buyer_wallet.key, previous_buy_info.price),
+                &[trade_pool.clone(), previous_buyer_wallet.clone(), system_program_sysvar.clone()],
+                &[
+                    trade_pool_seeds!(program.key, canvas_config_state.trade_pool_bump_seed),
+                ],
+            )?;
+        }
+        // - Transfer buy_price to trade pool
+        invoke(
+            &system_instruction::transfer(
+                buyer_wallet.key, trade_pool.key, price),
+            &[buyer_wallet.clone(), trade_pool.clone(), system_program_sysvar.clone()],
+        )?;
+        // - Update pixel
+        pixel_account_state.best_sol_buy_info = Some(PixelBuyInfo {
+            price,
+            buyer_wallet: *buyer_wallet.key,
+        });
+    } else {
+        return Err(NftCanvasError::BuyPriceTooLow.into());
+    }
+
+    canvas_stats_shard_state.add_pixel(&pixel_account_state);
+    pixel_account_state.pack_into_account(&mut pixel_account.data.borrow_mut())?;
+    canvas_stats_shard_state.pack_into(&mut canvas_stats_shard.data.borrow_mut())?;
+
+    Ok(())
+}
+
+pub fn process_cancel_bid(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    index: u32,
+) -> ProgramResult {
+    check_pixel_index(index)?;
+
+    let CancelBidAccounts {
+        program,
+        canvas_config,
+        canvas_stats_shard,
+        token_program,
+        token_mint,
+        system_program: system_program_sysvar,
+        rent_sysvar,
+        associated_token_program,
+        pixel_account,
+        trade_pool,
+        trade_pool_token_account,
+        buyer_wallet,
+        buyer_token_account,
+    } = CancelBidAccounts::load(program_id, accounts)?;
+
+    //
+
+    // (Allowed while paused)
+    let canvas_config_state = load_canvas_config(canvas_config)?;
+    check_trade_pool(program_id, trade_pool, &canvas_config_state)?;
+    let mut canvas_stats_shard_state = load_canvas_stats_shard(canvas_stats_shard, index)?;
+    let mint_info = MintInfo::unpack(&token_mint.data.borrow(), Clock::get()?.epoch)?;
+    let token_account_programs = TokenAccountPrograms {
+        token_mint,
+        system_program: system_program_sysvar,
+        token_program,
+        rent_sysvar,
+        associated_token_program,
+    };
+    let mut pixel_account_state = load_pixel(program_id, pixel_account, index)?;
+    canvas_stats_shard_state.remove_pixel(&pixel_account_state);
+
+    //
+
+    let best_buy_info = pixel_account_state.best_buy_info.take()
+        .ok_or(NftCanvasError::NoOpenBid)?;
+    if buyer_wallet.key != &best_buy_info.buyer_wallet {
+        return Err(NftCanvasError::BidderKeyMismatch.into());
+    }
+
+    //
+
+    // Refund buyer
+    create_token_account_if_missing(buyer_wallet, buyer_token_account, buyer_wallet, &token_account_programs)?;
+    invoke_signed(
+        &token::transfer_checked(
+            token_program.key,
+            trade_pool_token_account.key,
+            token_mint.key,
+            buyer_token_account.key,
+            trade_pool.key,
+            best_buy_info.price,
+            mint_info.decimals,
+        )?,
+        &[trade_pool_token_account.clone(), token_mint.clone(), buyer_token_account.clone(), trade_pool.clone(), token_program.clone()],
+        &[
+            trade_pool_seeds!(program.key, canvas_config_state.trade_pool_bump_seed),
+        ],
+    )?;
+    canvas_stats_shard_state.add_pixel(&pixel_account_state);
+    pixel_account_state.pack_into_account(&mut pixel_account.data.borrow_mut())?;
+    canvas_stats_shard_state.pack_into(&mut canvas_stats_shard.data.borrow_mut())?;
+
+    Ok(())
+}
+
+pub fn process_cancel_sol_bid(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    index: u32,
+) -> ProgramResult {
+    check_pixel_index(index)?;
+
+    let CancelSolBidAccounts {
+        program,
+        canvas_config,
+        canvas_stats_shard,
+        system_program: system_program_sysvar,
+        pixel_account,
+        trade_pool,
+        buyer_wallet,
+    } = CancelSolBidAccounts::load(program_id, accounts)?;
+
+    //
+
+    // (Allowed while paused)
+    let canvas_config_state = load_canvas_config(canvas_config)?;
+    check_trade_pool(program_id, trade_pool, &canvas_config_state)?;
+    let mut canvas_stats_shard_state = load_canvas_stats_shard(canvas_stats_shard, index)?;
+    let mut pixel_account_state = load_pixel(program_id, pixel_account, index)?;
+    canvas_stats_shard_state.remove_pixel(&pixel_account_state);
+
+    //
+
+    let best_buy_info = pixel_account_state.best_sol_buy_info.take()
+        .ok_or(NftCanvasError::NoOpenBid)?;
+    if buyer_wallet.key != &best_buy_info.buyer_wallet {
+        return Err(NftCanvasError::BidderKeyMismatch.into());
+    }
+
+    //
+
+    // Refund buyer
+    invoke_signed(
+        &system_instruction::transfer(
+            trade_pool.key, buyer_wallet.key, best_buy_info.price),
+        &[trade_pool.clone(), buyer_wallet.clone(), system_program_sysvar.clone()],
+        &[
+            trade_pool_seeds!(program.key, canvas_config_state.trade_pool_bump_seed),
+        ],
+    )?;
+    canvas_stats_shard_state.add_pixel(&pixel_account_state);
+    pixel_account_state.pack_into_account(&mut pixel_account.data.borrow_mut())?;
+    canvas_stats_shard_state.pack_into(&mut canvas_stats_shard.data.borrow_mut())?;
+
+    Ok(())
+}
+
+pub fn process_initialize_config(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let InitializeConfigAccounts {
+        program,
+        system_program: system_program_sysvar,
+        rent_sysvar,
+        canvas_config,
+        canvas_stats,
+        fee_vault,
+        trade_pool,
+        admin_wallet,
+        canvas_config_bump_seed,
+        canvas_stats_bump_seed,
+        trade_pool_bump_seed,
+        fee_vault_bump_seed,
+    } = InitializeConfigAccounts::load(program_id, accounts)?;
+
+    //
+
+    let rent_state = Rent::from_account_info(rent_sysvar)?;
+
+    // Create config account
+    create_program_account(
+        admin_wallet, canvas_config, system_program_sysvar, program.key, &rent_state, CanvasConfig::PACKED_SIZE,
+        canvas_config_seeds!(program.key, canvas_config_bump_seed),
+    )?;
+
+    // Initialize config account
+    CanvasConfig::new(*admin_wallet.key, trade_pool_bump_seed, fee_vault_bump_seed)
+        .pack_into(&mut canvas_config.data.borrow_mut())?;
+
+    // Create stats account
+    create_program_account(
+        admin_wallet, canvas_stats, system_program_sysvar, program.key, &rent_state, CanvasStats::PACKED_SIZE,
+        canvas_stats_seeds!(program.key, canvas_stats_bump_seed),
+    )?;
+
+    // Initialize stats account
+    CanvasStats::new()
+        .pack_into(&mut canvas_stats.data.borrow_mut())?;
+
+    // Fund the rent exempt reserves of the fee vault and trade pool (escrowed bids and collected fees come on top
+    // of it, and only those ever leave)
+    top_up(admin_wallet, fee_vault, system_program_sysvar, rent_state.minimum_balance(0))?;
+    top_up(admin_wallet, trade_pool, system_program_sysvar, rent_state.minimum_balance(0))?;
+
+    Ok(())
+}
+
+/// Creates the stats account of a canvas initialized before it existed. `minted` is the number of pixel accounts.
+pub fn process_initialize_stats(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    minted: u32,
+) -> ProgramResult {
+    let InitializeStatsAccounts {
+        program,
+        system_program: system_program_sysvar,
+        rent_sysvar,
+        canvas_config,
+        canvas_stats,
+        admin_wallet,
+        canvas_stats_bump_seed,
+    } = InitializeStatsAccounts::load(program_id, accounts)?;
+
+    //
+
+    let canvas_config_state = load_canvas_config(canvas_config)?;
+    let rent_state = Rent::from_account_info(rent_sysvar)?;
+
+    //
+
+    if admin_wallet.key != &canvas_config_state.admin {
+        return Err(NftCanvasError::AdminKeyMismatch.into());
+    }
+
+    // Create stats account (fails if it exists already)
+    create_program_account(
+        admin_wallet, canvas_stats, system_program_sysvar, program.key, &rent_state, CanvasStats::PACKED_SIZE,
+        canvas_stats_seeds!(program.key, canvas_stats_bump_seed),
+    )?;
+
+    // Initialize stats account
+    CanvasStats { minted, ..CanvasStats::new() }
+        .pack_into(&mut canvas_stats.data.borrow_mut())?;
+
+    Ok(())
+}
+
+/// Creates a stats shard account, seeded with the counts of the pixels of the shard already on the canvas.
+pub fn process_initialize_stats_shard(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    shard: u8,
+    listings: u32,
+    sol_listings: u32,
+    open_bids: u32,
+    open_sol_bids: u32,
+) -> ProgramResult {
+    if shard as u32 >= config::STATS_SHARDS {
+        return Err(NftCanvasError::CanvasStatsKeyMismatch.into());
+    }
+
+    let InitializeStatsShardAccounts {
+        program,
+        system_program: system_program_sysvar,
+        rent_sysvar,
+        canvas_config,
+        canvas_stats_shard,
+        admin_wallet,
+        canvas_stats_shard_bump_seed,
+    } = InitializeStatsShardAccounts::load(program_id, accounts, shard)?;
+
+    //
+
+    let canvas_config_state = load_canvas_config(canvas_config)?;
+    let rent_state = Rent::from_account_info(rent_sysvar)?;
+
+    //
+
+    if admin_wallet.key != &canvas_config_state.admin {
+        return Err(NftCanvasError::AdminKeyMismatch.into());
+    }
+
+    // Create stats shard account (fails if it exists already)
+    create_program_account(
+        admin_wallet, canvas_stats_shard, system_program_sysvar, program.key, &rent_state, CanvasStatsShard::PACKED_SIZE,
+        canvas_stats_shard_seeds!(program.key, shard, canvas_stats_shard_bump_seed),
+    )?;
+
+    // Initialize stats shard account
+    CanvasStatsShard { listings, sol_listings, open_bids, open_sol_bids, ..CanvasStatsShard::new(shard) }
+        .pack_into(&mut canvas_stats_shard.data.borrow_mut())?;
+
+    Ok(())
+}
+
+pub fn process_set_paused(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    paused: u8,
+    painting_paused: u8,
+) -> ProgramResult {
+    let SetConfigAccounts {
+        program: _,
+        canvas_config,
+        admin_wallet,
+    } = SetConfigAccounts::load(program_id, accounts)?;
+
+    //
+
+    let mut canvas_config_state = load_canvas_config(canvas_config)?;
+
+    //
+
+    if admin_wallet.key != &canvas_config_state.admin {
+        return Err(NftCanvasError::AdminKeyMismatch.into());
+    }
+
+    canvas_config_state.paused = paused != 0;
+    canvas_config_state.painting_paused = painting_paused != 0;
+    save_canvas_config(canvas_config, &mut canvas_config_state)?;
+
+    Ok(())
+}
+
+pub fn process_set_presale(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    presale: u8,
+    presale_price: u64,
+    presale_quota: u32,
+    allowlist_root: &[[u8; 32]],
+) -> ProgramResult {
+    let SetConfigAccounts {
+        program: _,
+        canvas_config,
+        admin_wallet,
+    } = SetConfigAccounts::load(program_id, accounts)?;
+
+    //
+
+    let mut canvas_config_state = load_canvas_config(canvas_config)?;
+
+    //
+
+    if admin_wallet.key != &canvas_config_state.admin {
+        return Err(NftCanvasError::AdminKeyMismatch.into());
+    }
+    if allowlist_root.len() != 1 {
+        return Err(NftCanvasError::FailedToUnpackData.into());
+    }
+
+    canvas_config_state.presale = presale != 0;
+    canvas_config_state.presale_price = presale_price;
+    canvas_config_state.presale_quota = presale_quota;
+    canvas_config_state.allowlist_root = allowlist_root[0];
+    save_canvas_config(canvas_config, &mut canvas_config_state)?;
+
+    Ok(())
+}
+
+pub fn process_set_mint_curve(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    mint_base_price: u64,
+    mint_price_increment: u32,
+) -> ProgramResult {
+    let SetConfigAccounts {
+        program: _,
+        canvas_config,
+        admin_wallet,
+    } = SetConfigAccounts::load(program_id, accounts)?;
+
+    //
+
+    let mut canvas_config_state = load_canvas_config(canvas_config)?;
+
+    //
+
+    if admin_wallet.key != &canvas_config_state.admin {
+        return Err(NftCanvasError::AdminKeyMismatch.into());
+    }
+
+    canvas_config_state.mint_base_price = mint_base_price;
+    canvas_config_state.mint_price_increment = mint_price_increment;
+    save_canvas_config(canvas_config, &mut canvas_config_state)?;
+
+    Ok(())
+}
+
+pub fn process_set_color_mode(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    color_mode: ColorMode,
+    palette: &[[u8; 3]],
+) -> ProgramResult {
+    let SetConfigAccounts {
+        program: _,
+        canvas_config,
+        admin_wallet,
+    } = SetConfigAccounts::load(program_id, accounts)?;
+
+    //
+
+    let mut canvas_config_state = load_canvas_config(canvas_config)?;
+
+    //
+
+    if admin_wallet.key != &canvas_config_state.admin {
+        return Err(NftCanvasError::AdminKeyMismatch.into());
+    }
+
+    canvas_config_state.set_color_mode(color_mode, palette)?;
+    save_canvas_config(canvas_config, &mut canvas_config_state)?;
+
+    Ok(())
+}
+
+pub fn process_withdraw_fees(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let WithdrawFeesAccounts {
+        program,
+        canvas_config,
+        system_program: system_program_sysvar,
+        token_program,
+        token_mint,
+        fee_vault,
+        fee_vault_token_account,
+        admin_wallet,
+        destination_wallet,
+        destination_token_account,
+    } = WithdrawFeesAccounts::load(program_id, accounts)?;
+
+    //
+
+    let canvas_config_state = load_canvas_config(canvas_config)?;
+    check_fee_vault(program_id, fee_vault, &canvas_config_state)?;
+    let mint_info = MintInfo::unpack(&token_mint.data.borrow(), Clock::get()?.epoch)?;
+
+    //
+
+    if admin_wallet.key != &canvas_config_state.admin {
+        return Err(NftCanvasError::AdminKeyMismatch.into());
+    }
+
+    //
+
+    // Sweep sol (keeping the vault rent exempt)
+    let lamports = fee_vault.lamports().saturating_sub(Rent::get()?.minimum_balance(0));
+    if lamports > 0 {
+        invoke_signed(
+            &system_instruction::transfer(
+                fee_vault.key, destination_wallet.key, lamports),
+            &[fee_vault.clone(), destination_wallet.clone(), system_program_sysvar.clone()],
+            &[
+                fee_vault_seeds!(program.key, canvas_config_state.fee_vault_bump_seed),
+            ],
+        )?;
+    }
+
+    // Sweep tokens
+    let amount = if fee_vault_token_account.data_is_empty() {
+        0
+    } else {
+        token::token_account_amount(&fee_vault_token_account.data.borrow())?
+    };
+    if amount > 0 {
+        invoke_signed(
+            &token::transfer_checked(
+                token_program.key,
+                fee_vault_token_account.key,
+                token_mint.key,
+                destination_token_account.key,
+                fee_vault.key,
+                amount,
+                mint_info.decimals,
+            )?,
+            &[fee_vault_token_account.clone(), token_mint.clone(), destination_token_account.clone(), fee_vault.clone(), token_program.clone()],
+            &[
+                fee_vault_seeds!(program.key, canvas_config_state.fee_vault_bump_seed),
+            ],
+        )?;
+    }
+
+    Ok(())
+}
+
+// Misc
+
+/// Loads the canvas config, checking it is initialized. The instruction accounts only check the program owns it,
+/// as no other program account has its size.
+fn load_canvas_config(canvas_config: &AccountInfo) -> Result<CanvasConfig, ProgramError> {
+    let canvas_config_state = CanvasConfig::unpack(&canvas_config.data.borrow())
+        .map_err(|_| NftCanvasError::CanvasConfigUninitialized)?;
+    if !canvas_config_state.is_initialized {
+        return Err(NftCanvasError::CanvasConfigUninitialized.into());
+    }
+    Ok(canvas_config_state)
+}
+
+/// Writes the canvas config back, in the layout of this version of the program.
+fn save_canvas_config(canvas_config: &AccountInfo, canvas_config_state: &mut CanvasConfig) -> ProgramResult {
+    canvas_config_state.version = CanvasConfig::VERSION;
+    canvas_config_state.pack_into(&mut canvas_config.data.borrow_mut())?;
+    Ok(())
+}
+
+/// Checks `trade_pool` is the trade pool address, from the bump seed stored in the canvas config.
+fn check_trade_pool(program_id: &Pubkey, trade_pool: &AccountInfo, canvas_config_state: &CanvasConfig) -> ProgramResult {
+    if config::create_trade_pool_address(program_id, canvas_config_state.trade_pool_bump_seed) != Ok(*trade_pool.key) {
+        return Err(NftCanvasError::TradePoolKeyMismatch.into());
+    }
+    Ok(())
+}
+
+/// Checks `fee_vault` is the fee vault address, from the bump seed stored in the canvas config.
+fn check_fee_vault(program_id: &Pubkey, fee_vault: &AccountInfo, canvas_config_state: &CanvasConfig) -> ProgramResult {
+    if config::create_fee_vault_address(program_id, canvas_config_state.fee_vault_bump_seed) != Ok(*fee_vault.key) {
+        return Err(NftCanvasError::FeeVaultKeyMismatch.into());
+    }
+    Ok(())
+}
+
+/// Loads the canvas stats, checking they are initialized. Like the config, they are told apart from the other program
+/// accounts by their size.
+fn load_canvas_stats(canvas_stats: &AccountInfo) -> Result<CanvasStats, ProgramError> {
+    let canvas_stats_state = CanvasStats::unpack(&canvas_stats.data.borrow())
+        .map_err(|_| NftCanvasError::CanvasStatsUninitialized)?;
+    if !canvas_stats_state.is_initialized {
+        return Err(NftCanvasError::CanvasStatsUninitialized.into());
+    }
+    Ok(canvas_stats_state)
+}
+
+/// Loads the stats shard counting the pixel at `index`. The instruction accounts check the program owns the account,
+/// which is then told apart from the other program accounts by its size, and from the other shards by its data.
+fn load_canvas_stats_shard(canvas_stats_shard: &AccountInfo, index: u32) -> Result<CanvasStatsShard, ProgramError> {
+    let canvas_stats_shard_state = CanvasStatsShard::unpack(&canvas_stats_shard.data.borrow())
+        .map_err(|_| NftCanvasError::CanvasStatsKeyMismatch)?;
+    if !canvas_stats_shard_state.is_initialized || canvas_stats_shard_state.shard != config::stats_shard(index) {
+        return Err(NftCanvasError::CanvasStatsKeyMismatch.into());
+    }
+    Ok(canvas_stats_shard_state)
+}
+
+/// Loads a minted pixel, checking the program owns its account, it holds the pixel at `index` and its address
+/// matches the stored bump seed.
+fn load_pixel(program_id: &Pubkey, pixel_account: &AccountInfo, index: u32) -> Result<Pixel, ProgramError> {
+    // (Never minted, or closed: the runtime purges accounts left without lamports)
+    if pixel_account.data_is_empty() || pixel_account.lamports() == 0 {
+        return Err(NftCanvasError::PixelNotMinted.into());
+    }
+    if pixel_account.owner != program_id {
+        return Err(NftCanvasError::PixelAccountOwnerMismatch.into());
+    }
+    let pixel_account_state = Pixel::unpack_account(program_id, &pixel_account.data.borrow())
+        .map_err(|_| NftCanvasError::PixelUninitialized)?;
+    if !pixel_account_state.is_initialized() || pixel_account_state.index != index {
+        return Err(NftCanvasError::PixelUninitialized.into());
+    }
+    if config::create_pixel_address(program_id, index, pixel_account_state.bump_seed) != Ok(*pixel_account.key) {
+        return Err(NftCanvasError::PixelAccountKeyMismatch.into());
+    }
+    Ok(pixel_account_state)
+}
+
+/// Transfers to `account` whatever it lacks to hold `lamports`.
+fn top_up<'a>(
+    funder: &AccountInfo<'a>,
+    account: &AccountInfo<'a>,
+    system_program_sysvar: &AccountInfo<'a>,
+    lamports: u64,
+) -> ProgramResult {
+    let shortfall = lamports.saturating_sub(account.lamports());
+    if shortfall > 0 {
+        invoke(
+            &system_instruction::transfer(funder.key, account.key, shortfall),
+            &[funder.clone(), account.clone(), system_program_sysvar.clone()],
+        )?;
+    }
+    Ok(())
+}
+
+/// Creates the `space` bytes account `account` owned by the program at a program address, even when lamports
+/// were already sent to the address (which makes `system_instruction::create_account` fail).
+fn create_program_account<'a>(
+    funder: &AccountInfo<'a>,
+    account: &AccountInfo<'a>,
+    system_program_sysvar: &AccountInfo<'a>,
+    program_id: &Pubkey,
+    rent_state: &Rent,
+    space: usize,
+    signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    let lamports = rent_state.minimum_balance(space).max(1);
+    if account.lamports() == 0 {
+        return invoke_signed(
+            &system_instruction::create_account(funder.key, account.key, lamports, space as u64, program_id),
+            &[funder.clone(), account.clone(), system_program_sysvar.clone()],
+            &[signer_seeds],
+        );
+    }
+
+    // - Top up to the rent exempt balance
+    top_up(funder, account, system_program_sysvar, lamports)?;
+    // - Allocate and assign, which the address signs for
+    invoke_signed(
+        &system_instruction::allocate(account.key, space as u64),
+        &[account.clone(), system_program_sysvar.clone()],
+        &[signer_seeds],
+    )?;
+    invoke_signed(
+        &system_instruction::assign(account.key, program_id),
+        &[account.clone(), system_program_sysvar.clone()],
+        &[signer_seeds],
+    )
+}
+
+/// Logs what a trade actually moved, so clients can read it back from the transaction logs.
+fn log_trade_receipt(index: u32, price: u64, amount_split: &config::TradeAmountSplit) {
+    // (The trailing comma keeps `msg!` from taking five arguments as `sol_log_64` numbers)
+    msg!("Trade receipt: index={}, price={}, to_seller={}, to_team={}",
+        index, price, amount_split.to_seller, amount_split.to_team,);
+}
+
+/// The accounts `create_token_account_if_missing` passes on to the associated token account program.
+struct TokenAccountPrograms<'r, 'a> {
+    token_mint: &'r AccountInfo<'a>,
+    system_program: &'r AccountInfo<'a>,
+    token_program: &'r AccountInfo<'a>,
+    rent_sysvar: &'r AccountInfo<'a>,
+    associated_token_program: &'r AccountInfo<'a>,
+}
+
+/// Creates the associated token account `token_account` of `wallet`, unless it already exists.
+///
+/// The associated token account program we build against has no idempotent create, but this is equivalent: only
+/// that program can sign for the address, and it only ever puts the token account of `wallet` for the mint
+/// there, so an address holding data holds that account. (The token transfers that follow check its mint and
+/// owner again.) An address that was only sent lamports is still created, the program tops it up.
+fn create_token_account_if_missing<'a>(
+    funder: &AccountInfo<'a>,
+    token_account: &AccountInfo<'a>,
+    wallet: &AccountInfo<'a>,
+    programs: &TokenAccountPrograms<'_, 'a>,
+) -> ProgramResult {
+    if !token_account.data_is_empty() {
+        return Ok(());
+    }
+    invoke(
+        &token::create_associated_token_account(
+            funder.key, token_account.key, wallet.key, programs.token_mint.key, programs.token_program.key),
+        &[
+            funder.clone(),
+            token_account.clone(),
+            wallet.clone(),
+            programs.token_mint.clone(),
+            programs.system_program.clone(),
+            programs.token_program.clone(),
+            programs.rent_sysvar.clone(),
+            programs.associated_token_program.clone(),
+        ],
+    )
+}
 
 fn check_pixel_index(index: u32) -> ProgramResult {
     if index < config::PIXEL_COUNT {
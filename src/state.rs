@@ -3,28 +3,125 @@ use borsh::BorshSchema;
 use borsh::BorshSerialize;
 use solana_program::pubkey::Pubkey;
 
+use crate::color;
+use crate::color::{ColorMode, PixelColor};
+use crate::config;
+use crate::error::NftCanvasError;
 use crate::packable::Packable;
 
 #[repr(C)]
 #[derive(Clone, Debug, Default, PartialEq, BorshSerialize, BorshDeserialize, BorshSchema)]
 pub struct Pixel {
     pub index: u32,
-    pub color: [u8; 3],
+    /// RGBA, opaque unless the canvas is in `ColorMode::Rgba`.
+    pub color: [u8; 4],
     pub owner_wallet: Pubkey,
     pub sell_price: u64,
     pub best_buy_info: Option<PixelBuyInfo>,
+    pub color_delegate: Option<Pubkey>,
+    pub sol_sell_price: u64,
+    pub best_sol_buy_info: Option<PixelBuyInfo>,
+    /// Bump seed of the pixel address, so it can be checked without `config::get_pixel`.
+    pub bump_seed: u8,
 }
 
-implement_packable!(Pixel, 88);
+implement_packable!(Pixel, 172);
 
 impl Pixel {
-    pub fn new(index: u32, color: [u8; 3], owner_wallet: Pubkey, sell_price: u64) -> Pixel {
+    pub fn new(index: u32, color: [u8; 4], owner_wallet: Pubkey, sell_price: u64, bump_seed: u8) -> Pixel {
         Pixel {
             index,
             color,
             owner_wallet,
             sell_price,
             best_buy_info: None,
+            color_delegate: None,
+            sol_sell_price: 0,
+            best_sol_buy_info: None,
+            bump_seed,
+        }
+    }
+
+    /// Hands the pixel over to `new_owner`, clearing the previous owner's listings and color delegate.
+    /// Open bids are left untouched.
+    pub fn transfer_ownership(&mut self, new_owner: Pubkey) {
+        self.owner_wallet = new_owner;
+        self.sell_price = 0;
+        self.sol_sell_price = 0;
+        self.color_delegate = None;
+    }
+
+    pub fn is_color_authority(&self, wallet: &Pubkey) -> bool {
+        &self.owner_wallet == wallet || self.color_delegate.as_ref() == Some(wallet)
+    }
+
+    /// Closed pixels are zeroed, which leaves them without an owner.
+    pub fn is_initialized(&self) -> bool {
+        self.owner_wallet != Pubkey::default()
+    }
+
+    /// Unpacks a pixel account in either layout, the account length telling which.
+    /// Legacy pixels get their bump seed from `config::get_pixel`.
+    pub fn unpack_account(program_id: &Pubkey, data: &[u8]) -> Result<Pixel, NftCanvasError> {
+        if data.len() == LegacyPixel::PACKED_SIZE {
+            let legacy_pixel = LegacyPixel::unpack(data)?;
+            let bump_seed = config::get_pixel(program_id, legacy_pixel.index).1;
+            Ok(legacy_pixel.upgrade(bump_seed))
+        } else {
+            Pixel::unpack(data)
+        }
+    }
+
+    /// Packs into a pixel account of either layout. Legacy accounts only take what `to_legacy` keeps.
+    pub fn pack_into_account(&self, data: &mut [u8]) -> Result<(), NftCanvasError> {
+        if data.len() == LegacyPixel::PACKED_SIZE {
+            self.to_legacy()
+                .ok_or(NftCanvasError::LegacyPixelLayout)?
+                .pack_into(data)
+        } else {
+            self.pack_into(data)
+        }
+    }
+
+    /// The pixel in the legacy layout, unless it is translucent, has a color delegate or is on the SOL market.
+    pub fn to_legacy(&self) -> Option<LegacyPixel> {
+        if self.color[3] != color::OPAQUE
+            || self.color_delegate.is_some()
+            || self.sol_sell_price != 0
+            || self.best_sol_buy_info.is_some() {
+            return None;
+        }
+        Some(LegacyPixel {
+            index: self.index,
+            color: [self.color[0], self.color[1], self.color[2]],
+            owner_wallet: self.owner_wallet,
+            sell_price: self.sell_price,
+            best_buy_info: self.best_buy_info.clone(),
+        })
+    }
+}
+
+/// Layout of the pixels minted before color delegates, the SOL market, RGBA colors and stored bump seeds.
+/// Accounts cannot be resized, so these pixels keep it: they are read and written back in it,
+/// and instructions needing more than it holds fail with `NftCanvasError::LegacyPixelLayout`.
+/// Closing and minting such a pixel again gives it the current layout.
+#[repr(C)]
+#[derive(Clone, Debug, Default, PartialEq, BorshSerialize, BorshDeserialize, BorshSchema)]
+pub struct LegacyPixel {
+    pub index: u32,
+    pub color: [u8; 3],
+    pub owner_wallet: Pubkey,
+    pub sell_price: u64,
+    pub best_buy_info: Option<PixelBuyInfo>,
+}
+
+implement_packable!(LegacyPixel, 88);
+
+impl LegacyPixel {
+    pub fn upgrade(self, bump_seed: u8) -> Pixel {
+        Pixel {
+            best_buy_info: self.best_buy_info,
+            ..Pixel::new(self.index, color::opaque(self.color), self.owner_wallet, self.sell_price, bump_seed)
         }
     }
 }
@@ -34,4 +131,257 @@ impl Pixel {
 pub struct PixelBuyInfo {
     pub price: u64,
     pub buyer_wallet: Pubkey,
-}
\ No newline at end of file
+}
+
+/// Canvas wide settings, stored in the `config::get_canvas_config` account.
+///
+/// Accounts cannot be resized, so the account is larger than the fields: new fields go at the end, where configs
+/// written by older versions of the program hold zeros, and `version` tells which fields a config was written with.
+#[repr(C)]
+#[derive(Clone, Debug, Default, PartialEq, BorshSerialize, BorshDeserialize, BorshSchema)]
+pub struct CanvasConfig {
+    pub is_initialized: bool,
+    /// `CanvasConfig::VERSION` of the program that last wrote the config.
+    pub version: u8,
+    pub admin: Pubkey,
+    /// Stops minting, listing, buying, bidding and selling. Bid cancellations and refunds still go through.
+    pub paused: bool,
+    /// Also stops color updates.
+    pub painting_paused: bool,
+    /// While set, only wallets in the allowlist can mint, at `presale_price`.
+    pub presale: bool,
+    pub presale_price: u64,
+    /// Maximum number of pixels each allowlisted wallet can mint during the presale.
+    pub presale_quota: u32,
+    /// Merkle root of the allowlist, see `allowlist::AllowlistTree`.
+    pub allowlist_root: [u8; 32],
+    /// Outside of the presale, the n-th mint costs `mint_base_price + n * mint_price_increment`.
+    pub mint_base_price: u64,
+    pub mint_price_increment: u32,
+    /// Bump seed of the trade pool address, so it can be checked without `config::get_trade_pool`.
+    pub trade_pool_bump_seed: u8,
+    pub color_mode: ColorMode,
+    /// Colors of `ColorMode::Palette`, the first `palette_len` are used.
+    pub palette_len: u8,
+    pub palette: [[u8; 3]; color::PALETTE_SIZE],
+    /// Bump seed of the fee vault address, so it can be checked without `config::get_fee_vault`.
+    pub fee_vault_bump_seed: u8,
+}
+
+implement_packable!(CanvasConfig, 512);
+
+impl CanvasConfig {
+    pub const VERSION: u8 = 1;
+
+    pub fn new(admin: Pubkey, trade_pool_bump_seed: u8, fee_vault_bump_seed: u8) -> CanvasConfig {
+        CanvasConfig {
+            is_initialized: true,
+            version: CanvasConfig::VERSION,
+            admin,
+            paused: false,
+            painting_paused: false,
+            presale: false,
+            presale_price: 0,
+            presale_quota: 0,
+            allowlist_root: [0u8; 32],
+            mint_base_price: config::MINT_COST,
+            mint_price_increment: 0,
+            trade_pool_bump_seed,
+            color_mode: ColorMode::Rgb,
+            palette_len: 0,
+            palette: [[0u8; 3]; color::PALETTE_SIZE],
+            fee_vault_bump_seed,
+        }
+    }
+
+    pub fn palette(&self) -> &[[u8; 3]] {
+        &self.palette[..(self.palette_len as usize).min(color::PALETTE_SIZE)]
+    }
+
+    /// The palette must be given in `ColorMode::Palette`, and only then.
+    pub fn set_color_mode(&mut self, color_mode: ColorMode, palette: &[[u8; 3]]) -> Result<(), NftCanvasError> {
+        let is_valid = match color_mode {
+            ColorMode::Rgb | ColorMode::Rgba => palette.is_empty(),
+            ColorMode::Palette => !palette.is_empty() && palette.len() <= color::PALETTE_SIZE,
+        };
+        if !is_valid {
+            return Err(NftCanvasError::InvalidPalette);
+        }
+        self.color_mode = color_mode;
+        self.palette_len = palette.len() as u8;
+        self.palette = [[0u8; 3]; color::PALETTE_SIZE];
+        self.palette[..palette.len()].copy_from_slice(palette);
+        Ok(())
+    }
+
+    /// The RGBA color a pixel gets for `color`, if the color mode allows it.
+    pub fn resolve_color(&self, color: &PixelColor) -> Result<[u8; 4], NftCanvasError> {
+        match (self.color_mode, *color) {
+            (ColorMode::Rgb, PixelColor::Rgb(rgb)) | (ColorMode::Rgba, PixelColor::Rgb(rgb)) => Ok(color::opaque(rgb)),
+            (ColorMode::Rgba, PixelColor::Rgba(rgba)) => Ok(rgba),
+            (ColorMode::Palette, PixelColor::Palette(index)) => self.palette().get(index as usize)
+                .map(|rgb| color::opaque(*rgb))
+                .ok_or(NftCanvasError::ColorNotInPalette),
+            _ => Err(NftCanvasError::ColorModeMismatch),
+        }
+    }
+
+    /// Price of the next mint, once `minted` pixels were minted.
+    pub fn mint_price(&self, minted: u32) -> u64 {
+        if self.presale {
+            self.presale_price
+        } else {
+            self.mint_base_price.saturating_add(self.mint_price_increment as u64 * minted as u64)
+        }
+    }
+
+    pub fn check_not_paused(&self) -> Result<(), NftCanvasError> {
+        if self.paused {
+            Err(NftCanvasError::Paused)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn check_painting_not_paused(&self) -> Result<(), NftCanvasError> {
+        if self.painting_paused {
+            Err(NftCanvasError::PaintingPaused)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Canvas wide mint counters, stored in the `config::get_canvas_stats` account.
+/// Only minting and closing pixels write it: the market counters are kept in `CanvasStatsShard` accounts, so that
+/// trades on pixels of different shards do not write lock the same account.
+#[repr(C)]
+#[derive(Clone, Debug, Default, PartialEq, BorshSerialize, BorshDeserialize, BorshSchema)]
+pub struct CanvasStats {
+    pub is_initialized: bool,
+    /// Pixels currently minted (closing a pixel decrements it).
+    pub minted: u32,
+    /// Mint fees sent to the fee vault, including withdrawn ones.
+    pub mint_fees: u64,
+}
+
+implement_packable!(CanvasStats, 13);
+
+impl CanvasStats {
+    pub fn new() -> CanvasStats {
+        CanvasStats {
+            is_initialized: true,
+            ..CanvasStats::default()
+        }
+    }
+
+    pub fn record_mint(&mut self, mint_cost: u64) {
+        self.minted = self.minted.saturating_add(1);
+        self.mint_fees = self.mint_fees.saturating_add(mint_cost);
+    }
+
+    pub fn record_close(&mut self) {
+        self.minted = self.minted.saturating_sub(1);
+    }
+}
+
+/// Market counters of the pixels in one shard (`config::stats_shard`), stored in the
+/// `config::get_canvas_stats_shard` account. Updated by every instruction that mints, closes, lists, bids on or
+/// trades a pixel. Dashboards `merge` the shards for canvas wide numbers.
+#[repr(C)]
+#[derive(Clone, Debug, Default, PartialEq, BorshSerialize, BorshDeserialize, BorshSchema)]
+pub struct CanvasStatsShard {
+    pub is_initialized: bool,
+    pub shard: u8,
+    pub listings: u32,
+    pub sol_listings: u32,
+    pub open_bids: u32,
+    pub open_sol_bids: u32,
+    pub volume: u64,
+    pub sol_volume: u64,
+    /// Trade fees sent to the fee vault, including withdrawn ones.
+    pub fees: u64,
+    pub sol_fees: u64,
+    pub last_sale_price: u64,
+    pub last_sale_slot: u64,
+    pub last_sol_sale_price: u64,
+    pub last_sol_sale_slot: u64,
+}
+
+implement_packable!(CanvasStatsShard, 82);
+
+impl CanvasStatsShard {
+    pub fn new(shard: u8) -> CanvasStatsShard {
+        CanvasStatsShard {
+            is_initialized: true,
+            shard,
+            ..CanvasStatsShard::default()
+        }
+    }
+
+    /// Counts `pixel` in, once minted or updated.
+    pub fn add_pixel(&mut self, pixel: &Pixel) {
+        self.listings = self.listings.saturating_add((pixel.sell_price != 0) as u32);
+        self.sol_listings = self.sol_listings.saturating_add((pixel.sol_sell_price != 0) as u32);
+        self.open_bids = self.open_bids.saturating_add(pixel.best_buy_info.is_some() as u32);
+        self.open_sol_bids = self.open_sol_bids.saturating_add(pixel.best_sol_buy_info.is_some() as u32);
+    }
+
+    /// Counts `pixel` out, before it is updated or closed.
+    pub fn remove_pixel(&mut self, pixel: &Pixel) {
+        self.listings = self.listings.saturating_sub((pixel.sell_price != 0) as u32);
+        self.sol_listings = self.sol_listings.saturating_sub((pixel.sol_sell_price != 0) as u32);
+        self.open_bids = self.open_bids.saturating_sub(pixel.best_buy_info.is_some() as u32);
+        self.open_sol_bids = self.open_sol_bids.saturating_sub(pixel.best_sol_buy_info.is_some() as u32);
+    }
+
+    pub fn record_sale(&mut self, price: u64, fee: u64, slot: u64) {
+        self.volume = self.volume.saturating_add(price);
+        self.fees = self.fees.saturating_add(fee);
+        self.last_sale_price = price;
+        self.last_sale_slot = slot;
+    }
+
+    pub fn record_sol_sale(&mut self, price: u64, fee: u64, slot: u64) {
+        self.sol_volume = self.sol_volume.saturating_add(price);
+        self.sol_fees = self.sol_fees.saturating_add(fee);
+        self.last_sol_sale_price = price;
+        self.last_sol_sale_slot = slot;
+    }
+
+    /// Adds up the counters of `other`, keeping the latest of the last sales.
+    pub fn merge(&mut self, other: &CanvasStatsShard) {
+        self.listings = self.listings.saturating_add(other.listings);
+        self.sol_listings = self.sol_listings.saturating_add(other.sol_listings);
+        self.open_bids = self.open_bids.saturating_add(other.open_bids);
+        self.open_sol_bids = self.open_sol_bids.saturating_add(other.open_sol_bids);
+        self.volume = self.volume.saturating_add(other.volume);
+        self.sol_volume = self.sol_volume.saturating_add(other.sol_volume);
+        self.fees = self.fees.saturating_add(other.fees);
+        self.sol_fees = self.sol_fees.saturating_add(other.sol_fees);
+        if other.last_sale_slot > self.last_sale_slot {
+            self.last_sale_price = other.last_sale_price;
+            self.last_sale_slot = other.last_sale_slot;
+        }
+        if other.last_sol_sale_slot > self.last_sol_sale_slot {
+            self.last_sol_sale_price = other.last_sol_sale_price;
+            self.last_sol_sale_slot = other.last_sol_sale_slot;
+        }
+    }
+}
+
+/// Counts presale mints of a wallet, stored in the `config::get_presale_receipt` account.
+#[repr(C)]
+#[derive(Clone, Debug, Default, PartialEq, BorshSerialize, BorshDeserialize, BorshSchema)]
+pub struct PresaleReceipt {
+    pub minted: u32,
+}
+
+implement_packable!(PresaleReceipt, 4);
+
+/// Client side quote of the next mint price, from the canvas config and canvas stats account data.
+pub fn quote_mint_price(canvas_config_data: &[u8], canvas_stats_data: &[u8]) -> Result<u64, NftCanvasError> {
+    let canvas_config = CanvasConfig::unpack(canvas_config_data)?;
+    let canvas_stats = CanvasStats::unpack(canvas_stats_data)?;
+    Ok(canvas_config.mint_price(canvas_stats.minted))
+}
@@ -0,0 +1,197 @@
+//! Token program interop, for both the original SPL Token program and Token-2022
+
+use solana_program::instruction::AccountMeta;
+use solana_program::instruction::Instruction;
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+use solana_program::sysvar;
+use spl_token::instruction::TokenInstruction;
+
+use crate::config;
+
+const MINT_LEN: usize = 82;
+const ACCOUNT_MINT_OFFSET: usize = 0;
+const ACCOUNT_OWNER_OFFSET: usize = 32;
+const ACCOUNT_AMOUNT_OFFSET: usize = 64;
+const MINT_DECIMALS_OFFSET: usize = 44;
+const MINT_IS_INITIALIZED_OFFSET: usize = 45;
+// Token-2022 pads mints to the token account size, then stores the account type followed by TLV extensions
+const ACCOUNT_TYPE_OFFSET: usize = 165;
+const ACCOUNT_TYPE_MINT: u8 = 1;
+const EXTENSION_TYPE_UNINITIALIZED: u16 = 0;
+const EXTENSION_TYPE_TRANSFER_FEE_CONFIG: u16 = 1;
+const TRANSFER_FEE_CONFIG_LEN: usize = 108;
+const OLDER_TRANSFER_FEE_OFFSET: usize = 72;
+const NEWER_TRANSFER_FEE_OFFSET: usize = 90;
+const MAX_FEE_BASIS_POINTS: u64 = 10_000;
+
+pub fn is_token_program(key: &Pubkey) -> bool {
+    key == &spl_token::id() || key == &config::token_2022_program::id()
+}
+
+/// Same as `spl_token::instruction::transfer_checked`, which only accepts the original token program.
+pub fn transfer_checked(
+    token_program: &Pubkey,
+    source: &Pubkey,
+    mint: &Pubkey,
+    destination: &Pubkey,
+    authority: &Pubkey,
+    amount: u64,
+    decimals: u8,
+) -> Result<Instruction, ProgramError> {
+    if !is_token_program(token_program) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let data = TokenInstruction::TransferChecked { amount, decimals }.pack();
+
+    let accounts = vec![
+        AccountMeta::new(*source, false),
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new(*destination, false),
+        AccountMeta::new_readonly(*authority, true),
+    ];
+
+    Ok(Instruction::new_with_bytes(*token_program, &data, accounts))
+}
+
+/// Same as `spl_associated_token_account::create_associated_token_account`, for either token program.
+/// The associated token account program fails if `token_account` already exists.
+pub fn create_associated_token_account(
+    funder: &Pubkey,
+    token_account: &Pubkey,
+    wallet: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*funder, true),
+        AccountMeta::new(*token_account, false),
+        AccountMeta::new_readonly(*wallet, false),
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+    ];
+
+    Instruction::new_with_bytes(spl_associated_token_account::id(), &[], accounts)
+}
+
+/// Whether a token account owned by either token program holds the token mint for `wallet`.
+pub fn is_token_account_of(data: &[u8], wallet: &Pubkey) -> bool {
+    data.get(ACCOUNT_MINT_OFFSET..ACCOUNT_MINT_OFFSET + 32) == Some(config::token_mint::id().as_ref())
+        && data.get(ACCOUNT_OWNER_OFFSET..ACCOUNT_OWNER_OFFSET + 32) == Some(wallet.as_ref())
+}
+
+/// Balance of a token account owned by either token program.
+pub fn token_account_amount(data: &[u8]) -> Result<u64, ProgramError> {
+    let mut amount = [0u8; 8];
+    amount.copy_from_slice(data.get(ACCOUNT_AMOUNT_OFFSET..ACCOUNT_AMOUNT_OFFSET + 8)
+        .ok_or(ProgramError::InvalidAccountData)?);
+    Ok(u64::from_le_bytes(amount))
+}
+
+/// The parts of a mint account the program needs to move its tokens.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MintInfo {
+    pub decimals: u8,
+    pub transfer_fee: TransferFee,
+}
+
+impl MintInfo {
+    /// Reads a mint owned by either token program. `epoch` selects which of the
+    /// two transfer fees scheduled by the Token-2022 transfer fee extension applies.
+    pub fn unpack(data: &[u8], epoch: u64) -> Result<MintInfo, ProgramError> {
+        if data.len() < MINT_LEN || data[MINT_IS_INITIALIZED_OFFSET] != 1 {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let decimals = data[MINT_DECIMALS_OFFSET];
+        let transfer_fee = match find_extension(data, EXTENSION_TYPE_TRANSFER_FEE_CONFIG)? {
+            Some(value) if value.len() == TRANSFER_FEE_CONFIG_LEN => {
+                let newer = TransferFee::unpack_epoch_fee(&value[NEWER_TRANSFER_FEE_OFFSET..]);
+                if epoch >= newer.0 {
+                    newer.1
+                } else {
+                    TransferFee::unpack_epoch_fee(&value[OLDER_TRANSFER_FEE_OFFSET..]).1
+                }
+            }
+            Some(_) => return Err(ProgramError::InvalidAccountData),
+            None => TransferFee::default(),
+        };
+        Ok(MintInfo { decimals, transfer_fee })
+    }
+}
+
+fn find_extension(data: &[u8], extension_type: u16) -> Result<Option<&[u8]>, ProgramError> {
+    if data.len() == MINT_LEN {
+        return Ok(None);
+    }
+    if data.len() <= ACCOUNT_TYPE_OFFSET || data[ACCOUNT_TYPE_OFFSET] != ACCOUNT_TYPE_MINT {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let mut offset = ACCOUNT_TYPE_OFFSET + 1;
+    while offset + 4 <= data.len() {
+        let current_type = u16::from_le_bytes([data[offset], data[offset + 1]]);
+        let length = u16::from_le_bytes([data[offset + 2], data[offset + 3]]) as usize;
+        if current_type == EXTENSION_TYPE_UNINITIALIZED {
+            break;
+        }
+        let value = data.get(offset + 4..offset + 4 + length)
+            .ok_or(ProgramError::InvalidAccountData)?;
+        if current_type == extension_type {
+            return Ok(Some(value));
+        }
+        offset += 4 + length;
+    }
+    Ok(None)
+}
+
+/// Token-2022 transfer fee, withheld from what the destination of a transfer receives.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TransferFee {
+    pub basis_points: u16,
+    pub maximum_fee: u64,
+}
+
+impl TransferFee {
+    fn unpack_epoch_fee(data: &[u8]) -> (u64, TransferFee) {
+        let mut epoch = [0u8; 8];
+        epoch.copy_from_slice(&data[0..8]);
+        let mut maximum_fee = [0u8; 8];
+        maximum_fee.copy_from_slice(&data[8..16]);
+        let basis_points = u16::from_le_bytes([data[16], data[17]]);
+        (u64::from_le_bytes(epoch), TransferFee { basis_points, maximum_fee: u64::from_le_bytes(maximum_fee) })
+    }
+
+    /// Fee withheld when sending `amount`.
+    pub fn calculate(&self, amount: u64) -> u64 {
+        if self.basis_points == 0 || amount == 0 {
+            return 0;
+        }
+        // (Rounded up, like the Token-2022 program does)
+        let numerator = amount as u128 * self.basis_points as u128;
+        let mut fee = numerator / MAX_FEE_BASIS_POINTS as u128;
+        if fee * (MAX_FEE_BASIS_POINTS as u128) < numerator {
+            fee += 1;
+        }
+        (fee as u64).min(self.maximum_fee)
+    }
+
+    /// Smallest amount to send so that the destination receives at least `net_amount`.
+    pub fn gross_up(&self, net_amount: u64) -> u64 {
+        if self.basis_points == 0 || net_amount == 0 {
+            return net_amount;
+        }
+        let mut amount = if self.basis_points as u64 >= MAX_FEE_BASIS_POINTS {
+            net_amount.saturating_add(self.maximum_fee)
+        } else {
+            let fee = (net_amount as u128 * self.basis_points as u128)
+                / (MAX_FEE_BASIS_POINTS - self.basis_points as u64) as u128;
+            net_amount.saturating_add((fee as u64).min(self.maximum_fee))
+        };
+        while amount - self.calculate(amount) < net_amount && amount < u64::MAX {
+            amount += 1;
+        }
+        amount
+    }
+}
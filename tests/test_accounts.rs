@@ -0,0 +1,218 @@
+use solana_program::account_info::AccountInfo;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::program_error::ProgramError;
+use solana_program::program_pack::Pack;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+use solana_program::sysvar;
+
+use chikin_nft_canvas::accounts::{AcceptBidAccounts, BuyPixelAccounts, MintPixelAccounts};
+use chikin_nft_canvas::color::PixelColor;
+use chikin_nft_canvas::config;
+use chikin_nft_canvas::error::NftCanvasError;
+use chikin_nft_canvas::instruction::NftCanvasInstruction;
+use spl_token::state::{Account as TokenAccount, AccountState};
+
+const INDEX: u32 = 7;
+
+/// Loads the accounts of `instruction` as the runtime would pass them, with the token accounts of `token_accounts`
+/// for their wallets. The other token accounts do not exist yet.
+fn load<F>(instruction: &Instruction, token_accounts: &[(Pubkey, Pubkey)], load: F) -> Result<(), ProgramError>
+where
+    F: for<'a, 'b> FnOnce(&'a [AccountInfo<'b>]) -> Result<(), ProgramError>,
+{
+    let program_accounts = [
+        config::get_canvas_config(&instruction.program_id).0,
+        config::get_canvas_stats(&instruction.program_id).0,
+        config::get_canvas_stats_shard(&instruction.program_id, config::stats_shard(INDEX)).0,
+    ];
+    let account = |key: &Pubkey| if key == &config::token_mint::id() {
+        (vec![], spl_token::id())
+    } else if program_accounts.contains(key) {
+        (vec![], instruction.program_id)
+    } else if let Some((_, wallet)) = token_accounts.iter().find(|(token_account, _)| token_account == key) {
+        let mut data = vec![0; TokenAccount::LEN];
+        let token_account = TokenAccount { mint: config::token_mint::id(), owner: *wallet, state: AccountState::Initialized, ..TokenAccount::default() };
+        TokenAccount::pack(token_account, &mut data).unwrap();
+        (data, spl_token::id())
+    } else {
+        (vec![], system_program::id())
+    };
+    let mut storage: Vec<(Pubkey, u64, Vec<u8>, Pubkey)> = instruction.accounts.iter()
+        .map(|meta| {
+            let (data, owner) = account(&meta.pubkey);
+            (meta.pubkey, 0, data, owner)
+        })
+        .collect();
+    let account_infos: Vec<AccountInfo> = storage.iter_mut().zip(&instruction.accounts)
+        .map(|((key, lamports, data, owner), meta)| {
+            AccountInfo::new(key, meta.is_signer, meta.is_writable, lamports, data, owner, false, 0)
+        })
+        .collect();
+    load(&account_infos)
+}
+
+fn mint_pixel(program_id: &Pubkey, owner_wallet: &Pubkey) -> Instruction {
+    NftCanvasInstruction::mint_pixel(
+        *program_id,
+        config::get_canvas_config(program_id).0,
+        config::get_canvas_stats(program_id).0,
+        config::get_canvas_stats_shard(program_id, config::stats_shard(INDEX)).0,
+        system_program::id(),
+        sysvar::rent::id(),
+        config::get_fee_vault(program_id).0,
+        config::get_pixel(program_id, INDEX).0,
+        *owner_wallet,
+        INDEX,
+        PixelColor::Rgb([1, 2, 3]),
+        0,
+    )
+}
+
+fn accept_bid(program_id: &Pubkey, pixel_owner: &Pubkey) -> Instruction {
+    let fee_vault = config::get_fee_vault(program_id).0;
+    let trade_pool = config::get_trade_pool(program_id).0;
+    NftCanvasInstruction::accept_bid(
+        *program_id,
+        config::get_canvas_config(program_id).0,
+        config::get_canvas_stats_shard(program_id, config::stats_shard(INDEX)).0,
+        spl_token::id(),
+        config::token_mint::id(),
+        system_program::id(),
+        sysvar::rent::id(),
+        spl_associated_token_account::id(),
+        config::get_pixel(program_id, INDEX).0,
+        fee_vault,
+        config::get_token_account(&fee_vault, &spl_token::id()),
+        trade_pool,
+        config::get_token_account(&trade_pool, &spl_token::id()),
+        *pixel_owner,
+        config::get_token_account(pixel_owner, &spl_token::id()),
+        INDEX,
+        0,
+    )
+}
+
+fn buy_pixel(program_id: &Pubkey, pixel_owner: &Pubkey, buyer_wallet: &Pubkey) -> Instruction {
+    let fee_vault = config::get_fee_vault(program_id).0;
+    let trade_pool = config::get_trade_pool(program_id).0;
+    NftCanvasInstruction::buy_pixel(
+        *program_id,
+        config::get_canvas_config(program_id).0,
+        config::get_canvas_stats_shard(program_id, config::stats_shard(INDEX)).0,
+        spl_token::id(),
+        config::token_mint::id(),
+        system_program::id(),
+        sysvar::rent::id(),
+        spl_associated_token_account::id(),
+        config::get_pixel(program_id, INDEX).0,
+        fee_vault,
+        config::get_token_account(&fee_vault, &spl_token::id()),
+        trade_pool,
+        config::get_token_account(&trade_pool, &spl_token::id()),
+        *pixel_owner,
+        config::get_token_account(pixel_owner, &spl_token::id()),
+        *buyer_wallet,
+        config::get_token_account(buyer_wallet, &spl_token::id()),
+        None,
+        INDEX,
+        100,
+        0,
+    )
+}
+
+#[test]
+fn test_mint_pixel_accounts() {
+    let program_id = Pubkey::new_unique();
+    let owner_wallet = Pubkey::new_unique();
+    let load_mint = |instruction: &Instruction| load(instruction, &[], |accounts| {
+        MintPixelAccounts::load(&program_id, accounts, INDEX).map(|_| ())
+    });
+
+    assert_eq!(load_mint(&mint_pixel(&program_id, &owner_wallet)), Ok(()));
+
+    let mut instruction = mint_pixel(&program_id, &owner_wallet);
+    instruction.accounts[4].pubkey = Pubkey::new_unique();
+    assert_eq!(load_mint(&instruction), Err(NftCanvasError::SystemProgramKeyMismatch.into()));
+
+    let mut instruction = mint_pixel(&program_id, &owner_wallet);
+    instruction.accounts[5].pubkey = Pubkey::new_unique();
+    assert_eq!(load_mint(&instruction), Err(NftCanvasError::RentSysvarKeyMismatch.into()));
+
+    let mut instruction = mint_pixel(&program_id, &owner_wallet);
+    instruction.accounts[8].is_signer = false;
+    assert_eq!(load_mint(&instruction), Err(NftCanvasError::FunderDidNotSign.into()));
+
+    let mut instruction = mint_pixel(&program_id, &owner_wallet);
+    instruction.accounts[7].is_writable = false;
+    assert_eq!(load_mint(&instruction), Err(NftCanvasError::AccountNotWritable.into()));
+
+    // The stats shard is any program account, the processor checks its data
+    let mut instruction = mint_pixel(&program_id, &owner_wallet);
+    instruction.accounts[3].pubkey = Pubkey::new_unique();
+    assert_eq!(load_mint(&instruction), Err(NftCanvasError::CanvasStatsKeyMismatch.into()));
+
+    // The presale receipt is checked when given
+    let mut instruction = mint_pixel(&program_id, &owner_wallet);
+    instruction.accounts.push(AccountMeta::new(Pubkey::new_unique(), false));
+    assert_eq!(load_mint(&instruction), Err(NftCanvasError::PresaleReceiptKeyMismatch.into()));
+
+    // Missing accounts
+    let mut instruction = mint_pixel(&program_id, &owner_wallet);
+    instruction.accounts.pop();
+    assert_eq!(load_mint(&instruction), Err(ProgramError::NotEnoughAccountKeys));
+}
+
+#[test]
+fn test_accept_bid_accounts() {
+    let program_id = Pubkey::new_unique();
+    let pixel_owner = Pubkey::new_unique();
+    // A token account of someone else, empty token accounts are checked when created
+    let stranger = Pubkey::new_unique();
+    let stranger_token_account = config::get_token_account(&stranger, &spl_token::id());
+    let token_accounts = [(stranger_token_account, stranger)];
+    let load_accept_bid = |instruction: &Instruction| load(instruction, &token_accounts, |accounts| {
+        AcceptBidAccounts::load(&program_id, accounts).map(|_| ())
+    });
+
+    assert_eq!(load_accept_bid(&accept_bid(&program_id, &pixel_owner)), Ok(()));
+
+    let mut instruction = accept_bid(&program_id, &pixel_owner);
+    instruction.accounts[5].pubkey = Pubkey::new_unique();
+    assert_eq!(load_accept_bid(&instruction), Err(NftCanvasError::SystemProgramKeyMismatch.into()));
+
+    let mut instruction = accept_bid(&program_id, &pixel_owner);
+    instruction.accounts[14].pubkey = stranger_token_account;
+    assert_eq!(load_accept_bid(&instruction), Err(NftCanvasError::PixelOwnerTokenAccountKeyMismatch.into()));
+
+    let mut instruction = accept_bid(&program_id, &pixel_owner);
+    instruction.accounts[13].is_signer = false;
+    assert_eq!(load_accept_bid(&instruction), Err(NftCanvasError::PixelOwnerDidNotSign.into()));
+}
+
+#[test]
+fn test_buy_pixel_accounts() {
+    let program_id = Pubkey::new_unique();
+    let pixel_owner = Pubkey::new_unique();
+    let buyer_wallet = Pubkey::new_unique();
+    let stranger = Pubkey::new_unique();
+    let stranger_token_account = config::get_token_account(&stranger, &spl_token::id());
+    let token_accounts = [(stranger_token_account, stranger)];
+    let load_buy = |instruction: &Instruction| load(instruction, &token_accounts, |accounts| {
+        BuyPixelAccounts::load(&program_id, accounts).map(|_| ())
+    });
+
+    assert_eq!(load_buy(&buy_pixel(&program_id, &pixel_owner, &buyer_wallet)), Ok(()));
+
+    let mut instruction = buy_pixel(&program_id, &pixel_owner, &buyer_wallet);
+    instruction.accounts[15].is_signer = false;
+    assert_eq!(load_buy(&instruction), Err(NftCanvasError::BuyerDidNotSign.into()));
+
+    let mut instruction = buy_pixel(&program_id, &pixel_owner, &buyer_wallet);
+    instruction.accounts[14].pubkey = stranger_token_account;
+    assert_eq!(load_buy(&instruction), Err(NftCanvasError::PixelOwnerTokenAccountKeyMismatch.into()));
+
+    let mut instruction = buy_pixel(&program_id, &pixel_owner, &buyer_wallet);
+    instruction.accounts[4].pubkey = Pubkey::new_unique();
+    assert_eq!(load_buy(&instruction), Err(NftCanvasError::TokenMintKeyMismatch.into()));
+}
@@ -0,0 +1,25 @@
+use chikin_nft_canvas::allowlist::{self, AllowlistTree};
+use solana_program::pubkey::Pubkey;
+
+#[test]
+fn test_allowlist() {
+    for wallet_count in [1usize, 2, 3, 7, 8, 33].iter() {
+        let wallets: Vec<Pubkey> = (0..*wallet_count).map(|_| Pubkey::new_unique()).collect();
+        let tree = AllowlistTree::new(&wallets);
+        let root = tree.root();
+        for wallet in wallets.iter() {
+            let proof = tree.proof(wallet).unwrap();
+            assert!(allowlist::verify(&root, wallet, &proof));
+            assert!(!allowlist::verify(&root, &Pubkey::new_unique(), &proof));
+            let packed_proof = allowlist::pack_hashes(&proof);
+            assert_eq!(allowlist::unpack_hashes(&packed_proof).unwrap(), proof);
+        }
+        assert!(tree.proof(&Pubkey::new_unique()).is_none());
+    }
+
+    let empty_tree = AllowlistTree::new(&[]);
+    assert_eq!(empty_tree.root(), [0u8; 32]);
+    assert!(!allowlist::verify(&empty_tree.root(), &Pubkey::new_unique(), &[]));
+
+    assert!(allowlist::unpack_hashes(&[0u8; 33]).is_err());
+}
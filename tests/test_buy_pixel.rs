@@ -0,0 +1,191 @@
+use std::sync::{Arc, Mutex};
+
+use solana_program::account_info::AccountInfo;
+use solana_program::bpf_loader;
+use solana_program::clock::Clock;
+use solana_program::entrypoint::{ProgramResult, SUCCESS};
+use solana_program::instruction::Instruction;
+use solana_program::program_error::ProgramError;
+use solana_program::program_option::COption;
+use solana_program::program_pack::Pack;
+use solana_program::program_stubs::{self, SyscallStubs};
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+use solana_program::sysvar;
+use spl_token::state::{Account as TokenAccount, AccountState, Mint};
+
+use chikin_nft_canvas::config;
+use chikin_nft_canvas::error::NftCanvasError;
+use chikin_nft_canvas::instruction::NftCanvasInstruction;
+use chikin_nft_canvas::packable::Packable;
+use chikin_nft_canvas::processor;
+use chikin_nft_canvas::state::{CanvasConfig, CanvasStatsShard, Pixel, PixelBuyInfo};
+use chikin_nft_canvas::token;
+
+const INDEX: u32 = 7;
+
+/// The syscall stubs are global, so tests setting them take turns.
+static STUBS: Mutex<()> = Mutex::new(());
+
+/// Records the invoked instructions and the logs, without running the instructions.
+struct RecordingStubs {
+    invoked: Arc<Mutex<Vec<Instruction>>>,
+    logs: Arc<Mutex<Vec<String>>>,
+}
+
+impl SyscallStubs for RecordingStubs {
+    fn sol_log(&self, message: &str) {
+        self.logs.lock().unwrap().push(message.to_string());
+    }
+
+    fn sol_invoke_signed(&self, instruction: &Instruction, _account_infos: &[AccountInfo], _signers_seeds: &[&[&[u8]]]) -> ProgramResult {
+        self.invoked.lock().unwrap().push(instruction.clone());
+        Ok(())
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Clock) = Clock::default() };
+        SUCCESS
+    }
+}
+
+/// Buys the pixel at `INDEX` as `buyer_wallet`, returning the pixel afterwards, the invoked instructions and the logs.
+fn buy(program_id: &Pubkey, pixel: &Pixel, buyer_wallet: &Pubkey, price: u64, direct_only: u8) -> (Result<Pixel, ProgramError>, Vec<Instruction>, Vec<String>) {
+    let fee_vault = config::get_fee_vault(program_id).0;
+    let trade_pool = config::get_trade_pool(program_id).0;
+    let instruction = NftCanvasInstruction::buy_pixel(
+        *program_id,
+        config::get_canvas_config(program_id).0,
+        config::get_canvas_stats_shard(program_id, config::stats_shard(INDEX)).0,
+        spl_token::id(),
+        config::token_mint::id(),
+        system_program::id(),
+        sysvar::rent::id(),
+        spl_associated_token_account::id(),
+        config::get_pixel(program_id, INDEX).0,
+        fee_vault,
+        config::get_token_account(&fee_vault, &spl_token::id()),
+        trade_pool,
+        config::get_token_account(&trade_pool, &spl_token::id()),
+        pixel.owner_wallet,
+        config::get_token_account(&pixel.owner_wallet, &spl_token::id()),
+        *buyer_wallet,
+        config::get_token_account(buyer_wallet, &spl_token::id()),
+        None,
+        INDEX,
+        price,
+        direct_only,
+    );
+    let mut mint_data = vec![0; Mint::LEN];
+    let mint = Mint { mint_authority: COption::None, supply: 0, decimals: 0, is_initialized: true, freeze_authority: COption::None };
+    Mint::pack(mint, &mut mint_data).unwrap();
+    // (The token program is not run)
+    let token_account = |wallet: &Pubkey| (1, token_account_data(wallet), spl_token::id());
+    let mut storage: Vec<(u64, Vec<u8>, Pubkey)> = vec![
+        (1, vec![], bpf_loader::id()),
+        (1, CanvasConfig::new(Pubkey::new_unique(), config::get_trade_pool(program_id).1, config::get_fee_vault(program_id).1).pack(), *program_id),
+        (1, CanvasStatsShard::new(config::stats_shard(INDEX)).pack(), *program_id),
+        (1, vec![], bpf_loader::id()),
+        (1, mint_data, spl_token::id()),
+        (1, vec![], Pubkey::default()),
+        (1, vec![], sysvar::id()),
+        (1, vec![], bpf_loader::id()),
+        (1, pixel.pack(), *program_id),
+        (1, vec![], system_program::id()),
+        token_account(&fee_vault),
+        (1, vec![], system_program::id()),
+        token_account(&trade_pool),
+        (1, vec![], system_program::id()),
+        token_account(&pixel.owner_wallet),
+        (1_000_000_000, vec![], system_program::id()),
+        token_account(buyer_wallet),
+    ];
+    let account_infos: Vec<AccountInfo> = storage.iter_mut().zip(&instruction.accounts)
+        .map(|((lamports, data, owner), meta)| {
+            AccountInfo::new(&meta.pubkey, meta.is_signer, meta.is_writable, lamports, data, owner, false, 0)
+        })
+        .collect();
+
+    let _stubs = STUBS.lock().unwrap();
+    let invoked = Arc::new(Mutex::new(Vec::new()));
+    let logs = Arc::new(Mutex::new(Vec::new()));
+    program_stubs::set_syscall_stubs(Box::new(RecordingStubs { invoked: invoked.clone(), logs: logs.clone() }));
+    let result = processor::process_instruction(program_id, &account_infos, &instruction.data);
+    drop(account_infos);
+    let invoked = invoked.lock().unwrap().clone();
+    let logs = logs.lock().unwrap().clone();
+    (result.map(|_| Pixel::unpack(&storage[8].1).unwrap()), invoked, logs)
+}
+
+#[test]
+fn test_buy_listed_pixel_refunds_own_bid() {
+    let program_id = Pubkey::new_unique();
+    let pixel_owner = Pubkey::new_unique();
+    let buyer_wallet = Pubkey::new_unique();
+    let trade_pool = config::get_trade_pool(&program_id).0;
+    let bump_seed = config::get_pixel(&program_id, INDEX).1;
+    let refund = token::transfer_checked(
+        &spl_token::id(),
+        &config::get_token_account(&trade_pool, &spl_token::id()),
+        &config::token_mint::id(),
+        &config::get_token_account(&buyer_wallet, &spl_token::id()),
+        &trade_pool,
+        100,
+        0,
+    ).unwrap();
+
+    // Buying at the listed price while holding the best bid refunds the bid
+    let mut pixel = Pixel::new(INDEX, [0, 0, 0, 255], pixel_owner, 300, bump_seed);
+    pixel.best_buy_info = Some(PixelBuyInfo { price: 100, buyer_wallet });
+    let (result, invoked, _) = buy(&program_id, &pixel, &buyer_wallet, 300, 0);
+    let pixel = result.unwrap();
+    assert_eq!((pixel.owner_wallet, pixel.best_buy_info), (buyer_wallet, None));
+    assert_eq!(invoked.iter().filter(|instruction| **instruction == refund).count(), 1);
+
+    // Someone else's bid stays open, in escrow
+    let other_buyer_wallet = Pubkey::new_unique();
+    let mut pixel = Pixel::new(INDEX, [0, 0, 0, 255], pixel_owner, 300, bump_seed);
+    pixel.best_buy_info = Some(PixelBuyInfo { price: 100, buyer_wallet: other_buyer_wallet });
+    let (result, invoked, _) = buy(&program_id, &pixel, &buyer_wallet, 300, 0);
+    let pixel = result.unwrap();
+    assert_eq!(pixel.best_buy_info, Some(PixelBuyInfo { price: 100, buyer_wallet: other_buyer_wallet }));
+    assert!(invoked.iter().all(|instruction| instruction.accounts[0].pubkey != config::get_token_account(&trade_pool, &spl_token::id())));
+}
+
+#[test]
+fn test_direct_buy_pays_listing_price() {
+    let program_id = Pubkey::new_unique();
+    let pixel_owner = Pubkey::new_unique();
+    let buyer_wallet = Pubkey::new_unique();
+    let fee_vault = config::get_fee_vault(&program_id).0;
+    let bump_seed = config::get_pixel(&program_id, INDEX).1;
+    let pay = |wallet: &Pubkey, amount| token::transfer_checked(
+        &spl_token::id(),
+        &config::get_token_account(&buyer_wallet, &spl_token::id()),
+        &config::token_mint::id(),
+        &config::get_token_account(wallet, &spl_token::id()),
+        &buyer_wallet,
+        amount,
+        0,
+    ).unwrap();
+    let pixel = Pixel::new(INDEX, [0, 0, 0, 255], pixel_owner, 300, bump_seed);
+
+    // The offered price only caps what the buyer pays
+    let (result, invoked, logs) = buy(&program_id, &pixel, &buyer_wallet, 1_000, 1);
+    assert_eq!(result.unwrap().owner_wallet, buyer_wallet);
+    assert_eq!(invoked, vec![pay(&pixel_owner, 297), pay(&fee_vault, 3)]);
+    assert!(logs.contains(&"Trade receipt: index=7, price=300, to_seller=297, to_team=3".to_string()));
+
+    // Below the listing, it is not a buy
+    let (result, invoked, _) = buy(&program_id, &pixel, &buyer_wallet, 299, 1);
+    assert_eq!(result, Err(NftCanvasError::CouldNotDirectBuy.into()));
+    assert!(invoked.is_empty());
+}
+
+/// The data of an initialized token account of `wallet`.
+fn token_account_data(wallet: &Pubkey) -> Vec<u8> {
+    let mut data = vec![0; TokenAccount::LEN];
+    let token_account = TokenAccount { mint: config::token_mint::id(), owner: *wallet, state: AccountState::Initialized, ..TokenAccount::default() };
+    TokenAccount::pack(token_account, &mut data).unwrap();
+    data
+}
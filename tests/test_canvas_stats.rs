@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use solana_program::account_info::AccountInfo;
+use solana_program::bpf_loader;
+use solana_program::entrypoint::ProgramResult;
+use solana_program::instruction::Instruction;
+use solana_program::program_stubs::{self, SyscallStubs};
+use solana_program::pubkey::Pubkey;
+use solana_program::rent::Rent;
+use solana_program::system_instruction;
+use solana_program::system_program;
+use solana_program::sysvar::{self, Sysvar};
+
+use chikin_nft_canvas::config;
+use chikin_nft_canvas::error::NftCanvasError;
+use chikin_nft_canvas::instruction::NftCanvasInstruction;
+use chikin_nft_canvas::packable::Packable;
+use chikin_nft_canvas::processor;
+use chikin_nft_canvas::state::{CanvasConfig, CanvasStats, CanvasStatsShard, Pixel, PixelBuyInfo};
+
+/// The syscall stubs are global, so tests setting them take turns.
+static STUBS: Mutex<()> = Mutex::new(());
+
+/// Lamports, data and owner of each account. Accounts missing from it are empty system accounts.
+type Bank = HashMap<Pubkey, (u64, Vec<u8>, Pubkey)>;
+
+/// Records the invoked instructions, without running them.
+struct RecordingStubs {
+    invoked: Arc<Mutex<Vec<Instruction>>>,
+}
+
+impl SyscallStubs for RecordingStubs {
+    fn sol_invoke_signed(&self, instruction: &Instruction, _account_infos: &[AccountInfo], _signers_seeds: &[&[&[u8]]]) -> ProgramResult {
+        self.invoked.lock().unwrap().push(instruction.clone());
+        Ok(())
+    }
+}
+
+/// A canvas initialized before the stats accounts existed.
+fn canvas(program_id: &Pubkey, admin_wallet: &Pubkey) -> Bank {
+    let mut rent_data = vec![0; Rent::size_of()];
+    let mut rent_lamports = 1;
+    let rent_sysvar = sysvar::rent::id();
+    let owner = sysvar::id();
+    Rent::default().to_account_info(&mut AccountInfo::new(&rent_sysvar, false, false, &mut rent_lamports, &mut rent_data, &owner, false, 0)).unwrap();
+    let mut bank = Bank::new();
+    bank.insert(*program_id, (1, vec![], bpf_loader::id()));
+    bank.insert(rent_sysvar, (1, rent_data, owner));
+    bank.insert(config::get_canvas_config(program_id).0, (1, CanvasConfig::new(*admin_wallet, config::get_trade_pool(program_id).1, config::get_fee_vault(program_id).1).pack(), *program_id));
+    bank
+}
+
+/// Runs `instruction` against `bank`, returning the invoked instructions.
+fn run(program_id: &Pubkey, instruction: &Instruction, bank: &mut Bank) -> (ProgramResult, Vec<Instruction>) {
+    let mut storage: Vec<(u64, Vec<u8>, Pubkey)> = instruction.accounts.iter()
+        .map(|meta| bank.get(&meta.pubkey).cloned().unwrap_or((0, vec![], system_program::id())))
+        .collect();
+    let account_infos: Vec<AccountInfo> = storage.iter_mut().zip(&instruction.accounts)
+        .map(|((lamports, data, owner), meta)| {
+            AccountInfo::new(&meta.pubkey, meta.is_signer, meta.is_writable, lamports, data, owner, false, 0)
+        })
+        .collect();
+
+    let _stubs = STUBS.lock().unwrap();
+    let invoked = Arc::new(Mutex::new(Vec::new()));
+    program_stubs::set_syscall_stubs(Box::new(RecordingStubs { invoked: invoked.clone() }));
+    let result = processor::process_instruction(program_id, &account_infos, &instruction.data);
+    drop(account_infos);
+    if result.is_ok() {
+        for (meta, account) in instruction.accounts.iter().zip(storage) {
+            bank.insert(meta.pubkey, account);
+        }
+    }
+    let invoked = invoked.lock().unwrap().clone();
+    (result, invoked)
+}
+
+fn initialize_stats_shard(program_id: &Pubkey, admin_wallet: &Pubkey, shard: u8) -> Instruction {
+    NftCanvasInstruction::initialize_stats_shard(
+        *program_id,
+        system_program::id(),
+        sysvar::rent::id(),
+        config::get_canvas_config(program_id).0,
+        config::get_canvas_stats_shard(program_id, shard).0,
+        *admin_wallet,
+        shard,
+        2,
+        1,
+        0,
+        3,
+    )
+}
+
+#[test]
+fn test_canvas_stats() {
+    let mut canvas_stats = CanvasStats::new();
+    canvas_stats.record_mint(1_000);
+    canvas_stats.record_mint(2_000);
+    assert_eq!((canvas_stats.minted, canvas_stats.mint_fees), (2, 3_000));
+
+    canvas_stats.record_close();
+    assert_eq!((canvas_stats.minted, canvas_stats.mint_fees), (1, 3_000));
+    canvas_stats.record_close();
+    canvas_stats.record_close();
+    assert_eq!(canvas_stats.minted, 0);
+}
+
+#[test]
+fn test_canvas_stats_shard() {
+    let mut shard = CanvasStatsShard::new(3);
+    let mut pixel = Pixel::new(3, [255, 0, 0, 255], Pubkey::new_unique(), 100, 255);
+    shard.add_pixel(&pixel);
+    assert_eq!((shard.listings, shard.open_sol_bids), (1, 0));
+
+    shard.remove_pixel(&pixel);
+    pixel.best_sol_buy_info = Some(PixelBuyInfo { price: 50, buyer_wallet: Pubkey::new_unique() });
+    shard.add_pixel(&pixel);
+    assert_eq!((shard.listings, shard.open_sol_bids), (1, 1));
+
+    shard.remove_pixel(&pixel);
+    shard.record_sale(100, 1, 10);
+    pixel.transfer_ownership(Pubkey::new_unique());
+    shard.add_pixel(&pixel);
+    assert_eq!((shard.listings, shard.open_sol_bids), (0, 1));
+    assert_eq!((shard.volume, shard.fees, shard.last_sale_price, shard.last_sale_slot), (100, 1, 100, 10));
+
+    shard.remove_pixel(&pixel);
+    assert_eq!(shard, CanvasStatsShard { volume: 100, fees: 1, last_sale_price: 100, last_sale_slot: 10, ..CanvasStatsShard::new(3) });
+    shard.remove_pixel(&pixel);
+    assert_eq!(shard.open_sol_bids, 0);
+}
+
+#[test]
+fn test_merge_canvas_stats_shards() {
+    let mut first = CanvasStatsShard { listings: 2, open_bids: 1, ..CanvasStatsShard::new(0) };
+    first.record_sale(100, 1, 20);
+    first.record_sol_sale(300, 3, 5);
+    let mut second = CanvasStatsShard { listings: 1, open_sol_bids: 4, ..CanvasStatsShard::new(1) };
+    second.record_sale(200, 2, 10);
+    second.record_sol_sale(400, 4, 30);
+
+    let mut total = CanvasStatsShard::default();
+    total.merge(&first);
+    total.merge(&second);
+    assert_eq!((total.listings, total.open_bids, total.open_sol_bids), (3, 1, 4));
+    assert_eq!((total.volume, total.fees, total.sol_volume, total.sol_fees), (300, 3, 700, 7));
+
+    // The last sales are the latest ones of any shard
+    assert_eq!((total.last_sale_price, total.last_sale_slot), (100, 20));
+    assert_eq!((total.last_sol_sale_price, total.last_sol_sale_slot), (400, 30));
+}
+
+#[test]
+fn test_initialize_stats() {
+    let program_id = Pubkey::new_unique();
+    let admin_wallet = Pubkey::new_unique();
+    let canvas_stats = config::get_canvas_stats(&program_id).0;
+    let initialize_stats = |admin_wallet: Pubkey| NftCanvasInstruction::initialize_stats(
+        program_id,
+        system_program::id(),
+        sysvar::rent::id(),
+        config::get_canvas_config(&program_id).0,
+        canvas_stats,
+        admin_wallet,
+        7,
+    );
+    let mut bank = canvas(&program_id, &admin_wallet);
+
+    // Only the admin creates it
+    let (result, invoked) = run(&program_id, &initialize_stats(Pubkey::new_unique()), &mut bank);
+    assert_eq!(result, Err(NftCanvasError::AdminKeyMismatch.into()));
+    assert!(invoked.is_empty());
+
+    // (The system program is not run, so the new account comes allocated)
+    bank.insert(canvas_stats, (0, vec![0; CanvasStats::PACKED_SIZE], system_program::id()));
+    let (result, invoked) = run(&program_id, &initialize_stats(admin_wallet), &mut bank);
+    assert_eq!(result, Ok(()));
+    let lamports = Rent::default().minimum_balance(CanvasStats::PACKED_SIZE);
+    assert_eq!(invoked, vec![system_instruction::create_account(&admin_wallet, &canvas_stats, lamports, CanvasStats::PACKED_SIZE as u64, &program_id)]);
+    assert_eq!(CanvasStats::unpack(&bank[&canvas_stats].1).unwrap(), CanvasStats { minted: 7, ..CanvasStats::new() });
+}
+
+#[test]
+fn test_initialize_stats_shard() {
+    let program_id = Pubkey::new_unique();
+    let admin_wallet = Pubkey::new_unique();
+    let mut bank = canvas(&program_id, &admin_wallet);
+
+    // Only the admin creates it, and only for the shards pixels go to
+    let (result, invoked) = run(&program_id, &initialize_stats_shard(&program_id, &Pubkey::new_unique(), 5), &mut bank);
+    assert_eq!(result, Err(NftCanvasError::AdminKeyMismatch.into()));
+    assert!(invoked.is_empty());
+    let (result, _) = run(&program_id, &initialize_stats_shard(&program_id, &admin_wallet, config::STATS_SHARDS as u8), &mut bank);
+    assert_eq!(result, Err(NftCanvasError::CanvasStatsKeyMismatch.into()));
+
+    // Shard accounts live at their own address
+    let mut wrong_address = initialize_stats_shard(&program_id, &admin_wallet, 5);
+    wrong_address.accounts[4].pubkey = config::get_canvas_stats_shard(&program_id, 6).0;
+    let (result, _) = run(&program_id, &wrong_address, &mut bank);
+    assert_eq!(result, Err(NftCanvasError::CanvasStatsKeyMismatch.into()));
+
+    // Seeded with the counts of the pixels of the shard already on the canvas
+    let canvas_stats_shard = config::get_canvas_stats_shard(&program_id, 5).0;
+    bank.insert(canvas_stats_shard, (0, vec![0; CanvasStatsShard::PACKED_SIZE], system_program::id()));
+    let (result, _) = run(&program_id, &initialize_stats_shard(&program_id, &admin_wallet, 5), &mut bank);
+    assert_eq!(result, Ok(()));
+    assert_eq!(
+        CanvasStatsShard::unpack(&bank[&canvas_stats_shard].1).unwrap(),
+        CanvasStatsShard { listings: 2, sol_listings: 1, open_bids: 0, open_sol_bids: 3, ..CanvasStatsShard::new(5) },
+    );
+}
@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use solana_program::account_info::AccountInfo;
+use solana_program::bpf_loader;
+use solana_program::clock::Clock;
+use solana_program::entrypoint::{ProgramResult, SUCCESS};
+use solana_program::instruction::Instruction;
+use solana_program::program_error::ProgramError;
+use solana_program::program_option::COption;
+use solana_program::program_pack::Pack;
+use solana_program::program_stubs::{self, SyscallStubs};
+use solana_program::pubkey::Pubkey;
+use solana_program::system_instruction;
+use solana_program::system_program;
+use solana_program::sysvar;
+use spl_token::state::{Account as TokenAccount, AccountState, Mint};
+
+use chikin_nft_canvas::config;
+use chikin_nft_canvas::error::NftCanvasError;
+use chikin_nft_canvas::instruction::NftCanvasInstruction;
+use chikin_nft_canvas::packable::Packable;
+use chikin_nft_canvas::processor;
+use chikin_nft_canvas::state::{CanvasConfig, CanvasStats, CanvasStatsShard, Pixel, PixelBuyInfo};
+use chikin_nft_canvas::token;
+
+const INDEX: u32 = 9;
+const PIXEL_RENT: u64 = 2_000_000;
+
+/// The syscall stubs are global, so tests setting them take turns.
+static STUBS: Mutex<()> = Mutex::new(());
+
+/// Lamports, data and owner of each account. Accounts missing from it are empty system accounts.
+type Bank = HashMap<Pubkey, (u64, Vec<u8>, Pubkey)>;
+
+/// Records the invoked instructions, without running them.
+struct RecordingStubs {
+    invoked: Arc<Mutex<Vec<Instruction>>>,
+}
+
+impl SyscallStubs for RecordingStubs {
+    fn sol_invoke_signed(&self, instruction: &Instruction, _account_infos: &[AccountInfo], _signers_seeds: &[&[&[u8]]]) -> ProgramResult {
+        self.invoked.lock().unwrap().push(instruction.clone());
+        Ok(())
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Clock) = Clock::default() };
+        SUCCESS
+    }
+}
+
+/// The canvas accounts, with `pixel` minted and counted in the stats.
+fn canvas(program_id: &Pubkey, pixel: &Pixel) -> Bank {
+    let trade_pool = config::get_trade_pool(program_id).0;
+    let mut mint_data = vec![0; Mint::LEN];
+    let mint = Mint { mint_authority: COption::None, supply: 0, decimals: 0, is_initialized: true, freeze_authority: COption::None };
+    Mint::pack(mint, &mut mint_data).unwrap();
+    let canvas_stats = CanvasStats { minted: 1, ..CanvasStats::new() };
+    let mut canvas_stats_shard = CanvasStatsShard::new(config::stats_shard(INDEX));
+    canvas_stats_shard.add_pixel(pixel);
+    let mut bank = Bank::new();
+    bank.insert(*program_id, (1, vec![], bpf_loader::id()));
+    bank.insert(config::get_canvas_config(program_id).0, (1, CanvasConfig::new(Pubkey::new_unique(), config::get_trade_pool(program_id).1, config::get_fee_vault(program_id).1).pack(), *program_id));
+    bank.insert(config::get_canvas_stats(program_id).0, (1, canvas_stats.pack(), *program_id));
+    bank.insert(config::get_canvas_stats_shard(program_id, config::stats_shard(INDEX)).0, (1, canvas_stats_shard.pack(), *program_id));
+    bank.insert(spl_token::id(), (1, vec![], bpf_loader::id()));
+    bank.insert(config::token_mint::id(), (1, mint_data, spl_token::id()));
+    bank.insert(sysvar::rent::id(), (1, vec![], sysvar::id()));
+    bank.insert(spl_associated_token_account::id(), (1, vec![], bpf_loader::id()));
+    bank.insert(config::get_pixel(program_id, INDEX).0, (PIXEL_RENT, pixel.pack(), *program_id));
+    bank.insert(pixel.owner_wallet, (1_000, vec![], system_program::id()));
+    for wallet in [trade_pool].iter().chain(pixel.best_buy_info.iter().map(|buy_info| &buy_info.buyer_wallet)) {
+        // (The token program is not run)
+        bank.insert(config::get_token_account(wallet, &spl_token::id()), (1, token_account_data(wallet), spl_token::id()));
+    }
+    bank
+}
+
+/// Runs `instruction` against `bank`, returning the invoked instructions.
+fn run(program_id: &Pubkey, instruction: &Instruction, bank: &mut Bank) -> (ProgramResult, Vec<Instruction>) {
+    let mut storage: Vec<(u64, Vec<u8>, Pubkey)> = instruction.accounts.iter()
+        .map(|meta| bank.get(&meta.pubkey).cloned().unwrap_or((0, vec![], system_program::id())))
+        .collect();
+    let account_infos: Vec<AccountInfo> = storage.iter_mut().zip(&instruction.accounts)
+        .map(|((lamports, data, owner), meta)| {
+            AccountInfo::new(&meta.pubkey, meta.is_signer, meta.is_writable, lamports, data, owner, false, 0)
+        })
+        .collect();
+
+    let _stubs = STUBS.lock().unwrap();
+    let invoked = Arc::new(Mutex::new(Vec::new()));
+    program_stubs::set_syscall_stubs(Box::new(RecordingStubs { invoked: invoked.clone() }));
+    let result = processor::process_instruction(program_id, &account_infos, &instruction.data);
+    drop(account_infos);
+    if result.is_ok() {
+        for (meta, account) in instruction.accounts.iter().zip(storage) {
+            bank.insert(meta.pubkey, account);
+        }
+    }
+    let invoked = invoked.lock().unwrap().clone();
+    (result, invoked)
+}
+
+fn close_pixel(program_id: &Pubkey, pixel_owner: &Pubkey, previous_buyer_wallet: Option<Pubkey>, previous_sol_buyer_wallet: Option<Pubkey>) -> Instruction {
+    NftCanvasInstruction::close_pixel(
+        *program_id,
+        config::get_canvas_config(program_id).0,
+        config::get_canvas_stats(program_id).0,
+        config::get_canvas_stats_shard(program_id, config::stats_shard(INDEX)).0,
+        system_program::id(),
+        spl_token::id(),
+        config::token_mint::id(),
+        sysvar::rent::id(),
+        spl_associated_token_account::id(),
+        config::get_pixel(program_id, INDEX).0,
+        config::get_trade_pool(program_id).0,
+        config::get_token_account(&config::get_trade_pool(program_id).0, &spl_token::id()),
+        *pixel_owner,
+        previous_buyer_wallet,
+        previous_sol_buyer_wallet,
+        INDEX,
+    )
+}
+
+#[test]
+fn test_close_pixel_refunds_bids() {
+    let program_id = Pubkey::new_unique();
+    let pixel_owner = Pubkey::new_unique();
+    let buyer_wallet = Pubkey::new_unique();
+    let sol_buyer_wallet = Pubkey::new_unique();
+    let trade_pool = config::get_trade_pool(&program_id).0;
+    let mut pixel = Pixel::new(INDEX, [1, 2, 3, 255], pixel_owner, 300, config::get_pixel(&program_id, INDEX).1);
+    pixel.sol_sell_price = 400;
+    pixel.best_buy_info = Some(PixelBuyInfo { price: 100, buyer_wallet });
+    pixel.best_sol_buy_info = Some(PixelBuyInfo { price: 200, buyer_wallet: sol_buyer_wallet });
+    let mut bank = canvas(&program_id, &pixel);
+
+    let (result, invoked) = run(&program_id, &close_pixel(&program_id, &pixel_owner, Some(buyer_wallet), Some(sol_buyer_wallet)), &mut bank);
+    assert_eq!(result, Ok(()));
+
+    // Both escrowed bids go back to their bidders
+    assert_eq!(invoked, vec![
+        token::transfer_checked(
+            &spl_token::id(),
+            &config::get_token_account(&trade_pool, &spl_token::id()),
+            &config::token_mint::id(),
+            &config::get_token_account(&buyer_wallet, &spl_token::id()),
+            &trade_pool,
+            100,
+            0,
+        ).unwrap(),
+        system_instruction::transfer(&trade_pool, &sol_buyer_wallet, 200),
+    ]);
+
+    // The pixel account is zeroed and its rent goes to the owner
+    let (pixel_lamports, pixel_data, _) = &bank[&config::get_pixel(&program_id, INDEX).0];
+    assert_eq!(*pixel_lamports, 0);
+    assert!(pixel_data.iter().all(|byte| *byte == 0));
+    assert_eq!(bank[&pixel_owner].0, 1_000 + PIXEL_RENT);
+
+    // Nothing is counted for the pixel anymore
+    let canvas_stats = CanvasStats::unpack(&bank[&config::get_canvas_stats(&program_id).0].1).unwrap();
+    assert_eq!(canvas_stats, CanvasStats::new());
+    let canvas_stats_shard = CanvasStatsShard::unpack(&bank[&config::get_canvas_stats_shard(&program_id, config::stats_shard(INDEX)).0].1).unwrap();
+    assert_eq!(canvas_stats_shard, CanvasStatsShard::new(config::stats_shard(INDEX)));
+}
+
+#[test]
+fn test_close_pixel_checks() {
+    let program_id = Pubkey::new_unique();
+    let pixel_owner = Pubkey::new_unique();
+    let buyer_wallet = Pubkey::new_unique();
+    let mut pixel = Pixel::new(INDEX, [1, 2, 3, 255], pixel_owner, 0, config::get_pixel(&program_id, INDEX).1);
+    pixel.best_buy_info = Some(PixelBuyInfo { price: 100, buyer_wallet });
+    let mut bank = canvas(&program_id, &pixel);
+
+    // Only the owner closes the pixel
+    let stranger = Pubkey::new_unique();
+    let (result, invoked) = run(&program_id, &close_pixel(&program_id, &stranger, Some(buyer_wallet), None), &mut bank);
+    assert_eq!(result, Err(NftCanvasError::PixelOwnerKeyMismatch.into()));
+    assert!(invoked.is_empty());
+
+    // The bid has to be refunded, to its bidder
+    let (result, _) = run(&program_id, &close_pixel(&program_id, &pixel_owner, None, None), &mut bank);
+    assert_eq!(result, Err(ProgramError::NotEnoughAccountKeys));
+    let (result, invoked) = run(&program_id, &close_pixel(&program_id, &pixel_owner, Some(stranger), None), &mut bank);
+    assert_eq!(result, Err(NftCanvasError::PreviousBuyerKeyMismatch.into()));
+    assert!(invoked.is_empty());
+    let mut wrong_token_account = close_pixel(&program_id, &pixel_owner, Some(buyer_wallet), None);
+    let previous_buyer_token_account = wrong_token_account.accounts.len() - 2;
+    let stranger_token_account = config::get_token_account(&stranger, &spl_token::id());
+    bank.insert(stranger_token_account, (1, token_account_data(&stranger), spl_token::id()));
+    wrong_token_account.accounts[previous_buyer_token_account].pubkey = stranger_token_account;
+    let (result, invoked) = run(&program_id, &wrong_token_account, &mut bank);
+    assert_eq!(result, Err(NftCanvasError::PreviousBuyerTokenAccountKeyMismatch.into()));
+    assert!(invoked.is_empty());
+    let mut readonly_token_account = close_pixel(&program_id, &pixel_owner, Some(buyer_wallet), None);
+    readonly_token_account.accounts[previous_buyer_token_account].is_writable = false;
+    let (result, _) = run(&program_id, &readonly_token_account, &mut bank);
+    assert_eq!(result, Err(NftCanvasError::AccountNotWritable.into()));
+
+    // Still minted
+    assert_eq!(bank[&config::get_pixel(&program_id, INDEX).0], (PIXEL_RENT, pixel.pack(), program_id));
+}
+
+/// The data of an initialized token account of `wallet`.
+fn token_account_data(wallet: &Pubkey) -> Vec<u8> {
+    let mut data = vec![0; TokenAccount::LEN];
+    let token_account = TokenAccount { mint: config::token_mint::id(), owner: *wallet, state: AccountState::Initialized, ..TokenAccount::default() };
+    TokenAccount::pack(token_account, &mut data).unwrap();
+    data
+}
@@ -0,0 +1,132 @@
+use solana_program::account_info::AccountInfo;
+use solana_program::bpf_loader;
+use solana_program::instruction::Instruction;
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+
+use chikin_nft_canvas::color::PixelColor;
+use chikin_nft_canvas::config;
+use chikin_nft_canvas::error::NftCanvasError;
+use chikin_nft_canvas::instruction::NftCanvasInstruction;
+use chikin_nft_canvas::packable::Packable;
+use chikin_nft_canvas::processor;
+use chikin_nft_canvas::state::{CanvasConfig, LegacyPixel, Pixel};
+
+const INDEX: u32 = 5;
+
+/// Runs `instruction` with the pixel account (the third one) holding `pixel_data`, returning the pixel data afterwards.
+fn run(program_id: &Pubkey, instruction: &Instruction, pixel_data: Vec<u8>) -> Result<Vec<u8>, ProgramError> {
+    let mut storage: Vec<(Pubkey, u64, Vec<u8>, Pubkey)> = instruction.accounts.iter()
+        .map(|meta| (meta.pubkey, 1, vec![], system_program::id()))
+        .collect();
+    storage[0].3 = bpf_loader::id();
+    storage[1] = (storage[1].0, 1, CanvasConfig::new(Pubkey::new_unique(), config::get_trade_pool(program_id).1, config::get_fee_vault(program_id).1).pack(), *program_id);
+    storage[2] = (storage[2].0, 1, pixel_data, *program_id);
+    let account_infos: Vec<AccountInfo> = storage.iter_mut().zip(&instruction.accounts)
+        .map(|((key, lamports, data, owner), meta)| {
+            AccountInfo::new(key, meta.is_signer, meta.is_writable, lamports, data, owner, false, 0)
+        })
+        .collect();
+    processor::process_instruction(program_id, &account_infos, &instruction.data)?;
+    drop(account_infos);
+    Ok(storage.swap_remove(2).2)
+}
+
+fn set_color_delegate(program_id: &Pubkey, wallet: &Pubkey, color_delegate: Option<Pubkey>, pixel_data: Vec<u8>) -> Result<Vec<u8>, ProgramError> {
+    let instruction = NftCanvasInstruction::set_color_delegate(
+        *program_id,
+        config::get_canvas_config(program_id).0,
+        config::get_pixel(program_id, INDEX).0,
+        *wallet,
+        color_delegate,
+        INDEX,
+    );
+    run(program_id, &instruction, pixel_data)
+}
+
+fn paint(program_id: &Pubkey, wallet: &Pubkey, color: [u8; 3], pixel_data: Vec<u8>) -> Result<Vec<u8>, ProgramError> {
+    let instruction = NftCanvasInstruction::update_pixel_color(
+        *program_id,
+        config::get_canvas_config(program_id).0,
+        config::get_pixel(program_id, INDEX).0,
+        *wallet,
+        INDEX,
+        PixelColor::Rgb(color),
+    );
+    run(program_id, &instruction, pixel_data)
+}
+
+#[test]
+fn test_set_color_delegate() {
+    let program_id = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let delegate = Pubkey::new_unique();
+    let stranger = Pubkey::new_unique();
+    let pixel = Pixel::new(INDEX, [0, 0, 0, 255], owner, 0, config::get_pixel(&program_id, INDEX).1);
+
+    // Only the owner names the delegate
+    assert_eq!(set_color_delegate(&program_id, &stranger, Some(delegate), pixel.pack()), Err(NftCanvasError::PixelOwnerKeyMismatch.into()));
+    assert_eq!(paint(&program_id, &delegate, [1, 2, 3], pixel.pack()), Err(NftCanvasError::ColorAuthorityKeyMismatch.into()));
+
+    let delegated = set_color_delegate(&program_id, &owner, Some(delegate), pixel.pack()).unwrap();
+    assert_eq!(Pixel::unpack(&delegated).unwrap().color_delegate, Some(delegate));
+
+    // Both the owner and the delegate paint, nobody else
+    let painted = paint(&program_id, &delegate, [1, 2, 3], delegated.clone()).unwrap();
+    assert_eq!(Pixel::unpack(&painted).unwrap().color, [1, 2, 3, 255]);
+    let painted = paint(&program_id, &owner, [4, 5, 6], painted).unwrap();
+    assert_eq!(Pixel::unpack(&painted).unwrap().color, [4, 5, 6, 255]);
+    assert_eq!(paint(&program_id, &stranger, [1, 2, 3], painted.clone()), Err(NftCanvasError::ColorAuthorityKeyMismatch.into()));
+
+    // The delegate cannot hand the pixel on
+    assert_eq!(set_color_delegate(&program_id, &delegate, Some(stranger), painted.clone()), Err(NftCanvasError::PixelOwnerKeyMismatch.into()));
+
+    // Removing the delegate
+    let removed = set_color_delegate(&program_id, &owner, None, painted).unwrap();
+    assert_eq!(Pixel::unpack(&removed).unwrap().color_delegate, None);
+    assert_eq!(paint(&program_id, &delegate, [1, 2, 3], removed), Err(NftCanvasError::ColorAuthorityKeyMismatch.into()));
+}
+
+#[test]
+fn test_transfer_clears_color_delegate() {
+    let owner = Pubkey::new_unique();
+    let delegate = Pubkey::new_unique();
+    let buyer = Pubkey::new_unique();
+    let mut pixel = Pixel::new(INDEX, [0, 0, 0, 255], owner, 100, 255);
+    pixel.color_delegate = Some(delegate);
+    assert!(pixel.is_color_authority(&delegate));
+
+    pixel.transfer_ownership(buyer);
+    assert_eq!(pixel.color_delegate, None);
+    assert!(!pixel.is_color_authority(&delegate));
+    assert!(pixel.is_color_authority(&buyer));
+}
+
+#[test]
+fn test_legacy_pixel() {
+    let program_id = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let legacy_pixel = LegacyPixel {
+        index: INDEX,
+        color: [0, 0, 0],
+        owner_wallet: owner,
+        sell_price: 100,
+        best_buy_info: None,
+    };
+
+    // Painted in place, keeping the legacy layout
+    let painted = paint(&program_id, &owner, [1, 2, 3], legacy_pixel.pack()).unwrap();
+    assert_eq!(painted.len(), LegacyPixel::PACKED_SIZE);
+    assert_eq!(LegacyPixel::unpack(&painted).unwrap(), LegacyPixel { color: [1, 2, 3], ..legacy_pixel.clone() });
+
+    // Removing the (missing) delegate rewrites the pixel in the legacy layout, naming one does not fit in it
+    assert_eq!(set_color_delegate(&program_id, &owner, None, painted.clone()), Ok(painted.clone()));
+    assert_eq!(set_color_delegate(&program_id, &owner, Some(Pubkey::new_unique()), painted.clone()), Err(NftCanvasError::LegacyPixelLayout.into()));
+
+    // Read as a current pixel, with the bump seed of its address
+    let pixel = Pixel::unpack_account(&program_id, &painted).unwrap();
+    assert_eq!(pixel.color, [1, 2, 3, 255]);
+    assert_eq!(pixel.bump_seed, config::get_pixel(&program_id, INDEX).1);
+    assert_eq!(pixel.to_legacy(), Some(LegacyPixel::unpack(&painted).unwrap()));
+}
@@ -0,0 +1,98 @@
+use solana_program::account_info::AccountInfo;
+use solana_program::bpf_loader;
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+
+use chikin_nft_canvas::color::{ColorMode, PixelColor, PALETTE_SIZE};
+use chikin_nft_canvas::config;
+use chikin_nft_canvas::error::NftCanvasError;
+use chikin_nft_canvas::instruction::NftCanvasInstruction;
+use chikin_nft_canvas::packable::Packable;
+use chikin_nft_canvas::processor;
+use chikin_nft_canvas::state::{CanvasConfig, Pixel};
+
+const INDEX: u32 = 3;
+
+/// Paints the pixel at `INDEX` in `color`, returning the pixel afterwards.
+fn paint(canvas_config: &CanvasConfig, color: PixelColor) -> Result<Pixel, ProgramError> {
+    let program_id = Pubkey::new_unique();
+    let wallet = Pubkey::new_unique();
+    let pixel = Pixel::new(INDEX, [0, 0, 0, 255], wallet, 0, config::get_pixel(&program_id, INDEX).1);
+    let instruction = NftCanvasInstruction::update_pixel_color(
+        program_id,
+        config::get_canvas_config(&program_id).0,
+        config::get_pixel(&program_id, INDEX).0,
+        wallet,
+        INDEX,
+        color,
+    );
+    let mut storage: Vec<(Pubkey, u64, Vec<u8>, Pubkey)> = vec![
+        (instruction.accounts[0].pubkey, 1, vec![], bpf_loader::id()),
+        (instruction.accounts[1].pubkey, 1, canvas_config.pack(), program_id),
+        (instruction.accounts[2].pubkey, 1, pixel.pack(), program_id),
+        (instruction.accounts[3].pubkey, 1, vec![], system_program::id()),
+    ];
+    let account_infos: Vec<AccountInfo> = storage.iter_mut().zip(&instruction.accounts)
+        .map(|((key, lamports, data, owner), meta)| {
+            AccountInfo::new(key, meta.is_signer, meta.is_writable, lamports, data, owner, false, 0)
+        })
+        .collect();
+    processor::process_instruction(&program_id, &account_infos, &instruction.data)?;
+    drop(account_infos);
+    Ok(Pixel::unpack(&storage[2].2).unwrap())
+}
+
+#[test]
+fn test_set_color_mode() {
+    let mut canvas_config = CanvasConfig::new(Pubkey::new_unique(), 255, 255);
+    assert_eq!(canvas_config.color_mode, ColorMode::Rgb);
+    assert!(canvas_config.palette().is_empty());
+
+    // The palette comes with the palette mode only
+    assert_eq!(canvas_config.set_color_mode(ColorMode::Rgba, &[[1, 2, 3]]), Err(NftCanvasError::InvalidPalette));
+    assert_eq!(canvas_config.set_color_mode(ColorMode::Palette, &[]), Err(NftCanvasError::InvalidPalette));
+    assert_eq!(canvas_config.set_color_mode(ColorMode::Palette, &[[0, 0, 0]; PALETTE_SIZE + 1]), Err(NftCanvasError::InvalidPalette));
+    assert_eq!(canvas_config.color_mode, ColorMode::Rgb);
+
+    canvas_config.set_color_mode(ColorMode::Palette, &[[1, 2, 3], [4, 5, 6]]).unwrap();
+    assert_eq!(canvas_config.palette(), &[[1, 2, 3], [4, 5, 6]]);
+    assert_eq!(CanvasConfig::unpack(&canvas_config.pack()).unwrap(), canvas_config);
+
+    // Switching back clears the palette
+    canvas_config.set_color_mode(ColorMode::Rgb, &[]).unwrap();
+    assert!(canvas_config.palette().is_empty());
+}
+
+#[test]
+fn test_resolve_color() {
+    let mut canvas_config = CanvasConfig::new(Pubkey::new_unique(), 255, 255);
+    assert_eq!(canvas_config.resolve_color(&PixelColor::Rgb([1, 2, 3])), Ok([1, 2, 3, 255]));
+    assert_eq!(canvas_config.resolve_color(&PixelColor::Rgba([1, 2, 3, 4])), Err(NftCanvasError::ColorModeMismatch));
+    assert_eq!(canvas_config.resolve_color(&PixelColor::Palette(0)), Err(NftCanvasError::ColorModeMismatch));
+
+    canvas_config.set_color_mode(ColorMode::Rgba, &[]).unwrap();
+    assert_eq!(canvas_config.resolve_color(&PixelColor::Rgb([1, 2, 3])), Ok([1, 2, 3, 255]));
+    assert_eq!(canvas_config.resolve_color(&PixelColor::Rgba([1, 2, 3, 4])), Ok([1, 2, 3, 4]));
+    assert_eq!(canvas_config.resolve_color(&PixelColor::Palette(0)), Err(NftCanvasError::ColorModeMismatch));
+
+    canvas_config.set_color_mode(ColorMode::Palette, &[[1, 2, 3], [4, 5, 6]]).unwrap();
+    assert_eq!(canvas_config.resolve_color(&PixelColor::Palette(1)), Ok([4, 5, 6, 255]));
+    assert_eq!(canvas_config.resolve_color(&PixelColor::Palette(2)), Err(NftCanvasError::ColorNotInPalette));
+    assert_eq!(canvas_config.resolve_color(&PixelColor::Rgb([4, 5, 6])), Err(NftCanvasError::ColorModeMismatch));
+}
+
+#[test]
+fn test_paint_color_mode() {
+    let mut canvas_config = CanvasConfig::new(Pubkey::new_unique(), 255, 255);
+    assert_eq!(paint(&canvas_config, PixelColor::Rgb([1, 2, 3])).unwrap().color, [1, 2, 3, 255]);
+    assert_eq!(paint(&canvas_config, PixelColor::Rgba([1, 2, 3, 4])), Err(NftCanvasError::ColorModeMismatch.into()));
+
+    canvas_config.set_color_mode(ColorMode::Rgba, &[]).unwrap();
+    assert_eq!(paint(&canvas_config, PixelColor::Rgba([1, 2, 3, 4])).unwrap().color, [1, 2, 3, 4]);
+
+    canvas_config.set_color_mode(ColorMode::Palette, &[[9, 8, 7]]).unwrap();
+    let pixel = paint(&canvas_config, PixelColor::Palette(0)).unwrap();
+    assert_eq!((pixel.color, pixel.index), ([9, 8, 7, 255], INDEX));
+    assert_eq!(paint(&canvas_config, PixelColor::Palette(1)), Err(NftCanvasError::ColorNotInPalette.into()));
+}
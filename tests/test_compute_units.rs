@@ -0,0 +1,442 @@
+//! Compute units consumed by each instruction path, checked against `tests/fixtures/compute_units.txt`.
+//!
+//! Only the BPF build is metered, so this runs under `cargo test-bpf --test test_compute_units`, which sets the `test-bpf` feature.
+//! Set `UPDATE_COMPUTE_UNITS=1` to rewrite the baseline after an intended change.
+#![cfg(feature = "test-bpf")]
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use log::{LevelFilter, Metadata, Record};
+use solana_program::instruction::Instruction;
+use solana_program::program_option::COption;
+use solana_program::program_pack::Pack;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+use solana_program::sysvar;
+use solana_program_test::*;
+use solana_sdk::account::Account;
+use solana_sdk::rent::Rent;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+use spl_token::state::{Account as TokenAccount, AccountState, Mint};
+
+use chikin_nft_canvas::allowlist::AllowlistTree;
+use chikin_nft_canvas::color::{ColorMode, PixelColor, PALETTE_SIZE};
+use chikin_nft_canvas::config;
+use chikin_nft_canvas::instruction::NftCanvasInstruction;
+use chikin_nft_canvas::packable::Packable;
+use chikin_nft_canvas::state::{CanvasConfig, CanvasStats, CanvasStatsShard, Pixel};
+
+const BASELINE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/compute_units.txt");
+const BASELINE_HEADER: &str = "\
+# Compute units consumed per instruction path, checked by tests/test_compute_units.rs
+# Regenerate with `UPDATE_COMPUTE_UNITS=1 cargo test-bpf --test test_compute_units`
+";
+/// How far past its baseline a path may go before failing.
+const REGRESSION_THRESHOLD_PERCENT: u64 = 5;
+const WALLET_LAMPORTS: u64 = 10_000_000_000;
+const WALLET_TOKENS: u64 = 1_000_000;
+
+/// Keeps the runtime's logs, which report the compute units consumed by each program invocation.
+struct ComputeUnitsLogger {
+    messages: Mutex<Vec<String>>,
+}
+
+impl log::Log for ComputeUnitsLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.target() == "solana_runtime::message_processor"
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            self.messages.lock().unwrap().push(record.args().to_string());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: ComputeUnitsLogger = ComputeUnitsLogger { messages: Mutex::new(Vec::new()) };
+
+fn token_account(wallet: &Pubkey, amount: u64) -> Account {
+    let mut data = vec![0; TokenAccount::LEN];
+    let token_account = TokenAccount {
+        mint: config::token_mint::id(),
+        owner: *wallet,
+        amount,
+        state: AccountState::Initialized,
+        ..TokenAccount::default()
+    };
+    TokenAccount::pack(token_account, &mut data).unwrap();
+    Account { lamports: Rent::default().minimum_balance(data.len()), data, owner: spl_token::id(), executable: false, rent_epoch: 0 }
+}
+
+struct Bench {
+    context: ProgramTestContext,
+    program_id: Pubkey,
+    consumed: Vec<(&'static str, u64)>,
+}
+
+impl Bench {
+    /// Sends `instruction` signed and paid for by `signer`, returning the compute units the program consumed.
+    async fn execute(&mut self, instruction: Instruction, signer: &Keypair) -> u64 {
+        LOGGER.messages.lock().unwrap().clear();
+        let recent_blockhash = self.context.banks_client.get_recent_blockhash().await.unwrap();
+        let transaction = Transaction::new_signed_with_payer(&[instruction], Some(&signer.pubkey()), &[signer], recent_blockhash);
+        self.context.banks_client.process_transaction(transaction).await.unwrap();
+
+        // (The outermost invocation is reported last, and includes the programs it invoked)
+        let prefix = format!("Program {} consumed ", self.program_id);
+        LOGGER.messages.lock().unwrap().iter().rev()
+            .find_map(|message| message.strip_prefix(&prefix)?.split_whitespace().next()?.parse().ok())
+            .expect("no compute units logged, is the program running as BPF?")
+    }
+
+    async fn run(&mut self, path: &'static str, instruction: Instruction, signer: &Keypair) {
+        let units = self.execute(instruction, signer).await;
+        self.consumed.push((path, units));
+    }
+
+    async fn pixel(&mut self, index: u32) -> Pixel {
+        let account = self.context.banks_client.get_account(config::get_pixel(&self.program_id, index).0).await.unwrap().unwrap();
+        Pixel::unpack(&account.data).unwrap()
+    }
+
+    fn mint_pixel(&self, wallet: &Keypair, index: u32) -> Instruction {
+        let program_id = self.program_id;
+        NftCanvasInstruction::mint_pixel(
+            program_id, config::get_canvas_config(&program_id).0, config::get_canvas_stats(&program_id).0, config::get_canvas_stats_shard(&program_id, config::stats_shard(index)).0, system_program::id(),
+            sysvar::rent::id(), config::get_fee_vault(&program_id).0,
+            config::get_pixel(&program_id, index).0, wallet.pubkey(), index, PixelColor::Rgb([1, 2, 3]), 0,
+        )
+    }
+
+    fn list_pixel(&self, wallet: &Keypair, index: u32, price: u64) -> Instruction {
+        let program_id = self.program_id;
+        NftCanvasInstruction::list_pixel(
+            program_id, config::get_canvas_config(&program_id).0, config::get_canvas_stats_shard(&program_id, config::stats_shard(index)).0,
+            config::get_pixel(&program_id, index).0, wallet.pubkey(), index, price,
+        )
+    }
+
+    fn list_pixel_sol(&self, wallet: &Keypair, index: u32, price: u64) -> Instruction {
+        let program_id = self.program_id;
+        NftCanvasInstruction::list_pixel_sol(
+            program_id, config::get_canvas_config(&program_id).0, config::get_canvas_stats_shard(&program_id, config::stats_shard(index)).0,
+            config::get_pixel(&program_id, index).0, wallet.pubkey(), index, price,
+        )
+    }
+
+    fn accept_bid(&self, wallet: &Keypair, index: u32, min_price: u64) -> Instruction {
+        let program_id = self.program_id;
+        let fee_vault = config::get_fee_vault(&program_id).0;
+        let trade_pool = config::get_trade_pool(&program_id).0;
+        NftCanvasInstruction::accept_bid(
+            program_id, config::get_canvas_config(&program_id).0, config::get_canvas_stats_shard(&program_id, config::stats_shard(index)).0, spl_token::id(),
+            config::token_mint::id(), system_program::id(), sysvar::rent::id(), spl_associated_token_account::id(),
+            config::get_pixel(&program_id, index).0, fee_vault, config::get_token_account(&fee_vault, &spl_token::id()),
+            trade_pool, config::get_token_account(&trade_pool, &spl_token::id()), wallet.pubkey(),
+            config::get_token_account(&wallet.pubkey(), &spl_token::id()), index, min_price,
+        )
+    }
+
+    #[allow(deprecated)]
+    fn sell_pixel(&self, wallet: &Keypair, index: u32, price: u64) -> Instruction {
+        let program_id = self.program_id;
+        let fee_vault = config::get_fee_vault(&program_id).0;
+        let trade_pool = config::get_trade_pool(&program_id).0;
+        NftCanvasInstruction::sell_pixel(
+            program_id, config::get_canvas_config(&program_id).0, config::get_canvas_stats_shard(&program_id, config::stats_shard(index)).0, spl_token::id(),
+            config::token_mint::id(), system_program::id(), sysvar::rent::id(), spl_associated_token_account::id(),
+            config::get_pixel(&program_id, index).0, fee_vault, config::get_token_account(&fee_vault, &spl_token::id()),
+            trade_pool, config::get_token_account(&trade_pool, &spl_token::id()), wallet.pubkey(),
+            config::get_token_account(&wallet.pubkey(), &spl_token::id()), index, price,
+        )
+    }
+
+    async fn buy_pixel(&mut self, wallet: &Keypair, index: u32, price: u64) -> Instruction {
+        let program_id = self.program_id;
+        let fee_vault = config::get_fee_vault(&program_id).0;
+        let trade_pool = config::get_trade_pool(&program_id).0;
+        let pixel = self.pixel(index).await;
+        NftCanvasInstruction::buy_pixel(
+            program_id, config::get_canvas_config(&program_id).0, config::get_canvas_stats_shard(&program_id, config::stats_shard(index)).0, spl_token::id(),
+            config::token_mint::id(), system_program::id(), sysvar::rent::id(), spl_associated_token_account::id(),
+            config::get_pixel(&program_id, index).0, fee_vault, config::get_token_account(&fee_vault, &spl_token::id()),
+            trade_pool, config::get_token_account(&trade_pool, &spl_token::id()), pixel.owner_wallet,
+            config::get_token_account(&pixel.owner_wallet, &spl_token::id()), wallet.pubkey(),
+            config::get_token_account(&wallet.pubkey(), &spl_token::id()),
+            pixel.best_buy_info.map(|buy_info| buy_info.buyer_wallet), index, price, 0,
+        )
+    }
+
+    fn cancel_bid(&self, wallet: &Keypair, index: u32) -> Instruction {
+        let program_id = self.program_id;
+        let trade_pool = config::get_trade_pool(&program_id).0;
+        NftCanvasInstruction::cancel_bid(
+            program_id, config::get_canvas_config(&program_id).0, config::get_canvas_stats_shard(&program_id, config::stats_shard(index)).0, spl_token::id(),
+            config::token_mint::id(), system_program::id(), sysvar::rent::id(), spl_associated_token_account::id(),
+            config::get_pixel(&program_id, index).0, trade_pool, config::get_token_account(&trade_pool, &spl_token::id()),
+            wallet.pubkey(), config::get_token_account(&wallet.pubkey(), &spl_token::id()), index,
+        )
+    }
+
+    fn accept_sol_bid(&self, wallet: &Keypair, index: u32, min_price: u64) -> Instruction {
+        let program_id = self.program_id;
+        NftCanvasInstruction::accept_sol_bid(
+            program_id, config::get_canvas_config(&program_id).0, config::get_canvas_stats_shard(&program_id, config::stats_shard(index)).0, system_program::id(),
+            config::get_pixel(&program_id, index).0, config::get_fee_vault(&program_id).0, config::get_trade_pool(&program_id).0,
+            wallet.pubkey(), index, min_price,
+        )
+    }
+
+    #[allow(deprecated)]
+    fn sell_pixel_sol(&self, wallet: &Keypair, index: u32, price: u64) -> Instruction {
+        let program_id = self.program_id;
+        NftCanvasInstruction::sell_pixel_sol(
+            program_id, config::get_canvas_config(&program_id).0, config::get_canvas_stats_shard(&program_id, config::stats_shard(index)).0, system_program::id(),
+            config::get_pixel(&program_id, index).0, config::get_fee_vault(&program_id).0, config::get_trade_pool(&program_id).0,
+            wallet.pubkey(), index, price,
+        )
+    }
+
+    async fn buy_pixel_sol(&mut self, wallet: &Keypair, index: u32, price: u64) -> Instruction {
+        let program_id = self.program_id;
+        let pixel = self.pixel(index).await;
+        NftCanvasInstruction::buy_pixel_sol(
+            program_id, config::get_canvas_config(&program_id).0, config::get_canvas_stats_shard(&program_id, config::stats_shard(index)).0, system_program::id(),
+            config::get_pixel(&program_id, index).0, config::get_fee_vault(&program_id).0, config::get_trade_pool(&program_id).0,
+            pixel.owner_wallet, wallet.pubkey(), pixel.best_sol_buy_info.map(|buy_info| buy_info.buyer_wallet), index, price, 0,
+        )
+    }
+
+    fn cancel_sol_bid(&self, wallet: &Keypair, index: u32) -> Instruction {
+        let program_id = self.program_id;
+        NftCanvasInstruction::cancel_sol_bid(
+            program_id, config::get_canvas_config(&program_id).0, config::get_canvas_stats_shard(&program_id, config::stats_shard(index)).0, system_program::id(),
+            config::get_pixel(&program_id, index).0, config::get_trade_pool(&program_id).0, wallet.pubkey(), index,
+        )
+    }
+
+    async fn close_pixel(&mut self, wallet: &Keypair, index: u32) -> Instruction {
+        let program_id = self.program_id;
+        let trade_pool = config::get_trade_pool(&program_id).0;
+        let pixel = self.pixel(index).await;
+        NftCanvasInstruction::close_pixel(
+            program_id, config::get_canvas_config(&program_id).0, config::get_canvas_stats(&program_id).0, config::get_canvas_stats_shard(&program_id, config::stats_shard(index)).0, system_program::id(),
+            spl_token::id(), config::token_mint::id(), sysvar::rent::id(), spl_associated_token_account::id(),
+            config::get_pixel(&program_id, index).0, trade_pool, config::get_token_account(&trade_pool, &spl_token::id()),
+            wallet.pubkey(), pixel.best_buy_info.map(|buy_info| buy_info.buyer_wallet),
+            pixel.best_sol_buy_info.map(|buy_info| buy_info.buyer_wallet), index,
+        )
+    }
+}
+
+fn load_baseline() -> HashMap<String, u64> {
+    std::fs::read_to_string(BASELINE).unwrap_or_default().lines()
+        .filter(|line| !line.starts_with('#') && !line.trim().is_empty())
+        .map(|line| {
+            let mut columns = line.split_whitespace();
+            let path = columns.next().unwrap().to_string();
+            let units = columns.next().and_then(|units| units.parse().ok()).unwrap_or_else(|| panic!("invalid baseline line: {}", line));
+            (path, units)
+        })
+        .collect()
+}
+
+fn save_baseline(consumed: &[(&str, u64)]) {
+    let mut baseline = String::from(BASELINE_HEADER);
+    for (path, units) in consumed {
+        baseline.push_str(&format!("{} {}\n", path, units));
+    }
+    std::fs::write(BASELINE, baseline).unwrap();
+}
+
+#[tokio::test]
+async fn test_compute_units() {
+    if find_file("chikin_nft_canvas.so").is_none() {
+        println!("chikin_nft_canvas.so not found, run `cargo test-bpf --test test_compute_units` to measure compute units");
+        return;
+    }
+    log::set_logger(&LOGGER).expect("another logger is installed");
+
+    let program_id = Pubkey::new_unique();
+    let admin = Keypair::new();
+    let seller = Keypair::new();
+    let buyer = Keypair::new();
+    let bidder = Keypair::new();
+    let fee_vault = config::get_fee_vault(&program_id).0;
+    let trade_pool = config::get_trade_pool(&program_id).0;
+
+    let mut program_test = ProgramTest::new("chikin_nft_canvas", program_id, None);
+    // (`ProgramTest::new` sets up its own logger, which only keeps the max level)
+    log::set_max_level(LevelFilter::Debug);
+    let mut mint_data = vec![0; Mint::LEN];
+    let mint = Mint { mint_authority: COption::None, supply: WALLET_TOKENS * 4, decimals: 0, is_initialized: true, freeze_authority: COption::None };
+    Mint::pack(mint, &mut mint_data).unwrap();
+    program_test.add_account(config::token_mint::id(), Account {
+        lamports: Rent::default().minimum_balance(Mint::LEN), data: mint_data, owner: spl_token::id(), executable: false, rent_epoch: 0,
+    });
+    let mut canvas_accounts = vec![
+        (config::get_canvas_config(&program_id).0, CanvasConfig::new(admin.pubkey(), config::get_trade_pool(&program_id).1, config::get_fee_vault(&program_id).1).pack()),
+        (config::get_canvas_stats(&program_id).0, CanvasStats::new().pack()),
+    ];
+    for shard in 0..config::STATS_SHARDS as u8 {
+        canvas_accounts.push((config::get_canvas_stats_shard(&program_id, shard).0, CanvasStatsShard::new(shard).pack()));
+    }
+    for (account, data) in canvas_accounts {
+        program_test.add_account(account, Account {
+            lamports: Rent::default().minimum_balance(data.len()), data, owner: program_id, executable: false, rent_epoch: 0,
+        });
+    }
+    // (Token accounts exist up front, so paths are measured without creating them)
+    for wallet in [&fee_vault, &trade_pool] {
+        program_test.add_account(config::get_token_account(wallet, &spl_token::id()), token_account(wallet, 0));
+    }
+    for wallet in [&admin, &seller, &buyer, &bidder] {
+        program_test.add_account(wallet.pubkey(), Account {
+            lamports: WALLET_LAMPORTS, data: vec![], owner: system_program::id(), executable: false, rent_epoch: 0,
+        });
+        program_test.add_account(config::get_token_account(&wallet.pubkey(), &spl_token::id()), token_account(&wallet.pubkey(), WALLET_TOKENS));
+    }
+
+    let mut bench = Bench { context: program_test.start_with_context().await, program_id, consumed: Vec::new() };
+
+    // Minting and painting
+    bench.run("mint_pixel_first", bench.mint_pixel(&seller, 0), &seller).await;
+    bench.run("mint_pixel", bench.mint_pixel(&seller, 1), &seller).await;
+    for index in 2..5 {
+        bench.execute(bench.mint_pixel(&seller, index), &seller).await;
+    }
+    let instruction = NftCanvasInstruction::update_pixel_color(
+        program_id, config::get_canvas_config(&program_id).0, config::get_pixel(&program_id, 0).0, seller.pubkey(), 0, PixelColor::Rgb([4, 5, 6]));
+    bench.run("update_pixel_color", instruction, &seller).await;
+    let instruction = NftCanvasInstruction::set_color_delegate(
+        program_id, config::get_canvas_config(&program_id).0, config::get_pixel(&program_id, 0).0, seller.pubkey(), Some(buyer.pubkey()), 0);
+    bench.run("set_color_delegate", instruction, &seller).await;
+
+    // Listings
+    bench.run("list_pixel", bench.list_pixel(&seller, 0, 1_000), &seller).await;
+    let instruction = NftCanvasInstruction::unlist_pixel(
+        program_id, config::get_canvas_config(&program_id).0, config::get_canvas_stats_shard(&program_id, config::stats_shard(0)).0, config::get_pixel(&program_id, 0).0, seller.pubkey(), 0);
+    bench.run("unlist_pixel", instruction, &seller).await;
+    bench.run("list_pixel_sol", bench.list_pixel_sol(&seller, 0, 1_000), &seller).await;
+    let instruction = NftCanvasInstruction::unlist_pixel_sol(
+        program_id, config::get_canvas_config(&program_id).0, config::get_canvas_stats_shard(&program_id, config::stats_shard(0)).0, config::get_pixel(&program_id, 0).0, seller.pubkey(), 0);
+    bench.run("unlist_pixel_sol", instruction, &seller).await;
+
+    // Token bids and sales
+    let instruction = bench.buy_pixel(&bidder, 1, 100).await;
+    bench.run("buy_pixel_bid", instruction, &bidder).await;
+    let instruction = bench.buy_pixel(&buyer, 1, 200).await;
+    bench.run("buy_pixel_outbid", instruction, &buyer).await;
+    bench.run("accept_bid", bench.accept_bid(&seller, 1, 200), &seller).await;
+    bench.execute(bench.list_pixel(&seller, 2, 300), &seller).await;
+    let instruction = bench.buy_pixel(&buyer, 2, 300).await;
+    bench.run("buy_pixel_listed", instruction, &buyer).await;
+    let instruction = bench.buy_pixel(&bidder, 3, 50).await;
+    bench.execute(instruction, &bidder).await;
+    bench.execute(bench.list_pixel(&seller, 3, 400), &seller).await;
+    let instruction = bench.buy_pixel(&bidder, 3, 400).await;
+    bench.run("buy_pixel_listed_own_bid", instruction, &bidder).await;
+    bench.run("sell_pixel_list", bench.sell_pixel(&seller, 0, 500), &seller).await;
+    let instruction = bench.buy_pixel(&buyer, 0, 100).await;
+    bench.execute(instruction, &buyer).await;
+    bench.run("sell_pixel_accept", bench.sell_pixel(&seller, 0, 100), &seller).await;
+    let instruction = bench.buy_pixel(&bidder, 2, 150).await;
+    bench.execute(instruction, &bidder).await;
+    bench.run("cancel_bid", bench.cancel_bid(&bidder, 2), &bidder).await;
+
+    // SOL bids and sales
+    let instruction = bench.buy_pixel_sol(&bidder, 2, 1_000).await;
+    bench.run("buy_pixel_sol_bid", instruction, &bidder).await;
+    let instruction = bench.buy_pixel_sol(&seller, 2, 2_000).await;
+    bench.run("buy_pixel_sol_outbid", instruction, &seller).await;
+    bench.run("accept_sol_bid", bench.accept_sol_bid(&buyer, 2, 2_000), &buyer).await;
+    bench.execute(bench.list_pixel_sol(&seller, 2, 3_000), &seller).await;
+    let instruction = bench.buy_pixel_sol(&bidder, 2, 3_000).await;
+    bench.run("buy_pixel_sol_listed", instruction, &bidder).await;
+    let instruction = bench.buy_pixel_sol(&seller, 3, 500).await;
+    bench.execute(instruction, &seller).await;
+    bench.execute(bench.list_pixel_sol(&bidder, 3, 4_000), &bidder).await;
+    let instruction = bench.buy_pixel_sol(&seller, 3, 4_000).await;
+    bench.run("buy_pixel_sol_listed_own_bid", instruction, &seller).await;
+    bench.run("sell_pixel_sol_list", bench.sell_pixel_sol(&buyer, 1, 5_000), &buyer).await;
+    let instruction = bench.buy_pixel_sol(&bidder, 1, 600).await;
+    bench.execute(instruction, &bidder).await;
+    bench.run("sell_pixel_sol_accept", bench.sell_pixel_sol(&buyer, 1, 600), &buyer).await;
+    let instruction = bench.buy_pixel_sol(&seller, 1, 700).await;
+    bench.execute(instruction, &seller).await;
+    bench.run("cancel_sol_bid", bench.cancel_sol_bid(&seller, 1), &seller).await;
+
+    // Closing, refunding both bids
+    let instruction = bench.buy_pixel(&buyer, 4, 100).await;
+    bench.execute(instruction, &buyer).await;
+    let instruction = bench.buy_pixel_sol(&bidder, 4, 100).await;
+    bench.execute(instruction, &bidder).await;
+    let instruction = bench.close_pixel(&seller, 4).await;
+    bench.run("close_pixel", instruction, &seller).await;
+
+    // Admin
+    let canvas_config = config::get_canvas_config(&program_id).0;
+    let allowlist = AllowlistTree::new(&[seller.pubkey()]);
+    let instruction = NftCanvasInstruction::set_presale(program_id, canvas_config, admin.pubkey(), 1, 2_000_000, 2, allowlist.root());
+    bench.run("set_presale", instruction, &admin).await;
+    let instruction = NftCanvasInstruction::presale_mint_pixel(
+        program_id, canvas_config, config::get_canvas_stats(&program_id).0, config::get_canvas_stats_shard(&program_id, config::stats_shard(5)).0, system_program::id(), sysvar::rent::id(), fee_vault,
+        config::get_pixel(&program_id, 5).0, seller.pubkey(),
+        config::get_presale_receipt(&program_id, &seller.pubkey()).0, 5, PixelColor::Rgb([1, 2, 3]), 0, &allowlist.proof(&seller.pubkey()).unwrap(),
+    );
+    bench.run("presale_mint_pixel", instruction, &seller).await;
+    let instruction = NftCanvasInstruction::set_presale(program_id, canvas_config, admin.pubkey(), 0, 0, 0, [0; 32]);
+    bench.execute(instruction, &admin).await;
+    let instruction = NftCanvasInstruction::set_paused(program_id, canvas_config, admin.pubkey(), 1, 1);
+    bench.run("set_paused", instruction, &admin).await;
+    let instruction = NftCanvasInstruction::set_paused(program_id, canvas_config, admin.pubkey(), 0, 0);
+    bench.execute(instruction, &admin).await;
+    let instruction = NftCanvasInstruction::set_mint_curve(program_id, canvas_config, admin.pubkey(), 2_000_000, 1_000);
+    bench.run("set_mint_curve", instruction, &admin).await;
+    let instruction = NftCanvasInstruction::set_color_mode(
+        program_id, canvas_config, admin.pubkey(), ColorMode::Palette, &[[0; 3]; PALETTE_SIZE]);
+    bench.run("set_color_mode", instruction, &admin).await;
+    let instruction = NftCanvasInstruction::update_pixel_color(
+        program_id, canvas_config, config::get_pixel(&program_id, 0).0, buyer.pubkey(), 0, PixelColor::Palette(1));
+    bench.run("update_pixel_color_palette", instruction, &buyer).await;
+    let instruction = NftCanvasInstruction::withdraw_fees(
+        program_id, canvas_config, system_program::id(), spl_token::id(), config::token_mint::id(), fee_vault,
+        config::get_token_account(&fee_vault, &spl_token::id()), admin.pubkey(), admin.pubkey(),
+        config::get_token_account(&admin.pubkey(), &spl_token::id()),
+    );
+    bench.run("withdraw_fees", instruction, &admin).await;
+
+    if std::env::var("UPDATE_COMPUTE_UNITS").is_ok() {
+        save_baseline(&bench.consumed);
+        return;
+    }
+
+    let baseline = load_baseline();
+    let mut regressions = Vec::new();
+    let mut missing = Vec::new();
+    println!("{:<30} {:>8} {:>8}", "path", "units", "baseline");
+    for (path, units) in &bench.consumed {
+        match baseline.get(*path) {
+            Some(baseline_units) => {
+                println!("{:<30} {:>8} {:>8}", path, units, baseline_units);
+                if *units > baseline_units + baseline_units * REGRESSION_THRESHOLD_PERCENT / 100 {
+                    regressions.push(format!("{}: {} (baseline {})", path, units, baseline_units));
+                }
+            }
+            None => {
+                println!("{:<30} {:>8} {:>8}", path, units, "-");
+                missing.push(*path);
+            }
+        }
+    }
+    assert!(
+        missing.is_empty(),
+        "no baseline for {}, regenerate it with `UPDATE_COMPUTE_UNITS=1 cargo test-bpf --test test_compute_units`",
+        missing.join(", "),
+    );
+    assert!(regressions.is_empty(), "compute units regressed past {}%:\n{}", REGRESSION_THRESHOLD_PERCENT, regressions.join("\n"));
+}
@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use solana_program::account_info::AccountInfo;
+use solana_program::bpf_loader;
+use solana_program::clock::Clock;
+use solana_program::entrypoint::{ProgramResult, SUCCESS};
+use solana_program::instruction::Instruction;
+use solana_program::program_option::COption;
+use solana_program::program_pack::Pack;
+use solana_program::program_stubs::{self, SyscallStubs};
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+use solana_program::sysvar;
+use spl_token::state::{Account as TokenAccount, AccountState, Mint};
+
+use chikin_nft_canvas::config;
+use chikin_nft_canvas::instruction::NftCanvasInstruction;
+use chikin_nft_canvas::packable::Packable;
+use chikin_nft_canvas::processor;
+use chikin_nft_canvas::state::{CanvasConfig, CanvasStats, CanvasStatsShard, Pixel, PixelBuyInfo};
+use chikin_nft_canvas::token;
+
+const INDEX: u32 = 13;
+
+/// The syscall stubs are global, so tests setting them take turns.
+static STUBS: Mutex<()> = Mutex::new(());
+
+/// Lamports, data and owner of each account. Accounts missing from it are empty system accounts.
+type Bank = HashMap<Pubkey, (u64, Vec<u8>, Pubkey)>;
+
+/// Records the invoked instructions, without running them.
+struct RecordingStubs {
+    invoked: Arc<Mutex<Vec<Instruction>>>,
+}
+
+impl SyscallStubs for RecordingStubs {
+    fn sol_invoke_signed(&self, instruction: &Instruction, _account_infos: &[AccountInfo], _signers_seeds: &[&[&[u8]]]) -> ProgramResult {
+        self.invoked.lock().unwrap().push(instruction.clone());
+        Ok(())
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Clock) = Clock::default() };
+        SUCCESS
+    }
+}
+
+/// The canvas accounts, with `pixel` minted and only the token accounts of `token_account_wallets`.
+fn canvas(program_id: &Pubkey, pixel: &Pixel, token_account_wallets: &[Pubkey]) -> Bank {
+    let mut mint_data = vec![0; Mint::LEN];
+    let mint = Mint { mint_authority: COption::None, supply: 0, decimals: 0, is_initialized: true, freeze_authority: COption::None };
+    Mint::pack(mint, &mut mint_data).unwrap();
+    let canvas_stats = CanvasStats { minted: 1, ..CanvasStats::new() };
+    let mut canvas_stats_shard = CanvasStatsShard::new(config::stats_shard(INDEX));
+    canvas_stats_shard.add_pixel(pixel);
+    let mut bank = Bank::new();
+    bank.insert(*program_id, (1, vec![], bpf_loader::id()));
+    bank.insert(config::get_canvas_config(program_id).0, (1, CanvasConfig::new(Pubkey::new_unique(), config::get_trade_pool(program_id).1, config::get_fee_vault(program_id).1).pack(), *program_id));
+    bank.insert(config::get_canvas_stats(program_id).0, (1, canvas_stats.pack(), *program_id));
+    bank.insert(config::get_canvas_stats_shard(program_id, config::stats_shard(INDEX)).0, (1, canvas_stats_shard.pack(), *program_id));
+    bank.insert(spl_token::id(), (1, vec![], bpf_loader::id()));
+    bank.insert(config::token_mint::id(), (1, mint_data, spl_token::id()));
+    bank.insert(sysvar::rent::id(), (1, vec![], sysvar::id()));
+    bank.insert(spl_associated_token_account::id(), (1, vec![], bpf_loader::id()));
+    bank.insert(config::get_pixel(program_id, INDEX).0, (1, pixel.pack(), *program_id));
+    for wallet in token_account_wallets {
+        // (The token program is not run)
+        bank.insert(token_account(wallet), (1, token_account_data(wallet), spl_token::id()));
+    }
+    bank
+}
+
+/// Runs `instruction` against `bank`, returning the invoked instructions.
+fn run(program_id: &Pubkey, instruction: &Instruction, bank: &mut Bank) -> (ProgramResult, Vec<Instruction>) {
+    let mut storage: Vec<(u64, Vec<u8>, Pubkey)> = instruction.accounts.iter()
+        .map(|meta| bank.get(&meta.pubkey).cloned().unwrap_or((0, vec![], system_program::id())))
+        .collect();
+    let account_infos: Vec<AccountInfo> = storage.iter_mut().zip(&instruction.accounts)
+        .map(|((lamports, data, owner), meta)| {
+            AccountInfo::new(&meta.pubkey, meta.is_signer, meta.is_writable, lamports, data, owner, false, 0)
+        })
+        .collect();
+
+    let _stubs = STUBS.lock().unwrap();
+    let invoked = Arc::new(Mutex::new(Vec::new()));
+    program_stubs::set_syscall_stubs(Box::new(RecordingStubs { invoked: invoked.clone() }));
+    let result = processor::process_instruction(program_id, &account_infos, &instruction.data);
+    drop(account_infos);
+    let invoked = invoked.lock().unwrap().clone();
+    (result, invoked)
+}
+
+fn token_account(wallet: &Pubkey) -> Pubkey {
+    config::get_token_account(wallet, &spl_token::id())
+}
+
+/// The associated token accounts created by `invoked`, with who paid for them.
+fn created(invoked: &[Instruction]) -> Vec<Instruction> {
+    invoked.iter()
+        .filter(|instruction| instruction.program_id == spl_associated_token_account::id())
+        .cloned()
+        .collect()
+}
+
+fn create(funder: &Pubkey, wallet: &Pubkey) -> Instruction {
+    token::create_associated_token_account(funder, &token_account(wallet), wallet, &config::token_mint::id(), &spl_token::id())
+}
+
+#[test]
+fn test_buy_creates_seller_token_accounts() {
+    let program_id = Pubkey::new_unique();
+    let pixel_owner = Pubkey::new_unique();
+    let buyer_wallet = Pubkey::new_unique();
+    let fee_vault = config::get_fee_vault(&program_id).0;
+    let trade_pool = config::get_trade_pool(&program_id).0;
+    let pixel = Pixel::new(INDEX, [1, 2, 3, 255], pixel_owner, 300, config::get_pixel(&program_id, INDEX).1);
+    let buy_pixel = NftCanvasInstruction::buy_pixel(
+        program_id,
+        config::get_canvas_config(&program_id).0,
+        config::get_canvas_stats_shard(&program_id, config::stats_shard(INDEX)).0,
+        spl_token::id(),
+        config::token_mint::id(),
+        system_program::id(),
+        sysvar::rent::id(),
+        spl_associated_token_account::id(),
+        config::get_pixel(&program_id, INDEX).0,
+        fee_vault,
+        token_account(&fee_vault),
+        trade_pool,
+        token_account(&trade_pool),
+        pixel_owner,
+        token_account(&pixel_owner),
+        buyer_wallet,
+        token_account(&buyer_wallet),
+        None,
+        INDEX,
+        300,
+        1,
+    );
+
+    // The buyer pays for the token accounts the payment goes to
+    let mut bank = canvas(&program_id, &pixel, &[buyer_wallet, trade_pool]);
+    let (result, invoked) = run(&program_id, &buy_pixel, &mut bank);
+    assert_eq!(result, Ok(()));
+    assert_eq!(created(&invoked), vec![create(&buyer_wallet, &pixel_owner), create(&buyer_wallet, &fee_vault)]);
+
+    // Existing ones are used as they are
+    let mut bank = canvas(&program_id, &pixel, &[buyer_wallet, trade_pool, pixel_owner, fee_vault]);
+    let (result, invoked) = run(&program_id, &buy_pixel, &mut bank);
+    assert_eq!(result, Ok(()));
+    assert_eq!(created(&invoked), vec![]);
+
+    // The buyer's own account is never created: the tokens are paid from it, so it has to exist already
+    let mut bank = canvas(&program_id, &pixel, &[trade_pool, pixel_owner, fee_vault]);
+    let (_, invoked) = run(&program_id, &buy_pixel, &mut bank);
+    assert_eq!(created(&invoked), vec![]);
+}
+
+#[test]
+fn test_refunds_create_bidder_token_accounts() {
+    let program_id = Pubkey::new_unique();
+    let pixel_owner = Pubkey::new_unique();
+    let buyer_wallet = Pubkey::new_unique();
+    let fee_vault = config::get_fee_vault(&program_id).0;
+    let trade_pool = config::get_trade_pool(&program_id).0;
+    let mut pixel = Pixel::new(INDEX, [1, 2, 3, 255], pixel_owner, 0, config::get_pixel(&program_id, INDEX).1);
+    pixel.best_buy_info = Some(PixelBuyInfo { price: 100, buyer_wallet });
+
+    // Accepting a bid pays the seller, who pays for their account
+    let accept_bid = NftCanvasInstruction::accept_bid(
+        program_id,
+        config::get_canvas_config(&program_id).0,
+        config::get_canvas_stats_shard(&program_id, config::stats_shard(INDEX)).0,
+        spl_token::id(),
+        config::token_mint::id(),
+        system_program::id(),
+        sysvar::rent::id(),
+        spl_associated_token_account::id(),
+        config::get_pixel(&program_id, INDEX).0,
+        fee_vault,
+        token_account(&fee_vault),
+        trade_pool,
+        token_account(&trade_pool),
+        pixel_owner,
+        token_account(&pixel_owner),
+        INDEX,
+        100,
+    );
+    let mut bank = canvas(&program_id, &pixel, &[trade_pool, fee_vault]);
+    let (result, invoked) = run(&program_id, &accept_bid, &mut bank);
+    assert_eq!(result, Ok(()));
+    assert_eq!(created(&invoked), vec![create(&pixel_owner, &pixel_owner)]);
+
+    // Cancelling refunds the bidder, who may have closed their account since
+    let cancel_bid = NftCanvasInstruction::cancel_bid(
+        program_id,
+        config::get_canvas_config(&program_id).0,
+        config::get_canvas_stats_shard(&program_id, config::stats_shard(INDEX)).0,
+        spl_token::id(),
+        config::token_mint::id(),
+        system_program::id(),
+        sysvar::rent::id(),
+        spl_associated_token_account::id(),
+        config::get_pixel(&program_id, INDEX).0,
+        trade_pool,
+        token_account(&trade_pool),
+        buyer_wallet,
+        token_account(&buyer_wallet),
+        INDEX,
+    );
+    let mut bank = canvas(&program_id, &pixel, &[trade_pool]);
+    let (result, invoked) = run(&program_id, &cancel_bid, &mut bank);
+    assert_eq!(result, Ok(()));
+    assert_eq!(created(&invoked), vec![create(&buyer_wallet, &buyer_wallet)]);
+
+    // Closing the pixel refunds the bidder too, at the owner's expense
+    let close_pixel = NftCanvasInstruction::close_pixel(
+        program_id,
+        config::get_canvas_config(&program_id).0,
+        config::get_canvas_stats(&program_id).0,
+        config::get_canvas_stats_shard(&program_id, config::stats_shard(INDEX)).0,
+        system_program::id(),
+        spl_token::id(),
+        config::token_mint::id(),
+        sysvar::rent::id(),
+        spl_associated_token_account::id(),
+        config::get_pixel(&program_id, INDEX).0,
+        trade_pool,
+        token_account(&trade_pool),
+        pixel_owner,
+        Some(buyer_wallet),
+        None,
+        INDEX,
+    );
+    let mut bank = canvas(&program_id, &pixel, &[trade_pool]);
+    let (result, invoked) = run(&program_id, &close_pixel, &mut bank);
+    assert_eq!(result, Ok(()));
+    assert_eq!(created(&invoked), vec![create(&pixel_owner, &buyer_wallet)]);
+}
+
+/// The data of an initialized token account of `wallet`.
+fn token_account_data(wallet: &Pubkey) -> Vec<u8> {
+    let mut data = vec![0; TokenAccount::LEN];
+    let token_account = TokenAccount { mint: config::token_mint::id(), owner: *wallet, state: AccountState::Initialized, ..TokenAccount::default() };
+    TokenAccount::pack(token_account, &mut data).unwrap();
+    data
+}
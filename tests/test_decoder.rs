@@ -0,0 +1,169 @@
+use solana_program::instruction::Instruction;
+use solana_program::message::Message;
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+
+use chikin_nft_canvas::color::{ColorMode, PixelColor};
+use chikin_nft_canvas::decoder;
+use chikin_nft_canvas::instruction::NftCanvasInstruction;
+use chikin_nft_canvas::packable::Packable;
+
+fn keys(count: usize) -> Vec<Pubkey> {
+    (0..count).map(|_| Pubkey::new_unique()).collect()
+}
+
+fn decode(instruction: &Instruction) -> decoder::DecodedInstruction {
+    let accounts: Vec<Pubkey> = instruction.accounts.iter().map(|account| account.pubkey).collect();
+    decoder::decode(&instruction.program_id, &accounts, &instruction.data).unwrap()
+}
+
+#[test]
+fn test_decode_buy_pixel() {
+    let k = keys(17);
+    let previous_buyer_wallet = Pubkey::new_unique();
+    let instruction = NftCanvasInstruction::buy_pixel(
+        k[0], k[1], k[2], k[3], k[4], k[5], k[6], k[7], k[8], k[9], k[10], k[11], k[12], k[13], k[14], k[15], k[16],
+        Some(previous_buyer_wallet), 7, 100, 1);
+    let decoded = decode(&instruction);
+    assert_eq!(decoded.program_id, k[0]);
+    assert_eq!(decoded.instruction, NftCanvasInstruction::BuyPixel { index: 7, price: 100, direct_only: 1 });
+    assert_eq!(decoded.accounts.len(), 19);
+    assert_eq!(decoded.account("pixel_account"), Some(&k[8]));
+    assert_eq!(decoded.account("buyer_wallet"), Some(&k[15]));
+    assert_eq!(decoded.account("previous_buyer_token_account"), Some(&instruction.accounts[17].pubkey));
+    assert_eq!(decoded.account("previous_buyer_wallet"), Some(&previous_buyer_wallet));
+
+    // Compiled into a message
+    let message = Message::new(std::slice::from_ref(&instruction), Some(&k[15]));
+    let decoded_compiled = decoder::decode_compiled(&message.account_keys, &message.instructions[0]).unwrap();
+    assert_eq!(decoded_compiled, decoded);
+
+    // Without the optional accounts, or missing some
+    let decoded = decode(&NftCanvasInstruction::buy_pixel(
+        k[0], k[1], k[2], k[3], k[4], k[5], k[6], k[7], k[8], k[9], k[10], k[11], k[12], k[13], k[14], k[15], k[16],
+        None, 7, 100, 1));
+    assert_eq!(decoded.account("previous_buyer_wallet"), None);
+    assert_eq!(
+        decoder::decode(&k[0], &k[..16], &instruction.data),
+        Err(ProgramError::NotEnoughAccountKeys));
+}
+
+#[test]
+fn test_decode_buy_pixel_sol() {
+    // (Named like the SOL bidder ClosePixel refunds)
+    let k = keys(9);
+    let previous_sol_buyer_wallet = Pubkey::new_unique();
+    let decoded = decode(&NftCanvasInstruction::buy_pixel_sol(
+        k[0], k[1], k[2], k[3], k[4], k[5], k[6], k[7], k[8], Some(previous_sol_buyer_wallet), 7, 100, 0));
+    assert_eq!(decoded.accounts.len(), 10);
+    assert_eq!(decoded.account("buyer_wallet"), Some(&k[8]));
+    assert_eq!(decoded.account("previous_sol_buyer_wallet"), Some(&previous_sol_buyer_wallet));
+}
+
+#[test]
+fn test_decode_close_pixel() {
+    let k = keys(13);
+    let previous_sol_buyer_wallet = Pubkey::new_unique();
+    let decoded = decode(&NftCanvasInstruction::close_pixel(
+        k[0], k[1], k[2], k[3], k[4], k[5], k[6], k[7], k[8], k[9], k[10], k[11], k[12],
+        None, Some(previous_sol_buyer_wallet), 3));
+    assert_eq!(decoded.account("canvas_stats_shard"), Some(&k[3]));
+    assert_eq!(decoded.account("pixel_owner"), Some(&k[12]));
+    assert_eq!(decoded.account("previous_buyer_wallet"), None);
+    assert_eq!(decoded.account("previous_sol_buyer_wallet"), Some(&previous_sol_buyer_wallet));
+
+    let previous_buyer_wallet = Pubkey::new_unique();
+    let mut instruction = NftCanvasInstruction::close_pixel(
+        k[0], k[1], k[2], k[3], k[4], k[5], k[6], k[7], k[8], k[9], k[10], k[11], k[12],
+        Some(previous_buyer_wallet), Some(previous_sol_buyer_wallet), 3);
+    let extra = Pubkey::new_unique();
+    instruction.accounts.push(solana_program::instruction::AccountMeta::new_readonly(extra, false));
+    let decoded = decode(&instruction);
+    assert_eq!(decoded.account("previous_buyer_wallet"), Some(&previous_buyer_wallet));
+    assert_eq!(decoded.account("previous_sol_buyer_wallet"), Some(&previous_sol_buyer_wallet));
+    assert_eq!(decoded.account("unused"), Some(&extra));
+}
+
+#[test]
+fn test_decode_hashes() {
+    let k = keys(10);
+    let proof = [[1u8; 32], [2u8; 32]];
+    let instruction = NftCanvasInstruction::presale_mint_pixel(
+        k[0], k[1], k[2], k[3], k[4], k[5], k[6], k[7], k[8], k[9], 5, PixelColor::Rgb([1, 2, 3]), 0, &proof);
+    let decoded = decode(&instruction);
+    assert_eq!(decoded.instruction, NftCanvasInstruction::MintPixel { index: 5, color: [1, 2, 3], sell_price: 0 });
+    assert_eq!(decoded.account("owner_wallet"), Some(&k[8]));
+    assert_eq!(decoded.account("presale_receipt"), Some(&k[9]));
+    assert_eq!(decoded.hashes, proof.to_vec());
+
+    let mut instruction = NftCanvasInstruction::update_pixel_color(k[0], k[1], k[2], k[3], 5, PixelColor::Rgb([1, 2, 3]));
+    assert_eq!(decode(&instruction).account("authority_wallet"), Some(&k[3]));
+    instruction.data.extend_from_slice(&[0u8; 32]);
+    let accounts: Vec<Pubkey> = instruction.accounts.iter().map(|account| account.pubkey).collect();
+    assert!(decoder::decode(&k[0], &accounts, &instruction.data).is_err());
+    assert!(decoder::decode(&k[0], &accounts, &[255]).is_err());
+}
+
+#[test]
+fn test_decode_color_variants() {
+    let k = keys(10);
+    let proof = [[7u8; 32]];
+
+    // RGB colors keep the original 16 byte encoding
+    let instruction = NftCanvasInstruction::mint_pixel(k[0], k[1], k[2], k[3], k[4], k[5], k[6], k[7], k[8], 5, PixelColor::Rgb([1, 2, 3]), 9);
+    assert_eq!(instruction.data, [&[0, 5, 0, 0, 0, 1, 2, 3][..], &9u64.to_le_bytes()].concat());
+    let instruction = NftCanvasInstruction::update_pixel_color(k[0], k[1], k[2], k[3], 5, PixelColor::Rgb([1, 2, 3]));
+    assert_eq!(instruction.data, [&[1, 5, 0, 0, 0, 1, 2, 3][..], &[0; 8]].concat());
+
+    // RGBA mints need a byte more, palette ones pack short
+    let instruction = NftCanvasInstruction::presale_mint_pixel(
+        k[0], k[1], k[2], k[3], k[4], k[5], k[6], k[7], k[8], k[9], 5, PixelColor::Rgba([1, 2, 3, 4]), 9, &proof);
+    assert_eq!(instruction.data.len(), 17 + 32);
+    let decoded = decode(&instruction);
+    assert_eq!(decoded.instruction, NftCanvasInstruction::MintPixelRgba { index: 5, color: [1, 2, 3, 4], sell_price: 9 });
+    assert_eq!(decoded.hashes, proof.to_vec());
+    let instruction = NftCanvasInstruction::presale_mint_pixel(
+        k[0], k[1], k[2], k[3], k[4], k[5], k[6], k[7], k[8], k[9], 5, PixelColor::Palette(2), 9, &proof);
+    assert_eq!(instruction.data.len(), NftCanvasInstruction::PACKED_SIZE + 32);
+    let decoded = decode(&instruction);
+    assert_eq!(decoded.instruction, NftCanvasInstruction::MintPixelPalette { index: 5, color: 2, sell_price: 9 });
+    assert_eq!(decoded.hashes, proof.to_vec());
+
+    let decoded = decode(&NftCanvasInstruction::update_pixel_color(k[0], k[1], k[2], k[3], 5, PixelColor::Palette(2)));
+    assert_eq!(decoded.instruction, NftCanvasInstruction::UpdatePixelColorPalette { index: 5, color: 2 });
+    assert_eq!(decoded.account("authority_wallet"), Some(&k[3]));
+
+    // (Cut short, the instruction does not unpack)
+    let instruction = NftCanvasInstruction::update_pixel_color(k[0], k[1], k[2], k[3], 5, PixelColor::Rgba([1, 2, 3, 4]));
+    assert_eq!(instruction.data.len(), NftCanvasInstruction::PACKED_SIZE);
+    let accounts: Vec<Pubkey> = instruction.accounts.iter().map(|account| account.pubkey).collect();
+    assert!(decoder::decode(&k[0], &accounts, &instruction.data[..8]).is_err());
+}
+
+#[test]
+fn test_decode_palette() {
+    let k = keys(3);
+    let palette = [[1, 2, 3], [4, 5, 6]];
+    let instruction = NftCanvasInstruction::set_color_mode(k[0], k[1], k[2], ColorMode::Palette, &palette);
+    let decoded = decode(&instruction);
+    assert_eq!(decoded.instruction, NftCanvasInstruction::SetColorMode { color_mode: ColorMode::Palette });
+    assert_eq!(decoded.account("admin_wallet"), Some(&k[2]));
+    assert_eq!(decoded.palette, palette.to_vec());
+    assert!(decoded.hashes.is_empty());
+
+    let mut instruction = instruction;
+    instruction.data.pop();
+    let accounts: Vec<Pubkey> = instruction.accounts.iter().map(|account| account.pubkey).collect();
+    assert!(decoder::decode(&k[0], &accounts, &instruction.data).is_err());
+}
+
+#[test]
+fn test_decode_initialize_stats_shard() {
+    let k = keys(6);
+    let decoded = decode(&NftCanvasInstruction::initialize_stats_shard(k[0], k[1], k[2], k[3], k[4], k[5], 3, 10, 0, 2, 0));
+    assert_eq!(
+        decoded.instruction,
+        NftCanvasInstruction::InitializeStatsShard { shard: 3, listings: 10, sol_listings: 0, open_bids: 2, open_sol_bids: 0 });
+    assert_eq!(decoded.account("canvas_stats_shard"), Some(&k[4]));
+    assert_eq!(decoded.account("admin_wallet"), Some(&k[5]));
+}
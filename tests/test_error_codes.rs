@@ -0,0 +1,21 @@
+use solana_program::program_error::ProgramError;
+
+use chikin_nft_canvas::error::NftCanvasError;
+
+#[test]
+fn test_error_codes() {
+    // (Clients depend on these codes)
+    assert_eq!(ProgramError::from(NftCanvasError::ProgramKeyMismatch), ProgramError::Custom(0));
+    assert_eq!(ProgramError::from(NftCanvasError::PixelOwnerKeyMismatch), ProgramError::Custom(9));
+    assert_eq!(ProgramError::from(NftCanvasError::Paused), ProgramError::Custom(30));
+    assert_eq!(ProgramError::from(NftCanvasError::CanvasStatsUninitialized), ProgramError::Custom(42));
+    assert_eq!(ProgramError::from(NftCanvasError::AccountNotWritable), ProgramError::Custom(46));
+
+    for code in 0..=54 {
+        let err = NftCanvasError::from_code(code).unwrap();
+        assert_eq!(err as u32, code);
+    }
+    assert_eq!(NftCanvasError::from_code(55), None);
+    assert_eq!(NftCanvasError::from_code(u32::MAX), None);
+    assert_eq!(NftCanvasError::PixelOwnerKeyMismatch.to_string(), "Wallet is not the pixel owner");
+}
@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use solana_program::account_info::AccountInfo;
+use solana_program::bpf_loader;
+use solana_program::clock::Clock;
+use solana_program::entrypoint::{ProgramResult, SUCCESS};
+use solana_program::instruction::Instruction;
+use solana_program::program_option::COption;
+use solana_program::program_pack::Pack;
+use solana_program::program_stubs::{self, SyscallStubs};
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+use solana_program::sysvar;
+use spl_token::state::{Account as TokenAccount, AccountState, Mint};
+
+use chikin_nft_canvas::config;
+use chikin_nft_canvas::error::NftCanvasError;
+use chikin_nft_canvas::instruction::NftCanvasInstruction;
+use chikin_nft_canvas::packable::Packable;
+use chikin_nft_canvas::processor;
+use chikin_nft_canvas::state::{CanvasConfig, CanvasStats, CanvasStatsShard, Pixel, PixelBuyInfo};
+use chikin_nft_canvas::token;
+
+const INDEX: u32 = 17;
+
+/// The syscall stubs are global, so tests setting them take turns.
+static STUBS: Mutex<()> = Mutex::new(());
+
+/// Lamports, data and owner of each account. Accounts missing from it are empty system accounts.
+type Bank = HashMap<Pubkey, (u64, Vec<u8>, Pubkey)>;
+
+/// Records the invoked instructions, without running them.
+struct RecordingStubs {
+    invoked: Arc<Mutex<Vec<Instruction>>>,
+}
+
+impl SyscallStubs for RecordingStubs {
+    fn sol_invoke_signed(&self, instruction: &Instruction, _account_infos: &[AccountInfo], _signers_seeds: &[&[&[u8]]]) -> ProgramResult {
+        self.invoked.lock().unwrap().push(instruction.clone());
+        Ok(())
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Clock) = Clock::default() };
+        SUCCESS
+    }
+}
+
+/// The canvas accounts, with `pixel` minted and only the token accounts of `token_account_wallets`.
+fn canvas(program_id: &Pubkey, pixel: &Pixel, token_account_wallets: &[Pubkey]) -> Bank {
+    let mut mint_data = vec![0; Mint::LEN];
+    let mint = Mint { mint_authority: COption::None, supply: 0, decimals: 0, is_initialized: true, freeze_authority: COption::None };
+    Mint::pack(mint, &mut mint_data).unwrap();
+    let canvas_stats = CanvasStats { minted: 1, ..CanvasStats::new() };
+    let mut canvas_stats_shard = CanvasStatsShard::new(config::stats_shard(INDEX));
+    canvas_stats_shard.add_pixel(pixel);
+    let mut bank = Bank::new();
+    bank.insert(*program_id, (1, vec![], bpf_loader::id()));
+    bank.insert(config::get_canvas_config(program_id).0, (1, CanvasConfig::new(Pubkey::new_unique(), config::get_trade_pool(program_id).1, config::get_fee_vault(program_id).1).pack(), *program_id));
+    bank.insert(config::get_canvas_stats(program_id).0, (1, canvas_stats.pack(), *program_id));
+    bank.insert(config::get_canvas_stats_shard(program_id, config::stats_shard(INDEX)).0, (1, canvas_stats_shard.pack(), *program_id));
+    bank.insert(spl_token::id(), (1, vec![], bpf_loader::id()));
+    bank.insert(config::token_mint::id(), (1, mint_data, spl_token::id()));
+    bank.insert(sysvar::rent::id(), (1, vec![], sysvar::id()));
+    bank.insert(spl_associated_token_account::id(), (1, vec![], bpf_loader::id()));
+    bank.insert(config::get_pixel(program_id, INDEX).0, (1, pixel.pack(), *program_id));
+    for wallet in token_account_wallets {
+        // (The token program is not run)
+        bank.insert(token_account(wallet), (1, token_account_data(wallet), spl_token::id()));
+    }
+    bank
+}
+
+/// Runs `instruction` against `bank`, returning the invoked instructions.
+fn run(program_id: &Pubkey, instruction: &Instruction, bank: &mut Bank) -> (ProgramResult, Vec<Instruction>) {
+    let mut storage: Vec<(u64, Vec<u8>, Pubkey)> = instruction.accounts.iter()
+        .map(|meta| bank.get(&meta.pubkey).cloned().unwrap_or((0, vec![], system_program::id())))
+        .collect();
+    let account_infos: Vec<AccountInfo> = storage.iter_mut().zip(&instruction.accounts)
+        .map(|((lamports, data, owner), meta)| {
+            AccountInfo::new(&meta.pubkey, meta.is_signer, meta.is_writable, lamports, data, owner, false, 0)
+        })
+        .collect();
+
+    let _stubs = STUBS.lock().unwrap();
+    let invoked = Arc::new(Mutex::new(Vec::new()));
+    program_stubs::set_syscall_stubs(Box::new(RecordingStubs { invoked: invoked.clone() }));
+    let result = processor::process_instruction(program_id, &account_infos, &instruction.data);
+    drop(account_infos);
+    if result.is_ok() {
+        for (meta, account) in instruction.accounts.iter().zip(storage) {
+            bank.insert(meta.pubkey, account);
+        }
+    }
+    let invoked = invoked.lock().unwrap().clone();
+    (result, invoked)
+}
+
+fn token_account(wallet: &Pubkey) -> Pubkey {
+    config::get_token_account(wallet, &spl_token::id())
+}
+
+/// The token transfer the trade pool signs for, out of its token account.
+fn pay_out(program_id: &Pubkey, wallet: &Pubkey, amount: u64) -> Instruction {
+    let trade_pool = config::get_trade_pool(program_id).0;
+    token::transfer_checked(&spl_token::id(), &token_account(&trade_pool), &config::token_mint::id(), &token_account(wallet), &trade_pool, amount, 0).unwrap()
+}
+
+fn pixel_in(program_id: &Pubkey, bank: &Bank) -> Pixel {
+    Pixel::unpack(&bank[&config::get_pixel(program_id, INDEX).0].1).unwrap()
+}
+
+fn accept_bid(program_id: &Pubkey, pixel_owner: &Pubkey, min_price: u64) -> Instruction {
+    let fee_vault = config::get_fee_vault(program_id).0;
+    let trade_pool = config::get_trade_pool(program_id).0;
+    NftCanvasInstruction::accept_bid(
+        *program_id,
+        config::get_canvas_config(program_id).0,
+        config::get_canvas_stats_shard(program_id, config::stats_shard(INDEX)).0,
+        spl_token::id(),
+        config::token_mint::id(),
+        system_program::id(),
+        sysvar::rent::id(),
+        spl_associated_token_account::id(),
+        config::get_pixel(program_id, INDEX).0,
+        fee_vault,
+        token_account(&fee_vault),
+        trade_pool,
+        token_account(&trade_pool),
+        *pixel_owner,
+        token_account(pixel_owner),
+        INDEX,
+        min_price,
+    )
+}
+
+#[test]
+fn test_list_never_sells() {
+    let program_id = Pubkey::new_unique();
+    let pixel_owner = Pubkey::new_unique();
+    let buyer_wallet = Pubkey::new_unique();
+    let mut pixel = Pixel::new(INDEX, [1, 2, 3, 255], pixel_owner, 0, config::get_pixel(&program_id, INDEX).1);
+    pixel.best_buy_info = Some(PixelBuyInfo { price: 500, buyer_wallet });
+    let mut bank = canvas(&program_id, &pixel, &[]);
+
+    // Listing below the best bid only lists
+    let list_pixel = NftCanvasInstruction::list_pixel(
+        program_id,
+        config::get_canvas_config(&program_id).0,
+        config::get_canvas_stats_shard(&program_id, config::stats_shard(INDEX)).0,
+        config::get_pixel(&program_id, INDEX).0,
+        pixel_owner,
+        INDEX,
+        400,
+    );
+    let (result, invoked) = run(&program_id, &list_pixel, &mut bank);
+    assert_eq!(result, Ok(()));
+    assert!(invoked.is_empty());
+    let listed = pixel_in(&program_id, &bank);
+    assert_eq!((listed.owner_wallet, listed.sell_price, listed.best_buy_info), (pixel_owner, 400, pixel.best_buy_info.clone()));
+
+    let unlist_pixel = NftCanvasInstruction::unlist_pixel(
+        program_id,
+        config::get_canvas_config(&program_id).0,
+        config::get_canvas_stats_shard(&program_id, config::stats_shard(INDEX)).0,
+        config::get_pixel(&program_id, INDEX).0,
+        pixel_owner,
+        INDEX,
+    );
+    let (result, invoked) = run(&program_id, &unlist_pixel, &mut bank);
+    assert_eq!(result, Ok(()));
+    assert!(invoked.is_empty());
+    assert_eq!(pixel_in(&program_id, &bank), pixel);
+
+    // Only the owner lists
+    let stranger = Pubkey::new_unique();
+    let list_pixel = NftCanvasInstruction::list_pixel(
+        program_id,
+        config::get_canvas_config(&program_id).0,
+        config::get_canvas_stats_shard(&program_id, config::stats_shard(INDEX)).0,
+        config::get_pixel(&program_id, INDEX).0,
+        stranger,
+        INDEX,
+        400,
+    );
+    assert_eq!(run(&program_id, &list_pixel, &mut bank).0, Err(NftCanvasError::PixelOwnerKeyMismatch.into()));
+}
+
+#[test]
+fn test_accept_bid() {
+    let program_id = Pubkey::new_unique();
+    let pixel_owner = Pubkey::new_unique();
+    let buyer_wallet = Pubkey::new_unique();
+    let fee_vault = config::get_fee_vault(&program_id).0;
+    let trade_pool = config::get_trade_pool(&program_id).0;
+    let mut pixel = Pixel::new(INDEX, [1, 2, 3, 255], pixel_owner, 900, config::get_pixel(&program_id, INDEX).1);
+    pixel.best_buy_info = Some(PixelBuyInfo { price: 500, buyer_wallet });
+    let mut bank = canvas(&program_id, &pixel, &[pixel_owner, fee_vault, trade_pool]);
+
+    // Never below the minimum price, and never lists
+    let (result, invoked) = run(&program_id, &accept_bid(&program_id, &pixel_owner, 501), &mut bank);
+    assert_eq!(result, Err(NftCanvasError::NoAcceptableBid.into()));
+    assert!(invoked.is_empty());
+
+    // Pays the bid out of escrow, 1% of it to the fee vault
+    let (result, invoked) = run(&program_id, &accept_bid(&program_id, &pixel_owner, 0), &mut bank);
+    assert_eq!(result, Ok(()));
+    assert_eq!(invoked, vec![pay_out(&program_id, &pixel_owner, 495), pay_out(&program_id, &fee_vault, 5)]);
+    let sold = pixel_in(&program_id, &bank);
+    assert_eq!((sold.owner_wallet, sold.sell_price, sold.best_buy_info), (buyer_wallet, 0, None));
+
+    // Without a bid there is nothing to accept
+    let pixel = Pixel::new(INDEX, [1, 2, 3, 255], pixel_owner, 0, config::get_pixel(&program_id, INDEX).1);
+    let mut bank = canvas(&program_id, &pixel, &[pixel_owner, fee_vault, trade_pool]);
+    assert_eq!(run(&program_id, &accept_bid(&program_id, &pixel_owner, 0), &mut bank).0, Err(NftCanvasError::NoAcceptableBid.into()));
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_sell_pixel_compatibility() {
+    let program_id = Pubkey::new_unique();
+    let pixel_owner = Pubkey::new_unique();
+    let buyer_wallet = Pubkey::new_unique();
+    let fee_vault = config::get_fee_vault(&program_id).0;
+    let trade_pool = config::get_trade_pool(&program_id).0;
+    let mut pixel = Pixel::new(INDEX, [1, 2, 3, 255], pixel_owner, 0, config::get_pixel(&program_id, INDEX).1);
+    pixel.best_buy_info = Some(PixelBuyInfo { price: 500, buyer_wallet });
+    let sell_pixel = |price| NftCanvasInstruction::sell_pixel(
+        program_id,
+        config::get_canvas_config(&program_id).0,
+        config::get_canvas_stats_shard(&program_id, config::stats_shard(INDEX)).0,
+        spl_token::id(),
+        config::token_mint::id(),
+        system_program::id(),
+        sysvar::rent::id(),
+        spl_associated_token_account::id(),
+        config::get_pixel(&program_id, INDEX).0,
+        fee_vault,
+        token_account(&fee_vault),
+        trade_pool,
+        token_account(&trade_pool),
+        pixel_owner,
+        token_account(&pixel_owner),
+        INDEX,
+        price,
+    );
+
+    // Still fills a bid at or above the price
+    let mut bank = canvas(&program_id, &pixel, &[pixel_owner, fee_vault, trade_pool]);
+    let (result, invoked) = run(&program_id, &sell_pixel(500), &mut bank);
+    assert_eq!(result, Ok(()));
+    assert_eq!(invoked, vec![pay_out(&program_id, &pixel_owner, 495), pay_out(&program_id, &fee_vault, 5)]);
+    assert_eq!(pixel_in(&program_id, &bank).owner_wallet, buyer_wallet);
+
+    // And lists above it, or unlists at 0
+    let mut bank = canvas(&program_id, &pixel, &[pixel_owner, fee_vault, trade_pool]);
+    let (result, invoked) = run(&program_id, &sell_pixel(600), &mut bank);
+    assert_eq!(result, Ok(()));
+    assert!(invoked.is_empty());
+    assert_eq!(pixel_in(&program_id, &bank).sell_price, 600);
+    let (result, invoked) = run(&program_id, &sell_pixel(0), &mut bank);
+    assert_eq!(result, Ok(()));
+    assert!(invoked.is_empty());
+    assert_eq!(pixel_in(&program_id, &bank), pixel);
+}
+
+/// The data of an initialized token account of `wallet`.
+fn token_account_data(wallet: &Pubkey) -> Vec<u8> {
+    let mut data = vec![0; TokenAccount::LEN];
+    let token_account = TokenAccount { mint: config::token_mint::id(), owner: *wallet, state: AccountState::Initialized, ..TokenAccount::default() };
+    TokenAccount::pack(token_account, &mut data).unwrap();
+    data
+}
@@ -0,0 +1,67 @@
+use solana_program::account_info::AccountInfo;
+use solana_program::bpf_loader;
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+
+use chikin_nft_canvas::color::PixelColor;
+use chikin_nft_canvas::config;
+use chikin_nft_canvas::error::NftCanvasError;
+use chikin_nft_canvas::instruction::NftCanvasInstruction;
+use chikin_nft_canvas::packable::Packable;
+use chikin_nft_canvas::processor;
+use chikin_nft_canvas::state::{CanvasConfig, Pixel};
+
+const INDEX: u32 = 7;
+
+/// Paints the pixel at `INDEX` as `wallet`, with the pixel account holding `pixel_data` and owned by `pixel_account_owner`.
+fn paint(program_id: &Pubkey, wallet: &Pubkey, pixel_data: Vec<u8>, pixel_lamports: u64, pixel_account_owner: &Pubkey) -> Result<(), ProgramError> {
+    let instruction = NftCanvasInstruction::update_pixel_color(
+        *program_id,
+        config::get_canvas_config(program_id).0,
+        config::get_pixel(program_id, INDEX).0,
+        *wallet,
+        INDEX,
+        PixelColor::Rgb([1, 2, 3]),
+    );
+    let mut storage: Vec<(Pubkey, u64, Vec<u8>, Pubkey)> = vec![
+        (instruction.accounts[0].pubkey, 1, vec![], bpf_loader::id()),
+        (instruction.accounts[1].pubkey, 1, CanvasConfig::new(Pubkey::new_unique(), config::get_trade_pool(program_id).1, config::get_fee_vault(program_id).1).pack(), *program_id),
+        (instruction.accounts[2].pubkey, pixel_lamports, pixel_data, *pixel_account_owner),
+        (instruction.accounts[3].pubkey, 1, vec![], system_program::id()),
+    ];
+    let account_infos: Vec<AccountInfo> = storage.iter_mut().zip(&instruction.accounts)
+        .map(|((key, lamports, data, owner), meta)| {
+            AccountInfo::new(key, meta.is_signer, meta.is_writable, lamports, data, owner, false, 0)
+        })
+        .collect();
+    processor::process_instruction(program_id, &account_infos, &instruction.data)
+}
+
+#[test]
+fn test_load_pixel() {
+    let program_id = Pubkey::new_unique();
+    let wallet = Pubkey::new_unique();
+    let bump_seed = config::get_pixel(&program_id, INDEX).1;
+    let pixel = Pixel::new(INDEX, [0, 0, 0, 255], wallet, 0, bump_seed);
+
+    assert_eq!(paint(&program_id, &wallet, pixel.pack(), 1, &program_id), Ok(()));
+
+    // Never minted (maybe pre-funded), or closed earlier in the transaction
+    assert_eq!(paint(&program_id, &wallet, vec![], 0, &system_program::id()), Err(NftCanvasError::PixelNotMinted.into()));
+    assert_eq!(paint(&program_id, &wallet, vec![], 1_000, &system_program::id()), Err(NftCanvasError::PixelNotMinted.into()));
+    assert_eq!(paint(&program_id, &wallet, pixel.pack(), 0, &program_id), Err(NftCanvasError::PixelNotMinted.into()));
+
+    // A look-alike account owned by another program
+    assert_eq!(paint(&program_id, &wallet, pixel.pack(), 1, &Pubkey::new_unique()), Err(NftCanvasError::PixelAccountOwnerMismatch.into()));
+
+    // Zeroed, truncated, or holding another pixel
+    assert_eq!(paint(&program_id, &wallet, vec![0; Pixel::PACKED_SIZE], 1, &program_id), Err(NftCanvasError::PixelUninitialized.into()));
+    assert_eq!(paint(&program_id, &wallet, vec![1; 10], 1, &program_id), Err(NftCanvasError::PixelUninitialized.into()));
+    let other_pixel = Pixel::new(INDEX + 1, [0, 0, 0, 255], wallet, 0, bump_seed);
+    assert_eq!(paint(&program_id, &wallet, other_pixel.pack(), 1, &program_id), Err(NftCanvasError::PixelUninitialized.into()));
+
+    // Stored bump seed not matching the pixel address
+    let wrong_bump_pixel = Pixel::new(INDEX, [0, 0, 0, 255], wallet, 0, bump_seed.wrapping_add(1));
+    assert_eq!(paint(&program_id, &wallet, wrong_bump_pixel.pack(), 1, &program_id), Err(NftCanvasError::PixelAccountKeyMismatch.into()));
+}
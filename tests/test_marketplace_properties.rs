@@ -0,0 +1,604 @@
+//! Runs random sequences of marketplace instructions through `processor::process_instruction`, against an in-memory
+//! bank emulating the system, token and associated token programs, and checks after every instruction that escrows,
+//! balances and pixel ownership stay consistent.
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
+
+use proptest::collection::vec;
+use proptest::prelude::*;
+use solana_program::account_info::AccountInfo;
+use solana_program::bpf_loader;
+use solana_program::clock::Clock;
+use solana_program::entrypoint::{ProgramResult, SUCCESS};
+use solana_program::instruction::Instruction;
+use solana_program::program_error::ProgramError;
+use solana_program::program_option::COption;
+use solana_program::program_pack::Pack;
+use solana_program::program_stubs::{self, SyscallStubs};
+use solana_program::pubkey::Pubkey;
+use solana_program::rent::Rent;
+use solana_program::system_program;
+use solana_program::sysvar;
+use spl_token::error::TokenError;
+use spl_token::instruction::TokenInstruction;
+use spl_token::state::{Account as TokenAccount, AccountState, Mint};
+
+use chikin_nft_canvas::color::PixelColor;
+use chikin_nft_canvas::config;
+use chikin_nft_canvas::instruction::NftCanvasInstruction;
+use chikin_nft_canvas::packable::Packable;
+use chikin_nft_canvas::processor;
+use chikin_nft_canvas::state::{CanvasConfig, CanvasStats, CanvasStatsShard, Pixel};
+
+const WALLETS: usize = 3;
+const PIXELS: u32 = 3;
+const WALLET_LAMPORTS: u64 = 1_000_000_000_000;
+const WALLET_TOKENS: u64 = 1_000_000;
+const MAX_PRICE: u64 = 1_000;
+
+#[derive(Clone, Debug, Default)]
+struct Account {
+    lamports: u64,
+    data: Vec<u8>,
+    owner: Pubkey,
+}
+
+/// Accounts the emulated programs assigned during the current instruction (`AccountInfo::owner` can't be changed
+/// from a CPI, so the bank applies them once the instruction succeeds).
+type Assigned = Arc<Mutex<HashMap<Pubkey, Pubkey>>>;
+
+/// Emulates the system, token and associated token programs, plus the clock sysvar.
+struct RuntimeStubs {
+    program_id: Pubkey,
+    assigned: Assigned,
+}
+
+impl RuntimeStubs {
+    fn owner_of(&self, account: &AccountInfo) -> Pubkey {
+        self.assigned.lock().unwrap().get(account.key).copied().unwrap_or(*account.owner)
+    }
+
+    fn assign(&self, account: &AccountInfo, owner: Pubkey) {
+        self.assigned.lock().unwrap().insert(*account.key, owner);
+    }
+
+    fn allocate(&self, account: &AccountInfo, space: usize) -> ProgramResult {
+        if !account.data_is_empty() || self.owner_of(account) != system_program::id() {
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+        *account.data.borrow_mut() = Box::leak(vec![0; space].into_boxed_slice());
+        Ok(())
+    }
+
+    fn move_lamports(&self, from: &AccountInfo, to: &AccountInfo, lamports: u64) -> ProgramResult {
+        if !from.data_is_empty() || self.owner_of(from) != system_program::id() {
+            return Err(ProgramError::InvalidArgument);
+        }
+        if from.lamports() < lamports {
+            return Err(ProgramError::Custom(1));
+        }
+        if from.key != to.key {
+            **from.lamports.borrow_mut() -= lamports;
+            **to.lamports.borrow_mut() += lamports;
+        }
+        Ok(())
+    }
+
+    fn invoke_system<'a>(&self, instruction: &Instruction, account: &dyn Fn(usize) -> Result<AccountInfo<'a>, ProgramError>, signed: &dyn Fn(usize) -> bool) -> ProgramResult {
+        let data = &instruction.data;
+        let u64_at = |offset: usize| {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(&data[offset..offset + 8]);
+            u64::from_le_bytes(bytes)
+        };
+        let pubkey_at = |offset: usize| Pubkey::new(&data[offset..offset + 32]);
+        match u32::from_le_bytes([data[0], data[1], data[2], data[3]]) {
+            // CreateAccount { lamports, space, owner }
+            0 => {
+                let (from, to) = (account(0)?, account(1)?);
+                if !signed(0) || !signed(1) {
+                    return Err(ProgramError::MissingRequiredSignature);
+                }
+                if to.lamports() != 0 {
+                    return Err(ProgramError::AccountAlreadyInitialized);
+                }
+                self.move_lamports(&from, &to, u64_at(4))?;
+                self.allocate(&to, u64_at(12) as usize)?;
+                self.assign(&to, pubkey_at(20));
+            }
+            // Assign { owner }
+            1 => {
+                if !signed(0) {
+                    return Err(ProgramError::MissingRequiredSignature);
+                }
+                self.assign(&account(0)?, pubkey_at(4));
+            }
+            // Transfer { lamports }
+            2 => {
+                if !signed(0) {
+                    return Err(ProgramError::MissingRequiredSignature);
+                }
+                self.move_lamports(&account(0)?, &account(1)?, u64_at(4))?;
+            }
+            // Allocate { space }
+            8 => {
+                if !signed(0) {
+                    return Err(ProgramError::MissingRequiredSignature);
+                }
+                self.allocate(&account(0)?, u64_at(4) as usize)?;
+            }
+            _ => return Err(ProgramError::InvalidInstructionData),
+        }
+        Ok(())
+    }
+
+    fn invoke_token<'a>(&self, instruction: &Instruction, account: &dyn Fn(usize) -> Result<AccountInfo<'a>, ProgramError>, signed: &dyn Fn(usize) -> bool) -> ProgramResult {
+        let (amount, decimals) = match TokenInstruction::unpack(&instruction.data)? {
+            TokenInstruction::TransferChecked { amount, decimals } => (amount, decimals),
+            _ => return Err(ProgramError::InvalidInstructionData),
+        };
+        let (source, mint, destination) = (account(0)?, account(1)?, account(2)?);
+        for token_account in [&source, &destination] {
+            if self.owner_of(token_account) != spl_token::id() {
+                return Err(ProgramError::IncorrectProgramId);
+            }
+        }
+        if Mint::unpack(&mint.data.borrow())?.decimals != decimals {
+            return Err(TokenError::MintDecimalsMismatch.into());
+        }
+        let mut source_state = TokenAccount::unpack(&source.data.borrow())?;
+        let mut destination_state = TokenAccount::unpack(&destination.data.borrow())?;
+        if source_state.mint != *mint.key || destination_state.mint != *mint.key {
+            return Err(TokenError::MintMismatch.into());
+        }
+        if source_state.owner != instruction.accounts[3].pubkey || !signed(3) {
+            return Err(TokenError::OwnerMismatch.into());
+        }
+        if source_state.amount < amount {
+            return Err(TokenError::InsufficientFunds.into());
+        }
+        if source.key != destination.key {
+            source_state.amount -= amount;
+            destination_state.amount = destination_state.amount.checked_add(amount).ok_or(TokenError::Overflow)?;
+            TokenAccount::pack(source_state, &mut source.data.borrow_mut())?;
+            TokenAccount::pack(destination_state, &mut destination.data.borrow_mut())?;
+        }
+        Ok(())
+    }
+
+    fn invoke_associated_token<'a>(&self, account: &dyn Fn(usize) -> Result<AccountInfo<'a>, ProgramError>, signed: &dyn Fn(usize) -> bool) -> ProgramResult {
+        let (funder, token_account, wallet, mint) = (account(0)?, account(1)?, account(2)?, account(3)?);
+        if token_account.key != &config::get_token_account(wallet.key, &spl_token::id()) {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        if !signed(0) {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        self.move_lamports(&funder, &token_account, Rent::default().minimum_balance(TokenAccount::LEN))?;
+        self.allocate(&token_account, TokenAccount::LEN)?;
+        self.assign(&token_account, spl_token::id());
+        let token_account_state = TokenAccount {
+            mint: *mint.key,
+            owner: *wallet.key,
+            state: AccountState::Initialized,
+            ..TokenAccount::default()
+        };
+        TokenAccount::pack(token_account_state, &mut token_account.data.borrow_mut())?;
+        Ok(())
+    }
+}
+
+impl SyscallStubs for RuntimeStubs {
+    fn sol_log(&self, _message: &str) {}
+
+    fn sol_invoke_signed(&self, instruction: &Instruction, account_infos: &[AccountInfo], signers_seeds: &[&[&[u8]]]) -> ProgramResult {
+        let account = |position: usize| {
+            let key = &instruction.accounts.get(position).ok_or(ProgramError::NotEnoughAccountKeys)?.pubkey;
+            account_infos.iter().find(|account_info| account_info.key == key).cloned().ok_or(ProgramError::NotEnoughAccountKeys)
+        };
+        let signed = |position: usize| {
+            let key = &instruction.accounts[position].pubkey;
+            account_infos.iter().any(|account_info| account_info.key == key && account_info.is_signer)
+                || signers_seeds.iter().any(|seeds| Pubkey::create_program_address(seeds, &self.program_id).as_ref() == Ok(key))
+        };
+        if instruction.program_id == system_program::id() {
+            self.invoke_system(instruction, &account, &signed)
+        } else if instruction.program_id == spl_token::id() {
+            self.invoke_token(instruction, &account, &signed)
+        } else if instruction.program_id == spl_associated_token_account::id() {
+            self.invoke_associated_token(&account, &signed)
+        } else {
+            Err(ProgramError::IncorrectProgramId)
+        }
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Clock) = Clock::default() };
+        SUCCESS
+    }
+}
+
+/// Who sends an op, resolved against the pixel when the op runs.
+#[derive(Clone, Copy, Debug)]
+enum Actor {
+    /// The pixel owner, or the first wallet while the pixel isn't minted.
+    Owner,
+    /// The best bidder in the op's currency, or the first wallet without one.
+    Bidder,
+    Wallet(usize),
+}
+
+fn actor() -> impl Strategy<Value = Actor> {
+    prop_oneof![
+        2 => Just(Actor::Owner),
+        1 => Just(Actor::Bidder),
+        2 => (0..WALLETS).prop_map(Actor::Wallet),
+    ]
+}
+
+#[derive(Clone, Debug)]
+enum Op {
+    Mint { actor: Actor, index: u32, sell_price: u64 },
+    UpdateColor { actor: Actor, index: u32, color: [u8; 3] },
+    List { actor: Actor, index: u32, price: u64 },
+    Unlist { actor: Actor, index: u32 },
+    Buy { actor: Actor, index: u32, price: u64, direct_only: bool },
+    AcceptBid { actor: Actor, index: u32, min_price: u64 },
+    CancelBid { actor: Actor, index: u32 },
+    ListSol { actor: Actor, index: u32, price: u64 },
+    BuySol { actor: Actor, index: u32, price: u64, direct_only: bool },
+    AcceptSolBid { actor: Actor, index: u32, min_price: u64 },
+    CancelSolBid { actor: Actor, index: u32 },
+    Close { actor: Actor, index: u32 },
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    let index = 0..PIXELS;
+    let price = 0..=MAX_PRICE;
+    prop_oneof![
+        (actor(), index.clone(), price.clone())
+            .prop_map(|(actor, index, sell_price)| Op::Mint { actor, index, sell_price }),
+        (actor(), index.clone(), any::<[u8; 3]>())
+            .prop_map(|(actor, index, color)| Op::UpdateColor { actor, index, color }),
+        (actor(), index.clone(), price.clone())
+            .prop_map(|(actor, index, price)| Op::List { actor, index, price }),
+        (actor(), index.clone())
+            .prop_map(|(actor, index)| Op::Unlist { actor, index }),
+        (actor(), index.clone(), price.clone(), any::<bool>())
+            .prop_map(|(actor, index, price, direct_only)| Op::Buy { actor, index, price, direct_only }),
+        (actor(), index.clone(), price.clone())
+            .prop_map(|(actor, index, min_price)| Op::AcceptBid { actor, index, min_price }),
+        (actor(), index.clone())
+            .prop_map(|(actor, index)| Op::CancelBid { actor, index }),
+        (actor(), index.clone(), price.clone())
+            .prop_map(|(actor, index, price)| Op::ListSol { actor, index, price }),
+        (actor(), index.clone(), price.clone(), any::<bool>())
+            .prop_map(|(actor, index, price, direct_only)| Op::BuySol { actor, index, price, direct_only }),
+        (actor(), index.clone(), price)
+            .prop_map(|(actor, index, min_price)| Op::AcceptSolBid { actor, index, min_price }),
+        (actor(), index.clone())
+            .prop_map(|(actor, index)| Op::CancelSolBid { actor, index }),
+        (actor(), index)
+            .prop_map(|(actor, index)| Op::Close { actor, index }),
+    ]
+}
+
+fn rent_data(rent: &Rent) -> Vec<u8> {
+    let mut data = rent.lamports_per_byte_year.to_le_bytes().to_vec();
+    data.extend_from_slice(&rent.exemption_threshold.to_le_bytes());
+    data.push(rent.burn_percent);
+    data
+}
+
+fn token_account_data(wallet: &Pubkey, amount: u64) -> Vec<u8> {
+    let mut data = vec![0; TokenAccount::LEN];
+    let token_account = TokenAccount {
+        mint: config::token_mint::id(),
+        owner: *wallet,
+        amount,
+        state: AccountState::Initialized,
+        ..TokenAccount::default()
+    };
+    TokenAccount::pack(token_account, &mut data).unwrap();
+    data
+}
+
+struct Bank {
+    program_id: Pubkey,
+    accounts: HashMap<Pubkey, Account>,
+    assigned: Assigned,
+    wallets: Vec<Pubkey>,
+    /// Pixels minted and not closed since, by the successful instructions so far.
+    minted: BTreeSet<u32>,
+    lamports: u64,
+    tokens: u64,
+}
+
+impl Bank {
+    fn new() -> Bank {
+        let program_id = Pubkey::new_unique();
+        let assigned = Assigned::default();
+        program_stubs::set_syscall_stubs(Box::new(RuntimeStubs { program_id, assigned: assigned.clone() }));
+
+        let wallets: Vec<Pubkey> = (0..WALLETS).map(|_| Pubkey::new_unique()).collect();
+        let trade_pool = config::get_trade_pool(&program_id).0;
+        let rent = Rent::default();
+        let token_account_lamports = rent.minimum_balance(TokenAccount::LEN);
+        // (Program accounts are rent exempt, like the program creates them)
+        let program_account = |data: Vec<u8>| Account { lamports: rent.minimum_balance(data.len()), data, owner: program_id };
+        let tokens = WALLET_TOKENS * WALLETS as u64;
+        let mut mint_data = vec![0; Mint::LEN];
+        let mint = Mint { mint_authority: COption::None, supply: tokens, decimals: 0, is_initialized: true, freeze_authority: COption::None };
+        Mint::pack(mint, &mut mint_data).unwrap();
+
+        let mut accounts = HashMap::new();
+        accounts.insert(program_id, Account { lamports: 1, data: vec![], owner: bpf_loader::id() });
+        accounts.insert(config::get_canvas_config(&program_id).0, program_account(CanvasConfig::new(Pubkey::new_unique(), config::get_trade_pool(&program_id).1, config::get_fee_vault(&program_id).1).pack()));
+        accounts.insert(config::get_canvas_stats(&program_id).0, program_account(CanvasStats::new().pack()));
+        for shard in 0..config::STATS_SHARDS as u8 {
+            accounts.insert(config::get_canvas_stats_shard(&program_id, shard).0, program_account(CanvasStatsShard::new(shard).pack()));
+        }
+        // (The reserves `InitializeConfig` funds)
+        accounts.insert(config::get_fee_vault(&program_id).0, Account { lamports: rent.minimum_balance(0), data: vec![], owner: system_program::id() });
+        accounts.insert(trade_pool, Account { lamports: rent.minimum_balance(0), data: vec![], owner: system_program::id() });
+        accounts.insert(config::token_mint::id(), Account { lamports: 1, data: mint_data, owner: spl_token::id() });
+        accounts.insert(sysvar::rent::id(), Account { lamports: 1, data: rent_data(&rent), owner: sysvar::id() });
+        accounts.insert(config::get_token_account(&trade_pool, &spl_token::id()), Account { lamports: token_account_lamports, data: token_account_data(&trade_pool, 0), owner: spl_token::id() });
+        for wallet in &wallets {
+            accounts.insert(*wallet, Account { lamports: WALLET_LAMPORTS, data: vec![], owner: system_program::id() });
+            accounts.insert(config::get_token_account(wallet, &spl_token::id()), Account { lamports: token_account_lamports, data: token_account_data(wallet, WALLET_TOKENS), owner: spl_token::id() });
+        }
+        let lamports = accounts.values().map(|account| account.lamports).sum();
+
+        Bank { program_id, accounts, assigned, wallets, minted: BTreeSet::new(), lamports, tokens }
+    }
+
+    /// Runs `instruction` as a transaction: its changes are kept only if it succeeds.
+    fn execute(&mut self, instruction: &Instruction) -> ProgramResult {
+        let mut keys: Vec<Pubkey> = Vec::new();
+        for meta in &instruction.accounts {
+            if !keys.contains(&meta.pubkey) {
+                keys.push(meta.pubkey);
+            }
+        }
+        let before: Vec<Account> = keys.iter()
+            .map(|key| self.accounts.get(key).cloned().unwrap_or(Account { owner: system_program::id(), ..Account::default() }))
+            .collect();
+        let mut storage: Vec<(Pubkey, u64, Vec<u8>, Pubkey)> = keys.iter().zip(&before)
+            .map(|(key, account)| (*key, account.lamports, account.data.clone(), account.owner))
+            .collect();
+        let unique_account_infos: Vec<AccountInfo> = storage.iter_mut()
+            .map(|(key, lamports, data, owner)| {
+                let metas = || instruction.accounts.iter().filter(|meta| meta.pubkey == *key);
+                let is_signer = metas().any(|meta| meta.is_signer);
+                let is_writable = metas().any(|meta| meta.is_writable);
+                AccountInfo::new(key, is_signer, is_writable, lamports, data, owner, false, 0)
+            })
+            .collect();
+        // (Accounts passed twice share the same info, as with the runtime)
+        let account_infos: Vec<AccountInfo> = instruction.accounts.iter()
+            .map(|meta| unique_account_infos[keys.iter().position(|key| key == &meta.pubkey).unwrap()].clone())
+            .collect();
+
+        self.assigned.lock().unwrap().clear();
+        processor::process_instruction(&self.program_id, &account_infos, &instruction.data)?;
+
+        let assigned = self.assigned.lock().unwrap().clone();
+        let after: Vec<Account> = unique_account_infos.iter()
+            .map(|account_info| Account {
+                lamports: account_info.lamports(),
+                data: account_info.data.borrow().to_vec(),
+                owner: assigned.get(account_info.key).copied().unwrap_or(*account_info.owner),
+            })
+            .collect();
+        assert_eq!(
+            before.iter().map(|account| account.lamports).sum::<u64>(),
+            after.iter().map(|account| account.lamports).sum::<u64>(),
+            "lamports created or destroyed by {:?}", instruction.data,
+        );
+        for ((account_info, before), after) in unique_account_infos.iter().zip(&before).zip(&after) {
+            if !account_info.is_writable {
+                assert!(before.lamports == after.lamports && before.data == after.data, "read-only account {} changed", account_info.key);
+            }
+        }
+        // (The runtime rejects transactions leaving a writable account with lamports, but not rent exempt)
+        let rent = Rent::default();
+        if unique_account_infos.iter().zip(&after)
+            .any(|(account_info, after)| account_info.is_writable && after.lamports != 0 && !rent.is_exempt(after.lamports, after.data.len())) {
+            return Err(ProgramError::InsufficientFunds);
+        }
+        for (key, account) in keys.into_iter().zip(after) {
+            // (Accounts left without lamports are purged)
+            if account.lamports == 0 {
+                self.accounts.remove(&key);
+            } else {
+                self.accounts.insert(key, account);
+            }
+        }
+        Ok(())
+    }
+
+    fn pixel(&self, index: u32) -> Option<Pixel> {
+        let account = self.accounts.get(&config::get_pixel(&self.program_id, index).0)?;
+        Some(Pixel::unpack(&account.data).unwrap())
+    }
+
+    fn token_amount(&self, wallet: &Pubkey) -> u64 {
+        self.accounts.get(&config::get_token_account(wallet, &spl_token::id()))
+            .map(|account| TokenAccount::unpack(&account.data).unwrap().amount)
+            .unwrap_or(0)
+    }
+
+    fn wallet(&self, actor: Actor, index: u32, sol: bool) -> Pubkey {
+        let pixel = self.pixel(index);
+        let wallet = match actor {
+            Actor::Owner => pixel.map(|pixel| pixel.owner_wallet),
+            Actor::Bidder if sol => pixel.and_then(|pixel| pixel.best_sol_buy_info).map(|buy_info| buy_info.buyer_wallet),
+            Actor::Bidder => pixel.and_then(|pixel| pixel.best_buy_info).map(|buy_info| buy_info.buyer_wallet),
+            Actor::Wallet(wallet) => Some(self.wallets[wallet]),
+        };
+        wallet.unwrap_or(self.wallets[0])
+    }
+
+    /// Builds the instruction for `op` as a client would, looking up the pixel owner and the bidders to refund.
+    fn instruction(&self, op: &Op) -> Instruction {
+        let program_id = self.program_id;
+        let canvas_config = config::get_canvas_config(&program_id).0;
+        let canvas_stats = config::get_canvas_stats(&program_id).0;
+        let fee_vault = config::get_fee_vault(&program_id).0;
+        let trade_pool = config::get_trade_pool(&program_id).0;
+        let token_account = |wallet: &Pubkey| config::get_token_account(wallet, &spl_token::id());
+        let canvas_stats_shard = |index: u32| config::get_canvas_stats_shard(&program_id, config::stats_shard(index)).0;
+        let pixel_account = |index: u32| config::get_pixel(&program_id, index).0;
+        let pixel_owner = |index: u32| self.wallet(Actor::Owner, index, false);
+        let buyer = |index: u32| self.pixel(index).and_then(|pixel| pixel.best_buy_info).map(|buy_info| buy_info.buyer_wallet);
+        let sol_buyer = |index: u32| self.pixel(index).and_then(|pixel| pixel.best_sol_buy_info).map(|buy_info| buy_info.buyer_wallet);
+
+        match *op {
+            Op::Mint { actor, index, sell_price } => NftCanvasInstruction::mint_pixel(
+                program_id, canvas_config, canvas_stats, canvas_stats_shard(index), system_program::id(), sysvar::rent::id(), fee_vault,
+                pixel_account(index), self.wallet(actor, index, false), index, PixelColor::Rgb([0, 0, 0]), sell_price,
+            ),
+            Op::UpdateColor { actor, index, color } => NftCanvasInstruction::update_pixel_color(
+                program_id, canvas_config, pixel_account(index), self.wallet(actor, index, false), index, PixelColor::Rgb(color),
+            ),
+            Op::List { actor, index, price } => NftCanvasInstruction::list_pixel(
+                program_id, canvas_config, canvas_stats_shard(index), pixel_account(index), self.wallet(actor, index, false), index, price,
+            ),
+            Op::Unlist { actor, index } => NftCanvasInstruction::unlist_pixel(
+                program_id, canvas_config, canvas_stats_shard(index), pixel_account(index), self.wallet(actor, index, false), index,
+            ),
+            Op::Buy { actor, index, price, direct_only } => {
+                let (wallet, pixel_owner) = (self.wallet(actor, index, false), pixel_owner(index));
+                NftCanvasInstruction::buy_pixel(
+                    program_id, canvas_config, canvas_stats_shard(index), spl_token::id(), config::token_mint::id(), system_program::id(),
+                    sysvar::rent::id(), spl_associated_token_account::id(), pixel_account(index), fee_vault,
+                    token_account(&fee_vault), trade_pool, token_account(&trade_pool), pixel_owner, token_account(&pixel_owner),
+                    wallet, token_account(&wallet), buyer(index), index, price, direct_only as u8,
+                )
+            }
+            Op::AcceptBid { actor, index, min_price } => {
+                let wallet = self.wallet(actor, index, false);
+                NftCanvasInstruction::accept_bid(
+                    program_id, canvas_config, canvas_stats_shard(index), spl_token::id(), config::token_mint::id(), system_program::id(),
+                    sysvar::rent::id(), spl_associated_token_account::id(), pixel_account(index), fee_vault,
+                    token_account(&fee_vault), trade_pool, token_account(&trade_pool), wallet, token_account(&wallet),
+                    index, min_price,
+                )
+            }
+            Op::CancelBid { actor, index } => {
+                let wallet = self.wallet(actor, index, false);
+                NftCanvasInstruction::cancel_bid(
+                    program_id, canvas_config, canvas_stats_shard(index), spl_token::id(), config::token_mint::id(), system_program::id(),
+                    sysvar::rent::id(), spl_associated_token_account::id(), pixel_account(index), trade_pool,
+                    token_account(&trade_pool), wallet, token_account(&wallet), index,
+                )
+            }
+            Op::ListSol { actor, index, price } => NftCanvasInstruction::list_pixel_sol(
+                program_id, canvas_config, canvas_stats_shard(index), pixel_account(index), self.wallet(actor, index, true), index, price,
+            ),
+            Op::BuySol { actor, index, price, direct_only } => NftCanvasInstruction::buy_pixel_sol(
+                program_id, canvas_config, canvas_stats_shard(index), system_program::id(), pixel_account(index), fee_vault, trade_pool,
+                pixel_owner(index), self.wallet(actor, index, true), sol_buyer(index), index, price, direct_only as u8,
+            ),
+            Op::AcceptSolBid { actor, index, min_price } => NftCanvasInstruction::accept_sol_bid(
+                program_id, canvas_config, canvas_stats_shard(index), system_program::id(), pixel_account(index), fee_vault, trade_pool,
+                self.wallet(actor, index, true), index, min_price,
+            ),
+            Op::CancelSolBid { actor, index } => NftCanvasInstruction::cancel_sol_bid(
+                program_id, canvas_config, canvas_stats_shard(index), system_program::id(), pixel_account(index), trade_pool,
+                self.wallet(actor, index, true), index,
+            ),
+            Op::Close { actor, index } => NftCanvasInstruction::close_pixel(
+                program_id, canvas_config, canvas_stats, canvas_stats_shard(index), system_program::id(), spl_token::id(), config::token_mint::id(),
+                sysvar::rent::id(), spl_associated_token_account::id(), pixel_account(index), trade_pool,
+                token_account(&trade_pool), self.wallet(actor, index, false), buyer(index), sol_buyer(index), index,
+            ),
+        }
+    }
+
+    fn apply(&mut self, op: &Op) -> ProgramResult {
+        let instruction = self.instruction(op);
+        self.execute(&instruction)?;
+        match *op {
+            Op::Mint { index, .. } => assert!(self.minted.insert(index), "pixel {} minted twice", index),
+            Op::Close { index, .. } => assert!(self.minted.remove(&index), "pixel {} closed while not minted", index),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn check_invariants(&self) {
+        let trade_pool = config::get_trade_pool(&self.program_id).0;
+        let pixels: Vec<Pixel> = (0..PIXELS).filter_map(|index| self.pixel(index)).collect();
+
+        // Every minted pixel has a single account, owned by the program and by one of the wallets
+        let minted: BTreeSet<u32> = (0..PIXELS).filter(|index| self.pixel(*index).is_some()).collect();
+        assert_eq!(minted, self.minted);
+        for (index, pixel) in self.minted.iter().zip(&pixels) {
+            assert_eq!(self.accounts[&config::get_pixel(&self.program_id, *index).0].owner, self.program_id);
+            assert_eq!(pixel.index, *index);
+            assert!(self.wallets.contains(&pixel.owner_wallet), "pixel {} owned by {}", index, pixel.owner_wallet);
+        }
+
+        // The trade pool holds exactly the open bids, on top of its rent exempt reserve
+        let open_bids: u64 = pixels.iter().filter_map(|pixel| pixel.best_buy_info.as_ref()).map(|buy_info| buy_info.price).sum();
+        assert_eq!(self.token_amount(&trade_pool), open_bids);
+        let open_sol_bids: u64 = pixels.iter().filter_map(|pixel| pixel.best_sol_buy_info.as_ref()).map(|buy_info| buy_info.price).sum();
+        assert_eq!(self.accounts[&trade_pool].lamports, Rent::default().minimum_balance(0) + open_sol_bids);
+
+        // Nothing is created or destroyed
+        let token_accounts = self.accounts.values()
+            .filter(|account| account.owner == spl_token::id() && account.data.len() == TokenAccount::LEN);
+        assert_eq!(token_accounts.map(|account| TokenAccount::unpack(&account.data).unwrap().amount).sum::<u64>(), self.tokens);
+        assert_eq!(self.accounts.values().map(|account| account.lamports).sum::<u64>(), self.lamports);
+
+        // The stats count what the pixels say
+        let canvas_stats = CanvasStats::unpack(&self.accounts[&config::get_canvas_stats(&self.program_id).0].data).unwrap();
+        assert_eq!(canvas_stats.minted as usize, pixels.len());
+        let mut stats = CanvasStatsShard::default();
+        let mut expected_stats = CanvasStatsShard::default();
+        for shard in 0..config::STATS_SHARDS as u8 {
+            let shard_stats = CanvasStatsShard::unpack(&self.accounts[&config::get_canvas_stats_shard(&self.program_id, shard).0].data).unwrap();
+            assert_eq!(shard_stats.shard, shard);
+            stats.merge(&shard_stats);
+        }
+        pixels.iter().for_each(|pixel| expected_stats.add_pixel(pixel));
+        assert_eq!(
+            (stats.listings, stats.sol_listings, stats.open_bids, stats.open_sol_bids),
+            (expected_stats.listings, expected_stats.sol_listings, expected_stats.open_bids, expected_stats.open_sol_bids),
+        );
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn test_marketplace_invariants(ops in vec(op(), 1..48)) {
+        let mut bank = Bank::new();
+        for op in &ops {
+            let _ = bank.apply(op);
+            bank.check_invariants();
+        }
+
+        // Every open bid can still be cancelled by its bidder, leaving nothing in escrow
+        for index in 0..PIXELS {
+            let pixel = match bank.pixel(index) {
+                Some(pixel) => pixel,
+                None => continue,
+            };
+            if pixel.best_buy_info.is_some() {
+                prop_assert_eq!(bank.apply(&Op::CancelBid { actor: Actor::Bidder, index }), Ok(()));
+            }
+            if pixel.best_sol_buy_info.is_some() {
+                prop_assert_eq!(bank.apply(&Op::CancelSolBid { actor: Actor::Bidder, index }), Ok(()));
+            }
+            bank.check_invariants();
+        }
+        let trade_pool = config::get_trade_pool(&bank.program_id).0;
+        prop_assert_eq!(bank.token_amount(&trade_pool), 0);
+        prop_assert_eq!(bank.accounts[&trade_pool].lamports, Rent::default().minimum_balance(0));
+    }
+}
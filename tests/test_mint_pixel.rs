@@ -0,0 +1,181 @@
+use std::sync::{Arc, Mutex};
+
+use solana_program::account_info::AccountInfo;
+use solana_program::bpf_loader;
+use solana_program::entrypoint::ProgramResult;
+use solana_program::instruction::Instruction;
+use solana_program::program_error::ProgramError;
+use solana_program::program_stubs::{self, SyscallStubs};
+use solana_program::pubkey::Pubkey;
+use solana_program::rent::Rent;
+use solana_program::system_instruction;
+use solana_program::system_program;
+use solana_program::sysvar;
+
+use chikin_nft_canvas::color::PixelColor;
+use chikin_nft_canvas::config;
+use chikin_nft_canvas::error::NftCanvasError;
+use chikin_nft_canvas::instruction::NftCanvasInstruction;
+use chikin_nft_canvas::packable::Packable;
+use chikin_nft_canvas::processor;
+use chikin_nft_canvas::state::{CanvasConfig, CanvasStats, CanvasStatsShard, Pixel};
+
+const INDEX: u32 = 7;
+
+/// Records the invoked instructions, and allocates the data of accounts the system program creates or allocates.
+struct RecordingStubs {
+    invoked: Arc<Mutex<Vec<Instruction>>>,
+}
+
+impl SyscallStubs for RecordingStubs {
+    fn sol_invoke_signed(&self, instruction: &Instruction, account_infos: &[AccountInfo], _signers_seeds: &[&[&[u8]]]) -> ProgramResult {
+        self.invoked.lock().unwrap().push(instruction.clone());
+        let tag = u32::from_le_bytes([instruction.data[0], instruction.data[1], instruction.data[2], instruction.data[3]]);
+        let (account, space_offset) = match tag {
+            // CreateAccount { lamports, space, owner }
+            0 => (&instruction.accounts[1].pubkey, 12),
+            // Allocate { space }
+            8 => (&instruction.accounts[0].pubkey, 4),
+            _ => return Ok(()),
+        };
+        let mut space = [0u8; 8];
+        space.copy_from_slice(&instruction.data[space_offset..space_offset + 8]);
+        let account = account_infos.iter().find(|account_info| account_info.key == account).unwrap();
+        *account.data.borrow_mut() = Box::leak(vec![0; u64::from_le_bytes(space) as usize].into_boxed_slice());
+        Ok(())
+    }
+}
+
+fn rent_data(rent: &Rent) -> Vec<u8> {
+    let mut data = rent.lamports_per_byte_year.to_le_bytes().to_vec();
+    data.extend_from_slice(&rent.exemption_threshold.to_le_bytes());
+    data.push(rent.burn_percent);
+    data
+}
+
+/// Mints the pixel at `INDEX` with the pixel account holding `pixel_lamports` and `pixel_data`, returning the
+/// instructions invoked on the pixel account.
+fn mint(program_id: &Pubkey, pixel_lamports: u64, pixel_data: Vec<u8>) -> (Result<(), ProgramError>, Vec<Instruction>) {
+    let canvas_config = CanvasConfig::new(Pubkey::new_unique(), config::get_trade_pool(program_id).1, config::get_fee_vault(program_id).1);
+    let (result, invoked) = mint_on(program_id, &canvas_config, &CanvasStats::new(), pixel_lamports, pixel_data);
+    let pixel_account = config::get_pixel(program_id, INDEX).0;
+    let invoked = invoked.into_iter()
+        .filter(|invoked| invoked.accounts.iter().any(|meta| meta.pubkey == pixel_account))
+        .collect();
+    (result, invoked)
+}
+
+/// Mints the pixel at `INDEX` on a canvas with `canvas_config` and `canvas_stats`, returning all invoked instructions.
+fn mint_on(
+    program_id: &Pubkey,
+    canvas_config: &CanvasConfig,
+    canvas_stats: &CanvasStats,
+    pixel_lamports: u64,
+    pixel_data: Vec<u8>,
+) -> (Result<(), ProgramError>, Vec<Instruction>) {
+    let owner_wallet = Pubkey::new_unique();
+    let instruction = NftCanvasInstruction::mint_pixel(
+        *program_id,
+        config::get_canvas_config(program_id).0,
+        config::get_canvas_stats(program_id).0,
+        config::get_canvas_stats_shard(program_id, config::stats_shard(INDEX)).0,
+        system_program::id(),
+        sysvar::rent::id(),
+        config::get_fee_vault(program_id).0,
+        config::get_pixel(program_id, INDEX).0,
+        owner_wallet,
+        INDEX,
+        PixelColor::Rgb([1, 2, 3]),
+        0,
+    );
+    let pixel_account_owner = if pixel_data.is_empty() { system_program::id() } else { *program_id };
+    let mut storage: Vec<(u64, Vec<u8>, Pubkey)> = vec![
+        (1, vec![], bpf_loader::id()),
+        (1, canvas_config.pack(), *program_id),
+        (1, canvas_stats.pack(), *program_id),
+        (1, CanvasStatsShard::new(config::stats_shard(INDEX)).pack(), *program_id),
+        (1, vec![], Pubkey::default()),
+        (1, rent_data(&Rent::default()), sysvar::id()),
+        (1, vec![], system_program::id()),
+        (pixel_lamports, pixel_data, pixel_account_owner),
+        (1_000_000_000, vec![], system_program::id()),
+    ];
+    let account_infos: Vec<AccountInfo> = storage.iter_mut().zip(&instruction.accounts)
+        .map(|((lamports, data, owner), meta)| {
+            AccountInfo::new(&meta.pubkey, meta.is_signer, meta.is_writable, lamports, data, owner, false, 0)
+        })
+        .collect();
+
+    let invoked = Arc::new(Mutex::new(Vec::new()));
+    program_stubs::set_syscall_stubs(Box::new(RecordingStubs { invoked: invoked.clone() }));
+    let result = processor::process_instruction(program_id, &account_infos, &instruction.data);
+    let invoked = invoked.lock().unwrap().clone();
+    (result, invoked)
+}
+
+#[test]
+fn test_mint_pixel() {
+    let program_id = Pubkey::new_unique();
+    let pixel_account = config::get_pixel(&program_id, INDEX).0;
+    let owner_wallet = |invoked: &[Instruction]| invoked[0].accounts[0].pubkey;
+    let rent_exempt = Rent::default().minimum_balance(Pixel::PACKED_SIZE);
+
+    // A fresh address
+    let (result, invoked) = mint(&program_id, 0, vec![]);
+    assert_eq!(result, Ok(()));
+    assert_eq!(invoked, vec![
+        system_instruction::create_account(&owner_wallet(&invoked), &pixel_account, rent_exempt, Pixel::PACKED_SIZE as u64, &program_id),
+    ]);
+
+    // Someone sent a few lamports to the address first: topped up, then allocated and assigned
+    let (result, invoked) = mint(&program_id, 1_000, vec![]);
+    assert_eq!(result, Ok(()));
+    assert_eq!(invoked, vec![
+        system_instruction::transfer(&owner_wallet(&invoked), &pixel_account, rent_exempt - 1_000),
+        system_instruction::allocate(&pixel_account, Pixel::PACKED_SIZE as u64),
+        system_instruction::assign(&pixel_account, &program_id),
+    ]);
+
+    // ... or more than the rent
+    let (result, invoked) = mint(&program_id, rent_exempt + 1, vec![]);
+    assert_eq!(result, Ok(()));
+    assert_eq!(invoked, vec![
+        system_instruction::allocate(&pixel_account, Pixel::PACKED_SIZE as u64),
+        system_instruction::assign(&pixel_account, &program_id),
+    ]);
+
+    // Minted already
+    let pixel = Pixel::new(INDEX, [0, 0, 0, 255], Pubkey::new_unique(), 0, config::get_pixel(&program_id, INDEX).1);
+    let (result, invoked) = mint(&program_id, rent_exempt, pixel.pack());
+    assert_eq!(result, Err(NftCanvasError::PixelAlreadyMinted.into()));
+    assert_eq!(invoked, vec![]);
+}
+
+#[test]
+fn test_mint_price_follows_canvas_stats() {
+    let program_id = Pubkey::new_unique();
+    let fee_vault = config::get_fee_vault(&program_id).0;
+    let mut canvas_config = CanvasConfig::new(Pubkey::new_unique(), config::get_trade_pool(&program_id).1, config::get_fee_vault(&program_id).1);
+    canvas_config.mint_base_price = 1_000;
+    canvas_config.mint_price_increment = 10;
+    let mut canvas_stats = CanvasStats::new();
+    for _ in 0..3 {
+        canvas_stats.record_mint(1_000);
+    }
+    let paid = |invoked: &[Instruction]| invoked.iter()
+        .find(|invoked| invoked.accounts.iter().any(|meta| meta.pubkey == fee_vault))
+        .cloned()
+        .unwrap();
+
+    let (result, invoked) = mint_on(&program_id, &canvas_config, &canvas_stats, 0, vec![]);
+    assert_eq!(result, Ok(()));
+    let owner_wallet = invoked[0].accounts[0].pubkey;
+    assert_eq!(paid(&invoked), system_instruction::transfer(&owner_wallet, &fee_vault, 1_030));
+
+    // Closing a pixel lowers the price of the next mint again
+    canvas_stats.record_close();
+    let (result, invoked) = mint_on(&program_id, &canvas_config, &canvas_stats, 0, vec![]);
+    assert_eq!(result, Ok(()));
+    let owner_wallet = invoked[0].accounts[0].pubkey;
+    assert_eq!(paid(&invoked), system_instruction::transfer(&owner_wallet, &fee_vault, 1_020));
+}
@@ -0,0 +1,38 @@
+use solana_program::pubkey::Pubkey;
+
+use chikin_nft_canvas::config;
+use chikin_nft_canvas::packable::Packable;
+use chikin_nft_canvas::state::{quote_mint_price, CanvasConfig, CanvasStats};
+
+#[test]
+fn test_mint_price() {
+    let mut canvas_config = CanvasConfig::new(Pubkey::new_unique(), 255, 255);
+    assert_eq!(canvas_config.mint_price(0), config::MINT_COST);
+    assert_eq!(canvas_config.mint_price(config::PIXEL_COUNT), config::MINT_COST);
+
+    canvas_config.mint_base_price = 1_000;
+    canvas_config.mint_price_increment = 10;
+    assert_eq!(canvas_config.mint_price(0), 1_000);
+    assert_eq!(canvas_config.mint_price(5), 1_050);
+    canvas_config.mint_base_price = u64::MAX - 1;
+    assert_eq!(canvas_config.mint_price(5), u64::MAX);
+
+    canvas_config.presale = true;
+    canvas_config.presale_price = 500;
+    assert_eq!(canvas_config.mint_price(5), 500);
+
+    canvas_config.presale = false;
+    canvas_config.mint_base_price = 1_000;
+    let canvas_config_data = canvas_config.pack();
+    let mut canvas_stats = CanvasStats::new();
+    assert_eq!(quote_mint_price(&canvas_config_data, &canvas_stats.pack()).unwrap(), 1_000);
+    for _ in 0..7 {
+        canvas_stats.record_mint(1_000);
+    }
+    assert_eq!(quote_mint_price(&canvas_config_data, &canvas_stats.pack()).unwrap(), 1_070);
+    assert!(quote_mint_price(&canvas_config_data[1..], &canvas_stats.pack()).is_err());
+
+    // Closed pixels no longer count
+    canvas_stats.record_close();
+    assert_eq!(quote_mint_price(&canvas_config_data, &canvas_stats.pack()).unwrap(), 1_060);
+}
@@ -3,7 +3,6 @@ use solana_program_test::*;
 use solana_sdk::native_token::Sol;
 use solana_sdk::rent::Rent;
 
-use chikin_nft_canvas;
 use chikin_nft_canvas::instruction::NftCanvasInstruction;
 use chikin_nft_canvas::state::Pixel;
 use chikin_nft_canvas::config;
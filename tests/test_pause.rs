@@ -0,0 +1,325 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use solana_program::account_info::AccountInfo;
+use solana_program::bpf_loader;
+use solana_program::clock::Clock;
+use solana_program::entrypoint::{ProgramResult, SUCCESS};
+use solana_program::instruction::Instruction;
+use solana_program::program_option::COption;
+use solana_program::program_pack::Pack;
+use solana_program::program_stubs::{self, SyscallStubs};
+use solana_program::pubkey::Pubkey;
+use solana_program::rent::Rent;
+use solana_program::system_instruction;
+use solana_program::system_program;
+use solana_program::sysvar::{self, Sysvar};
+use spl_token::state::{Account as TokenAccount, AccountState, Mint};
+
+use chikin_nft_canvas::config;
+use chikin_nft_canvas::error::NftCanvasError;
+use chikin_nft_canvas::instruction::NftCanvasInstruction;
+use chikin_nft_canvas::packable::Packable;
+use chikin_nft_canvas::processor;
+use chikin_nft_canvas::color::PixelColor;
+use chikin_nft_canvas::state::{CanvasConfig, CanvasStats, CanvasStatsShard, Pixel, PixelBuyInfo};
+use chikin_nft_canvas::token;
+
+const INDEX: u32 = 19;
+
+/// The syscall stubs are global, so tests setting them take turns.
+static STUBS: Mutex<()> = Mutex::new(());
+
+/// Lamports, data and owner of each account. Accounts missing from it are empty system accounts.
+type Bank = HashMap<Pubkey, (u64, Vec<u8>, Pubkey)>;
+
+/// Records the invoked instructions, without running them.
+struct RecordingStubs {
+    invoked: Arc<Mutex<Vec<Instruction>>>,
+}
+
+impl SyscallStubs for RecordingStubs {
+    fn sol_invoke_signed(&self, instruction: &Instruction, _account_infos: &[AccountInfo], _signers_seeds: &[&[&[u8]]]) -> ProgramResult {
+        self.invoked.lock().unwrap().push(instruction.clone());
+        Ok(())
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Clock) = Clock::default() };
+        SUCCESS
+    }
+}
+
+/// The canvas accounts administered by `admin`, with `pixel` minted and only the token accounts of `token_account_wallets`.
+fn canvas(program_id: &Pubkey, admin: &Pubkey, pixel: &Pixel, token_account_wallets: &[Pubkey]) -> Bank {
+    let mut mint_data = vec![0; Mint::LEN];
+    let mint = Mint { mint_authority: COption::None, supply: 0, decimals: 0, is_initialized: true, freeze_authority: COption::None };
+    Mint::pack(mint, &mut mint_data).unwrap();
+    let canvas_stats = CanvasStats { minted: 1, ..CanvasStats::new() };
+    let mut bank = Bank::new();
+    bank.insert(*program_id, (1, vec![], bpf_loader::id()));
+    bank.insert(config::get_canvas_config(program_id).0, (1, CanvasConfig::new(*admin, config::get_trade_pool(program_id).1, config::get_fee_vault(program_id).1).pack(), *program_id));
+    bank.insert(config::get_canvas_stats(program_id).0, (1, canvas_stats.pack(), *program_id));
+    for shard in 0..config::STATS_SHARDS as u8 {
+        let mut canvas_stats_shard = CanvasStatsShard::new(shard);
+        if shard == config::stats_shard(INDEX) {
+            canvas_stats_shard.add_pixel(pixel);
+        }
+        bank.insert(config::get_canvas_stats_shard(program_id, shard).0, (1, canvas_stats_shard.pack(), *program_id));
+    }
+    bank.insert(spl_token::id(), (1, vec![], bpf_loader::id()));
+    bank.insert(config::token_mint::id(), (1, mint_data, spl_token::id()));
+    let mut rent_data = vec![0; Rent::size_of()];
+    let mut rent_lamports = 1;
+    let (rent_sysvar, owner) = (sysvar::rent::id(), sysvar::id());
+    Rent::default().to_account_info(&mut AccountInfo::new(&rent_sysvar, false, false, &mut rent_lamports, &mut rent_data, &owner, false, 0)).unwrap();
+    bank.insert(rent_sysvar, (1, rent_data, owner));
+    bank.insert(spl_associated_token_account::id(), (1, vec![], bpf_loader::id()));
+    bank.insert(config::get_pixel(program_id, INDEX).0, (1, pixel.pack(), *program_id));
+    for wallet in token_account_wallets {
+        // (The token program is not run)
+        bank.insert(token_account(wallet), (1, token_account_data(wallet), spl_token::id()));
+    }
+    bank
+}
+
+/// Runs `instruction` against `bank`, returning the invoked instructions.
+fn run(program_id: &Pubkey, instruction: &Instruction, bank: &mut Bank) -> (ProgramResult, Vec<Instruction>) {
+    let mut storage: Vec<(u64, Vec<u8>, Pubkey)> = instruction.accounts.iter()
+        .map(|meta| bank.get(&meta.pubkey).cloned().unwrap_or((0, vec![], system_program::id())))
+        .collect();
+    let account_infos: Vec<AccountInfo> = storage.iter_mut().zip(&instruction.accounts)
+        .map(|((lamports, data, owner), meta)| {
+            AccountInfo::new(&meta.pubkey, meta.is_signer, meta.is_writable, lamports, data, owner, false, 0)
+        })
+        .collect();
+
+    let _stubs = STUBS.lock().unwrap();
+    let invoked = Arc::new(Mutex::new(Vec::new()));
+    program_stubs::set_syscall_stubs(Box::new(RecordingStubs { invoked: invoked.clone() }));
+    let result = processor::process_instruction(program_id, &account_infos, &instruction.data);
+    drop(account_infos);
+    if result.is_ok() {
+        for (meta, account) in instruction.accounts.iter().zip(storage) {
+            bank.insert(meta.pubkey, account);
+        }
+    }
+    let invoked = invoked.lock().unwrap().clone();
+    (result, invoked)
+}
+
+fn token_account(wallet: &Pubkey) -> Pubkey {
+    config::get_token_account(wallet, &spl_token::id())
+}
+
+fn canvas_config_in(program_id: &Pubkey, bank: &Bank) -> CanvasConfig {
+    CanvasConfig::unpack(&bank[&config::get_canvas_config(program_id).0].1).unwrap()
+}
+
+fn set_paused(program_id: &Pubkey, admin: &Pubkey, paused: u8, painting_paused: u8) -> Instruction {
+    NftCanvasInstruction::set_paused(*program_id, config::get_canvas_config(program_id).0, *admin, paused, painting_paused)
+}
+
+/// The instructions that stop while the canvas is paused, as `pixel_owner` or `buyer_wallet`.
+fn trades(program_id: &Pubkey, pixel_owner: &Pubkey, buyer_wallet: &Pubkey) -> Vec<Instruction> {
+    let canvas_config = config::get_canvas_config(program_id).0;
+    let canvas_stats_shard = config::get_canvas_stats_shard(program_id, config::stats_shard(INDEX)).0;
+    let pixel_account = config::get_pixel(program_id, INDEX).0;
+    let fee_vault = config::get_fee_vault(program_id).0;
+    let trade_pool = config::get_trade_pool(program_id).0;
+    let mint_pixel = NftCanvasInstruction::mint_pixel(
+        *program_id,
+        canvas_config,
+        config::get_canvas_stats(program_id).0,
+        config::get_canvas_stats_shard(program_id, config::stats_shard(INDEX + 1)).0,
+        system_program::id(),
+        sysvar::rent::id(),
+        fee_vault,
+        config::get_pixel(program_id, INDEX + 1).0,
+        *buyer_wallet,
+        INDEX + 1,
+        PixelColor::Rgb([1, 2, 3]),
+        0,
+    );
+    let list_pixel = NftCanvasInstruction::list_pixel(*program_id, canvas_config, canvas_stats_shard, pixel_account, *pixel_owner, INDEX, 400);
+    let accept_bid = NftCanvasInstruction::accept_bid(
+        *program_id,
+        canvas_config,
+        canvas_stats_shard,
+        spl_token::id(),
+        config::token_mint::id(),
+        system_program::id(),
+        sysvar::rent::id(),
+        spl_associated_token_account::id(),
+        pixel_account,
+        fee_vault,
+        token_account(&fee_vault),
+        trade_pool,
+        token_account(&trade_pool),
+        *pixel_owner,
+        token_account(pixel_owner),
+        INDEX,
+        0,
+    );
+    let buy_pixel = NftCanvasInstruction::buy_pixel(
+        *program_id,
+        canvas_config,
+        canvas_stats_shard,
+        spl_token::id(),
+        config::token_mint::id(),
+        system_program::id(),
+        sysvar::rent::id(),
+        spl_associated_token_account::id(),
+        pixel_account,
+        fee_vault,
+        token_account(&fee_vault),
+        trade_pool,
+        token_account(&trade_pool),
+        *pixel_owner,
+        token_account(pixel_owner),
+        *buyer_wallet,
+        token_account(buyer_wallet),
+        None,
+        INDEX,
+        300,
+        0,
+    );
+    let buy_pixel_sol = NftCanvasInstruction::buy_pixel_sol(
+        *program_id,
+        canvas_config,
+        canvas_stats_shard,
+        system_program::id(),
+        pixel_account,
+        fee_vault,
+        trade_pool,
+        *pixel_owner,
+        *buyer_wallet,
+        None,
+        INDEX,
+        300,
+        0,
+    );
+    vec![mint_pixel, list_pixel, accept_bid, buy_pixel, buy_pixel_sol]
+}
+
+fn paint(program_id: &Pubkey, pixel_owner: &Pubkey) -> Instruction {
+    NftCanvasInstruction::update_pixel_color(
+        *program_id,
+        config::get_canvas_config(program_id).0,
+        config::get_pixel(program_id, INDEX).0,
+        *pixel_owner,
+        INDEX,
+        PixelColor::Rgb([4, 5, 6]),
+    )
+}
+
+#[test]
+fn test_set_paused() {
+    let program_id = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let pixel = Pixel::new(INDEX, [1, 2, 3, 255], Pubkey::new_unique(), 0, config::get_pixel(&program_id, INDEX).1);
+    let mut bank = canvas(&program_id, &admin, &pixel, &[]);
+
+    // Only the admin pauses
+    let stranger = Pubkey::new_unique();
+    assert_eq!(run(&program_id, &set_paused(&program_id, &stranger, 1, 1), &mut bank).0, Err(NftCanvasError::AdminKeyMismatch.into()));
+    let mut unsigned = set_paused(&program_id, &admin, 1, 1);
+    unsigned.accounts[2].is_signer = false;
+    assert_eq!(run(&program_id, &unsigned, &mut bank).0, Err(NftCanvasError::AdminDidNotSign.into()));
+    assert!(!canvas_config_in(&program_id, &bank).paused);
+
+    assert_eq!(run(&program_id, &set_paused(&program_id, &admin, 1, 0), &mut bank).0, Ok(()));
+    assert_eq!((canvas_config_in(&program_id, &bank).paused, canvas_config_in(&program_id, &bank).painting_paused), (true, false));
+    assert_eq!(run(&program_id, &set_paused(&program_id, &admin, 1, 1), &mut bank).0, Ok(()));
+    assert_eq!((canvas_config_in(&program_id, &bank).paused, canvas_config_in(&program_id, &bank).painting_paused), (true, true));
+    assert_eq!(run(&program_id, &set_paused(&program_id, &admin, 0, 0), &mut bank).0, Ok(()));
+    assert_eq!(canvas_config_in(&program_id, &bank), CanvasConfig::new(admin, config::get_trade_pool(&program_id).1, config::get_fee_vault(&program_id).1));
+}
+
+#[test]
+fn test_paused_canvas() {
+    let program_id = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let pixel_owner = Pubkey::new_unique();
+    let buyer_wallet = Pubkey::new_unique();
+    let sol_buyer_wallet = Pubkey::new_unique();
+    let fee_vault = config::get_fee_vault(&program_id).0;
+    let trade_pool = config::get_trade_pool(&program_id).0;
+    let mut pixel = Pixel::new(INDEX, [1, 2, 3, 255], pixel_owner, 300, config::get_pixel(&program_id, INDEX).1);
+    pixel.best_buy_info = Some(PixelBuyInfo { price: 100, buyer_wallet });
+    pixel.best_sol_buy_info = Some(PixelBuyInfo { price: 200, buyer_wallet: sol_buyer_wallet });
+    let token_account_wallets = [pixel_owner, buyer_wallet, fee_vault, trade_pool];
+    let mut bank = canvas(&program_id, &admin, &pixel, &token_account_wallets);
+    assert_eq!(run(&program_id, &set_paused(&program_id, &admin, 1, 0), &mut bank).0, Ok(()));
+
+    // Minting, listing, bidding, buying and selling stop, before anything moves
+    for instruction in trades(&program_id, &pixel_owner, &buyer_wallet) {
+        let (result, invoked) = run(&program_id, &instruction, &mut bank);
+        assert_eq!(result, Err(NftCanvasError::Paused.into()));
+        assert!(invoked.is_empty());
+    }
+
+    // Painting stops separately
+    assert_eq!(run(&program_id, &paint(&program_id, &pixel_owner), &mut bank).0, Ok(()));
+    assert_eq!(run(&program_id, &set_paused(&program_id, &admin, 1, 1), &mut bank).0, Ok(()));
+    assert_eq!(run(&program_id, &paint(&program_id, &pixel_owner), &mut bank).0, Err(NftCanvasError::PaintingPaused.into()));
+
+    // Bidders still get their escrow back
+    let cancel_bid = NftCanvasInstruction::cancel_bid(
+        program_id,
+        config::get_canvas_config(&program_id).0,
+        config::get_canvas_stats_shard(&program_id, config::stats_shard(INDEX)).0,
+        spl_token::id(),
+        config::token_mint::id(),
+        system_program::id(),
+        sysvar::rent::id(),
+        spl_associated_token_account::id(),
+        config::get_pixel(&program_id, INDEX).0,
+        trade_pool,
+        token_account(&trade_pool),
+        buyer_wallet,
+        token_account(&buyer_wallet),
+        INDEX,
+    );
+    let (result, invoked) = run(&program_id, &cancel_bid, &mut bank);
+    assert_eq!(result, Ok(()));
+    assert_eq!(invoked, vec![
+        token::transfer_checked(&spl_token::id(), &token_account(&trade_pool), &config::token_mint::id(), &token_account(&buyer_wallet), &trade_pool, 100, 0).unwrap(),
+    ]);
+
+    // And owners can still leave, refunding the remaining bid
+    let close_pixel = NftCanvasInstruction::close_pixel(
+        program_id,
+        config::get_canvas_config(&program_id).0,
+        config::get_canvas_stats(&program_id).0,
+        config::get_canvas_stats_shard(&program_id, config::stats_shard(INDEX)).0,
+        system_program::id(),
+        spl_token::id(),
+        config::token_mint::id(),
+        sysvar::rent::id(),
+        spl_associated_token_account::id(),
+        config::get_pixel(&program_id, INDEX).0,
+        trade_pool,
+        token_account(&trade_pool),
+        pixel_owner,
+        None,
+        Some(sol_buyer_wallet),
+        INDEX,
+    );
+    let (result, invoked) = run(&program_id, &close_pixel, &mut bank);
+    assert_eq!(result, Ok(()));
+    assert_eq!(invoked, vec![system_instruction::transfer(&trade_pool, &sol_buyer_wallet, 200)]);
+
+    // (Unpaused, the same instructions go through)
+    let mut bank = canvas(&program_id, &admin, &pixel, &token_account_wallets);
+    let list_pixel = trades(&program_id, &pixel_owner, &buyer_wallet).swap_remove(1);
+    assert_eq!(run(&program_id, &list_pixel, &mut bank).0, Ok(()));
+}
+
+/// The data of an initialized token account of `wallet`.
+fn token_account_data(wallet: &Pubkey) -> Vec<u8> {
+    let mut data = vec![0; TokenAccount::LEN];
+    let token_account = TokenAccount { mint: config::token_mint::id(), owner: *wallet, state: AccountState::Initialized, ..TokenAccount::default() };
+    TokenAccount::pack(token_account, &mut data).unwrap();
+    data
+}
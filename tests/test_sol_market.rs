@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use solana_program::account_info::AccountInfo;
+use solana_program::bpf_loader;
+use solana_program::clock::Clock;
+use solana_program::entrypoint::{ProgramResult, SUCCESS};
+use solana_program::instruction::Instruction;
+use solana_program::program_stubs::{self, SyscallStubs};
+use solana_program::pubkey::Pubkey;
+use solana_program::rent::Rent;
+use solana_program::system_instruction;
+use solana_program::system_program;
+use solana_program::sysvar::{self, Sysvar};
+
+use chikin_nft_canvas::config;
+use chikin_nft_canvas::error::NftCanvasError;
+use chikin_nft_canvas::instruction::NftCanvasInstruction;
+use chikin_nft_canvas::packable::Packable;
+use chikin_nft_canvas::processor;
+use chikin_nft_canvas::state::{CanvasConfig, CanvasStats, CanvasStatsShard, Pixel, PixelBuyInfo};
+
+const INDEX: u32 = 11;
+
+/// The syscall stubs are global, so tests setting them take turns.
+static STUBS: Mutex<()> = Mutex::new(());
+
+/// Lamports, data and owner of each account. Accounts missing from it are empty system accounts.
+type Bank = HashMap<Pubkey, (u64, Vec<u8>, Pubkey)>;
+
+/// Records the invoked instructions, without running them.
+struct RecordingStubs {
+    invoked: Arc<Mutex<Vec<Instruction>>>,
+}
+
+impl SyscallStubs for RecordingStubs {
+    fn sol_invoke_signed(&self, instruction: &Instruction, _account_infos: &[AccountInfo], _signers_seeds: &[&[&[u8]]]) -> ProgramResult {
+        self.invoked.lock().unwrap().push(instruction.clone());
+        Ok(())
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Clock) = Clock::default() };
+        SUCCESS
+    }
+}
+
+/// The canvas accounts, with `pixel` minted and counted in the stats.
+fn canvas(program_id: &Pubkey, pixel: &Pixel) -> Bank {
+    let canvas_stats = CanvasStats { minted: 1, ..CanvasStats::new() };
+    let mut canvas_stats_shard = CanvasStatsShard::new(config::stats_shard(INDEX));
+    canvas_stats_shard.add_pixel(pixel);
+    let mut bank = Bank::new();
+    bank.insert(*program_id, (1, vec![], bpf_loader::id()));
+    bank.insert(config::get_canvas_config(program_id).0, (1, CanvasConfig::new(Pubkey::new_unique(), config::get_trade_pool(program_id).1, config::get_fee_vault(program_id).1).pack(), *program_id));
+    bank.insert(config::get_canvas_stats(program_id).0, (1, canvas_stats.pack(), *program_id));
+    bank.insert(config::get_canvas_stats_shard(program_id, config::stats_shard(INDEX)).0, (1, canvas_stats_shard.pack(), *program_id));
+    bank.insert(config::get_pixel(program_id, INDEX).0, (1, pixel.pack(), *program_id));
+    bank
+}
+
+/// Runs `instruction` against `bank`, returning the invoked instructions.
+fn run(program_id: &Pubkey, instruction: &Instruction, bank: &mut Bank) -> (ProgramResult, Vec<Instruction>) {
+    let mut storage: Vec<(u64, Vec<u8>, Pubkey)> = instruction.accounts.iter()
+        .map(|meta| bank.get(&meta.pubkey).cloned().unwrap_or((0, vec![], system_program::id())))
+        .collect();
+    let account_infos: Vec<AccountInfo> = storage.iter_mut().zip(&instruction.accounts)
+        .map(|((lamports, data, owner), meta)| {
+            AccountInfo::new(&meta.pubkey, meta.is_signer, meta.is_writable, lamports, data, owner, false, 0)
+        })
+        .collect();
+
+    let _stubs = STUBS.lock().unwrap();
+    let invoked = Arc::new(Mutex::new(Vec::new()));
+    program_stubs::set_syscall_stubs(Box::new(RecordingStubs { invoked: invoked.clone() }));
+    let result = processor::process_instruction(program_id, &account_infos, &instruction.data);
+    drop(account_infos);
+    if result.is_ok() {
+        for (meta, account) in instruction.accounts.iter().zip(storage) {
+            bank.insert(meta.pubkey, account);
+        }
+    }
+    let invoked = invoked.lock().unwrap().clone();
+    (result, invoked)
+}
+
+fn pixel_in(program_id: &Pubkey, bank: &Bank) -> Pixel {
+    Pixel::unpack(&bank[&config::get_pixel(program_id, INDEX).0].1).unwrap()
+}
+
+fn canvas_stats_shard_in(program_id: &Pubkey, bank: &Bank) -> CanvasStatsShard {
+    CanvasStatsShard::unpack(&bank[&config::get_canvas_stats_shard(program_id, config::stats_shard(INDEX)).0].1).unwrap()
+}
+
+fn buy_pixel_sol(program_id: &Pubkey, pixel: &Pixel, buyer_wallet: &Pubkey, price: u64, direct_only: u8) -> Instruction {
+    NftCanvasInstruction::buy_pixel_sol(
+        *program_id,
+        config::get_canvas_config(program_id).0,
+        config::get_canvas_stats_shard(program_id, config::stats_shard(INDEX)).0,
+        system_program::id(),
+        config::get_pixel(program_id, INDEX).0,
+        config::get_fee_vault(program_id).0,
+        config::get_trade_pool(program_id).0,
+        pixel.owner_wallet,
+        *buyer_wallet,
+        pixel.best_sol_buy_info.as_ref().map(|buy_info| buy_info.buyer_wallet),
+        INDEX,
+        price,
+        direct_only,
+    )
+}
+
+#[test]
+fn test_initialize_config_funds_reserves() {
+    let program_id = Pubkey::new_unique();
+    let admin_wallet = config::admin_wallet::id();
+    let fee_vault = config::get_fee_vault(&program_id).0;
+    let trade_pool = config::get_trade_pool(&program_id).0;
+    let instruction = NftCanvasInstruction::initialize_config(
+        program_id,
+        system_program::id(),
+        sysvar::rent::id(),
+        config::get_canvas_config(&program_id).0,
+        config::get_canvas_stats(&program_id).0,
+        fee_vault,
+        trade_pool,
+        admin_wallet,
+    );
+    let rent = Rent::default();
+    let mut rent_data = vec![0; Rent::size_of()];
+    let mut rent_lamports = 1;
+    let rent_sysvar = sysvar::rent::id();
+    let owner = sysvar::id();
+    rent.to_account_info(&mut AccountInfo::new(&rent_sysvar, false, false, &mut rent_lamports, &mut rent_data, &owner, false, 0)).unwrap();
+    let mut bank = Bank::new();
+    bank.insert(program_id, (1, vec![], bpf_loader::id()));
+    bank.insert(rent_sysvar, (1, rent_data, owner));
+    // (The system program is not run, so the new accounts come allocated)
+    bank.insert(config::get_canvas_config(&program_id).0, (1, vec![0; CanvasConfig::PACKED_SIZE], system_program::id()));
+    bank.insert(config::get_canvas_stats(&program_id).0, (1, vec![0; CanvasStats::PACKED_SIZE], system_program::id()));
+    // (Someone already sent lamports to the trade pool)
+    bank.insert(trade_pool, (100, vec![], system_program::id()));
+
+    let (result, invoked) = run(&program_id, &instruction, &mut bank);
+    assert_eq!(result, Ok(()));
+    let reserve = rent.minimum_balance(0);
+    assert_eq!(invoked[invoked.len() - 2..], [
+        system_instruction::transfer(&admin_wallet, &fee_vault, reserve),
+        system_instruction::transfer(&admin_wallet, &trade_pool, reserve - 100),
+    ]);
+}
+
+#[test]
+fn test_sol_bids_escrow() {
+    let program_id = Pubkey::new_unique();
+    let pixel_owner = Pubkey::new_unique();
+    let buyer_wallet = Pubkey::new_unique();
+    let other_buyer_wallet = Pubkey::new_unique();
+    let trade_pool = config::get_trade_pool(&program_id).0;
+    let pixel = Pixel::new(INDEX, [1, 2, 3, 255], pixel_owner, 0, config::get_pixel(&program_id, INDEX).1);
+    let mut bank = canvas(&program_id, &pixel);
+
+    // Unlisted, so only a bid is possible
+    let (result, _) = run(&program_id, &buy_pixel_sol(&program_id, &pixel, &buyer_wallet, 100, 1), &mut bank);
+    assert_eq!(result, Err(NftCanvasError::CouldNotDirectBuy.into()));
+
+    // The bid goes into the trade pool
+    let (result, invoked) = run(&program_id, &buy_pixel_sol(&program_id, &pixel, &buyer_wallet, 100, 0), &mut bank);
+    assert_eq!(result, Ok(()));
+    assert_eq!(invoked, vec![system_instruction::transfer(&buyer_wallet, &trade_pool, 100)]);
+    let pixel = pixel_in(&program_id, &bank);
+    assert_eq!(pixel.best_sol_buy_info, Some(PixelBuyInfo { price: 100, buyer_wallet }));
+
+    // Outbidding takes more, and refunds the previous bid
+    let (result, _) = run(&program_id, &buy_pixel_sol(&program_id, &pixel, &other_buyer_wallet, 100, 0), &mut bank);
+    assert_eq!(result, Err(NftCanvasError::BuyPriceTooLow.into()));
+    let mut readonly_refund = buy_pixel_sol(&program_id, &pixel, &other_buyer_wallet, 150, 0);
+    readonly_refund.accounts.last_mut().unwrap().is_writable = false;
+    let (result, invoked) = run(&program_id, &readonly_refund, &mut bank);
+    assert_eq!(result, Err(NftCanvasError::AccountNotWritable.into()));
+    assert!(invoked.is_empty());
+    let (result, invoked) = run(&program_id, &buy_pixel_sol(&program_id, &pixel, &other_buyer_wallet, 150, 0), &mut bank);
+    assert_eq!(result, Ok(()));
+    assert_eq!(invoked, vec![
+        system_instruction::transfer(&trade_pool, &buyer_wallet, 100),
+        system_instruction::transfer(&other_buyer_wallet, &trade_pool, 150),
+    ]);
+    let pixel = pixel_in(&program_id, &bank);
+    assert_eq!(pixel.best_sol_buy_info, Some(PixelBuyInfo { price: 150, buyer_wallet: other_buyer_wallet }));
+    assert_eq!(canvas_stats_shard_in(&program_id, &bank).open_sol_bids, 1);
+
+    // Cancelling refunds it
+    let instruction = NftCanvasInstruction::cancel_sol_bid(
+        program_id,
+        config::get_canvas_config(&program_id).0,
+        config::get_canvas_stats_shard(&program_id, config::stats_shard(INDEX)).0,
+        system_program::id(),
+        config::get_pixel(&program_id, INDEX).0,
+        trade_pool,
+        other_buyer_wallet,
+        INDEX,
+    );
+    let (result, invoked) = run(&program_id, &instruction, &mut bank);
+    assert_eq!(result, Ok(()));
+    assert_eq!(invoked, vec![system_instruction::transfer(&trade_pool, &other_buyer_wallet, 150)]);
+    assert_eq!(pixel_in(&program_id, &bank).best_sol_buy_info, None);
+    assert_eq!(canvas_stats_shard_in(&program_id, &bank).open_sol_bids, 0);
+}
+
+#[test]
+fn test_sol_trades_split_fees() {
+    let program_id = Pubkey::new_unique();
+    let pixel_owner = Pubkey::new_unique();
+    let buyer_wallet = Pubkey::new_unique();
+    let fee_vault = config::get_fee_vault(&program_id).0;
+    let trade_pool = config::get_trade_pool(&program_id).0;
+
+    // A direct buy pays the listing, 1% of it to the fee vault, and refunds the buyer's own bid
+    let mut pixel = Pixel::new(INDEX, [1, 2, 3, 255], pixel_owner, 0, config::get_pixel(&program_id, INDEX).1);
+    pixel.sol_sell_price = 1_000;
+    pixel.best_sol_buy_info = Some(PixelBuyInfo { price: 200, buyer_wallet });
+    let mut bank = canvas(&program_id, &pixel);
+    let (result, invoked) = run(&program_id, &buy_pixel_sol(&program_id, &pixel, &buyer_wallet, 1_500, 1), &mut bank);
+    assert_eq!(result, Ok(()));
+    assert_eq!(invoked, vec![
+        system_instruction::transfer(&buyer_wallet, &pixel_owner, 990),
+        system_instruction::transfer(&buyer_wallet, &fee_vault, 10),
+        system_instruction::transfer(&trade_pool, &buyer_wallet, 200),
+    ]);
+    let pixel = pixel_in(&program_id, &bank);
+    assert_eq!((pixel.owner_wallet, pixel.sol_sell_price, pixel.best_sol_buy_info), (buyer_wallet, 0, None));
+
+    // Accepting a bid pays it out of the trade pool, split the same way
+    let mut pixel = Pixel::new(INDEX, [1, 2, 3, 255], pixel_owner, 0, config::get_pixel(&program_id, INDEX).1);
+    pixel.best_sol_buy_info = Some(PixelBuyInfo { price: 2_000, buyer_wallet });
+    let mut bank = canvas(&program_id, &pixel);
+    let accept_sol_bid = |min_price| NftCanvasInstruction::accept_sol_bid(
+        program_id,
+        config::get_canvas_config(&program_id).0,
+        config::get_canvas_stats_shard(&program_id, config::stats_shard(INDEX)).0,
+        system_program::id(),
+        config::get_pixel(&program_id, INDEX).0,
+        fee_vault,
+        trade_pool,
+        pixel_owner,
+        INDEX,
+        min_price,
+    );
+    let (result, _) = run(&program_id, &accept_sol_bid(2_001), &mut bank);
+    assert_eq!(result, Err(NftCanvasError::NoAcceptableBid.into()));
+    let (result, invoked) = run(&program_id, &accept_sol_bid(2_000), &mut bank);
+    assert_eq!(result, Ok(()));
+    assert_eq!(invoked, vec![
+        system_instruction::transfer(&trade_pool, &pixel_owner, 1_980),
+        system_instruction::transfer(&trade_pool, &fee_vault, 20),
+    ]);
+    let pixel = pixel_in(&program_id, &bank);
+    assert_eq!((pixel.owner_wallet, pixel.best_sol_buy_info), (buyer_wallet, None));
+    let canvas_stats_shard = canvas_stats_shard_in(&program_id, &bank);
+    assert_eq!((canvas_stats_shard.sol_volume, canvas_stats_shard.sol_fees, canvas_stats_shard.open_sol_bids), (2_000, 20, 0));
+}
+
+#[test]
+fn test_fee_vault_checked_from_config() {
+    let program_id = Pubkey::new_unique();
+    let pixel_owner = Pubkey::new_unique();
+    let buyer_wallet = Pubkey::new_unique();
+    let mut pixel = Pixel::new(INDEX, [1, 2, 3, 255], pixel_owner, 0, config::get_pixel(&program_id, INDEX).1);
+    pixel.sol_sell_price = 1_000;
+
+    // The fees go to the fee vault of the bump seed in the config
+    let mut bank = canvas(&program_id, &pixel);
+    let mut wrong_fee_vault = buy_pixel_sol(&program_id, &pixel, &buyer_wallet, 1_000, 1);
+    wrong_fee_vault.accounts[5].pubkey = Pubkey::new_unique();
+    let (result, invoked) = run(&program_id, &wrong_fee_vault, &mut bank);
+    assert_eq!(result, Err(NftCanvasError::FeeVaultKeyMismatch.into()));
+    assert!(invoked.is_empty());
+
+    // Even the fee vault address is rejected with any other bump seed in the config
+    let fee_vault_bump_seed = config::get_fee_vault(&program_id).1;
+    let canvas_config = CanvasConfig {
+        fee_vault_bump_seed: fee_vault_bump_seed.wrapping_sub(1),
+        ..CanvasConfig::new(Pubkey::new_unique(), config::get_trade_pool(&program_id).1, fee_vault_bump_seed)
+    };
+    bank.insert(config::get_canvas_config(&program_id).0, (1, canvas_config.pack(), program_id));
+    let (result, invoked) = run(&program_id, &buy_pixel_sol(&program_id, &pixel, &buyer_wallet, 1_000, 1), &mut bank);
+    assert_eq!(result, Err(NftCanvasError::FeeVaultKeyMismatch.into()));
+    assert!(invoked.is_empty());
+}
@@ -0,0 +1,53 @@
+use chikin_nft_canvas::config::TradeAmountSplit;
+use chikin_nft_canvas::token::{MintInfo, TransferFee};
+
+fn token_2022_mint(decimals: u8, older: (u64, u64, u16), newer: (u64, u64, u16)) -> Vec<u8> {
+    let mut data = vec![0u8; 166];
+    data[44] = decimals;
+    data[45] = 1;
+    data[165] = 1;
+    data.extend_from_slice(&1u16.to_le_bytes());
+    data.extend_from_slice(&108u16.to_le_bytes());
+    data.extend_from_slice(&[0u8; 72]);
+    for (epoch, maximum_fee, basis_points) in [older, newer].iter() {
+        data.extend_from_slice(&epoch.to_le_bytes());
+        data.extend_from_slice(&maximum_fee.to_le_bytes());
+        data.extend_from_slice(&basis_points.to_le_bytes());
+    }
+    data
+}
+
+#[test]
+fn test_transfer_fee() {
+    let transfer_fee = TransferFee { basis_points: 50, maximum_fee: 1_000 };
+    assert_eq!(transfer_fee.calculate(0), 0);
+    assert_eq!(transfer_fee.calculate(1), 1);
+    assert_eq!(transfer_fee.calculate(10_000), 50);
+    assert_eq!(transfer_fee.calculate(10_000_000), 1_000);
+    for net_amount in [1u64, 99, 10_000, 10_000_000].iter() {
+        let amount = transfer_fee.gross_up(*net_amount);
+        assert!(amount - transfer_fee.calculate(amount) >= *net_amount);
+        assert!(amount - 1 - transfer_fee.calculate(amount - 1) < *net_amount);
+    }
+
+    let split = TradeAmountSplit::split_with_transfer_fee(1_000_000, &transfer_fee);
+    assert_eq!(split.to_seller + split.to_team, 1_000_000);
+    assert!(split.to_team - transfer_fee.calculate(split.to_team) >= 1_000_000 / chikin_nft_canvas::config::TAX_DIV);
+    let split = TradeAmountSplit::split_with_transfer_fee(1_000_000, &TransferFee::default());
+    assert_eq!(split.to_team, 10_000);
+}
+
+#[test]
+fn test_mint_info() {
+    let mut spl_token_mint = vec![0u8; 82];
+    spl_token_mint[44] = 6;
+    spl_token_mint[45] = 1;
+    assert_eq!(MintInfo::unpack(&spl_token_mint, 0).unwrap(), MintInfo { decimals: 6, transfer_fee: TransferFee::default() });
+
+    let token_2022_mint = token_2022_mint(9, (0, 10, 100), (5, 20, 200));
+    assert_eq!(MintInfo::unpack(&token_2022_mint, 4).unwrap().transfer_fee, TransferFee { basis_points: 100, maximum_fee: 10 });
+    assert_eq!(MintInfo::unpack(&token_2022_mint, 5).unwrap().transfer_fee, TransferFee { basis_points: 200, maximum_fee: 20 });
+    assert_eq!(MintInfo::unpack(&token_2022_mint, 5).unwrap().decimals, 9);
+
+    assert!(MintInfo::unpack(&spl_token_mint[..81], 0).is_err());
+}
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use std::process;
+use std::str::FromStr;
+
+use chikin_nft_canvas::config;
+use chikin_nft_canvas::packable::Packable;
+use chikin_nft_canvas::state::{CanvasConfig, CanvasStats, Pixel};
+use chikin_nft_canvas_tools::plan;
+use chikin_nft_canvas_tools::plan::{PlanContext, Rect, RgbaImage};
+use chikin_nft_canvas_tools::rpc::RpcClient;
+use solana_program::native_token::lamports_to_sol;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+use solana_sdk::transaction::Transaction;
+
+const USAGE: &str = "\
+Usage: canvas-plan <IMAGE_PNG> <x,y> [OPTIONS]
+
+Plans painting the image with its top left corner at x,y: unminted pixels are minted, pixels the
+wallet owns or is the color delegate of are painted. Prints the plan and its cost, and sends it
+with --send.
+
+Options:
+    --size <WIDTHxHEIGHT>   Scale the image to this many pixels (default the image size)
+    --canvas <INPUT>        Diff against a canvas snapshot or account dump instead of the cluster
+    --wallet <WALLET>       Plan for this wallet (default the keypair wallet)
+    --send                  Send the transactions
+    --url <URL>             JSON RPC URL (default http://127.0.0.1:8899)
+    --keypair <PATH>        Signer keypair (default ~/.config/solana/id.json)
+    --program-id <ID>       Canvas program id (default $NFTCANVAS_PROGRAM_ID)";
+
+const DEFAULT_URL: &str = "http://127.0.0.1:8899";
+
+fn main() {
+    if let Err(err) = run(env::args().skip(1).collect()) {
+        eprintln!("error: {}", err);
+        eprintln!();
+        eprintln!("{}", USAGE);
+        process::exit(1);
+    }
+}
+
+fn run(args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let mut positional = Vec::new();
+    let mut size = None;
+    let mut canvas = None;
+    let mut wallet = None;
+    let mut send = false;
+    let mut url = DEFAULT_URL.to_string();
+    let mut keypair = None;
+    let mut program_id = None;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("missing value for {}", arg));
+        match arg.as_str() {
+            "--size" => size = Some(plan::parse_size(&value()?)?),
+            "--canvas" => canvas = Some(value()?),
+            "--wallet" => wallet = Some(Pubkey::from_str(&value()?)?),
+            "--send" => send = true,
+            "--url" => url = value()?,
+            "--keypair" => keypair = Some(value()?),
+            "--program-id" => program_id = Some(Pubkey::from_str(&value()?)?),
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg).into()),
+            _ => positional.push(arg),
+        }
+    }
+    let (image_path, at) = match positional.as_slice() {
+        [image_path, at] => (image_path, at),
+        _ => return Err("expected IMAGE_PNG and x,y".into()),
+    };
+    let program_id = match program_id {
+        Some(program_id) => program_id,
+        None => Pubkey::from_str(&env::var("NFTCANVAS_PROGRAM_ID")
+            .map_err(|_| "no --program-id given and NFTCANVAS_PROGRAM_ID is not set")?)?,
+    };
+    let signer = if send || wallet.is_none() {
+        Some(chikin_nft_canvas_tools::read_keypair(keypair.as_deref())?)
+    } else {
+        None
+    };
+    let wallet = match (&signer, wallet) {
+        (Some(signer), Some(wallet)) if signer.pubkey() != wallet => {
+            return Err("--wallet must be the keypair wallet to --send".into());
+        }
+        (_, Some(wallet)) => wallet,
+        (Some(signer), None) => signer.pubkey(),
+        (None, None) => unreachable!(),
+    };
+
+    let image = RgbaImage::read_png(BufReader::new(File::open(image_path)?))?;
+    let (width, height) = size.unwrap_or((image.width, image.height));
+    let (x, y) = chikin_nft_canvas_tools::coordinates(chikin_nft_canvas_tools::parse_coordinates(at)?);
+    let rect = Rect { x, y, width, height };
+    rect.check_on_canvas()?;
+    let cells = image.quantize(width, height);
+
+    let rpc = RpcClient::new(&url);
+    let current: HashMap<u32, Pixel> = match canvas {
+        Some(canvas) => chikin_nft_canvas_tools::read_pixels_file(canvas, Some(&program_id))?.into_iter()
+            .filter(|pixel| pixel.index < config::PIXEL_COUNT)
+            .map(|pixel| (pixel.index, pixel))
+            .collect(),
+        None => {
+            let pixel_accounts: Vec<Pubkey> = rect.indexes().map(|index| config::get_pixel(&program_id, index).0).collect();
+            let mut current = HashMap::new();
+            for account in rpc.get_multiple_accounts(&pixel_accounts)?.into_iter().flatten() {
+                let pixel = Pixel::unpack_account(&program_id, &account.data)?;
+                current.insert(pixel.index, pixel);
+            }
+            current
+        }
+    };
+    let canvas_config = rpc.get_account(&config::get_canvas_config(&program_id).0)?.ok_or("canvas config not found")?;
+    let canvas_config = CanvasConfig::unpack(&canvas_config.data)?;
+    let diff = plan::diff(&cells, &rect, &current, &wallet, &canvas_config);
+
+    let canvas_stats = rpc.get_account(&config::get_canvas_stats(&program_id).0)?.ok_or("canvas stats not found")?;
+    let minted = CanvasStats::unpack(&canvas_stats.data)?.minted;
+    let context = PlanContext {
+        program_id,
+        wallet,
+        canvas_config,
+        minted,
+        pixel_rent: rpc.get_minimum_balance_for_rent_exemption(Pixel::PACKED_SIZE)?,
+        lamports_per_signature: rpc.get_lamports_per_signature()?,
+    };
+    let plan = plan::plan(&diff.changes, &context)?;
+
+    println!("{}x{} pixels at {},{} for {}", width, height, x, y, wallet);
+    println!("mint: {}", plan.mints);
+    println!("paint: {}", plan.paints);
+    println!("unchanged: {}", diff.unchanged);
+    println!("transparent: {}", diff.transparent);
+    println!("blocked: {} (owned by other wallets)", diff.blocked.len());
+    println!("transactions: {}", plan.transactions.len());
+    println!("mint cost: {} SOL", lamports_to_sol(plan.cost.mint));
+    println!("rent: {} SOL", lamports_to_sol(plan.cost.rent));
+    println!("signature fees: {} SOL", lamports_to_sol(plan.cost.signature_fees));
+    println!("total: {} SOL", lamports_to_sol(plan.cost.total()));
+
+    if let Some(signer) = signer.filter(|_| send) {
+        for (number, instructions) in plan.transactions.iter().enumerate() {
+            let blockhash = rpc.get_latest_blockhash()?;
+            let transaction = Transaction::new_signed_with_payer(
+                instructions, Some(&signer.pubkey()), &[&signer], blockhash);
+            let signature = rpc.send_and_confirm_transaction(&transaction)?;
+            println!("Confirmed {}/{} {}", number + 1, plan.transactions.len(), signature);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use std::error::Error;
+use std::fs;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::process;
+use std::str::FromStr;
+
+use chikin_nft_canvas_tools::dump;
+use chikin_nft_canvas_tools::snapshot::Snapshot;
+use solana_program::pubkey::Pubkey;
+
+const USAGE: &str = "\
+Usage:
+    canvas-snapshot export <ACCOUNTS_JSON> <SNAPSHOT> [--program-id <ID>] [--slot <SLOT>]
+    canvas-snapshot info <SNAPSHOT>
+
+ACCOUNTS_JSON is a getProgramAccounts response with base64 account data. The program id and slot
+are read from it when it has account owners and was requested with withContext.";
+
+fn main() {
+    if let Err(err) = run(std::env::args().skip(1).collect()) {
+        eprintln!("error: {}", err);
+        eprintln!();
+        eprintln!("{}", USAGE);
+        process::exit(1);
+    }
+}
+
+fn run(args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let mut positional = Vec::new();
+    let mut program_id = None;
+    let mut slot = None;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("missing value for {}", arg));
+        match arg.as_str() {
+            "--program-id" => program_id = Some(Pubkey::from_str(&value()?)?),
+            "--slot" => slot = Some(value()?.parse()?),
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg).into()),
+            _ => positional.push(arg),
+        }
+    }
+
+    match positional.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["export", input, output] => {
+            let dump = dump::parse_dump(&fs::read_to_string(input)?)?;
+            let snapshot = Snapshot::from_dump(&dump, program_id, slot)?;
+            snapshot.write(BufWriter::new(File::create(output)?))?;
+            println!("Exported {} pixels at slot {} to {}", snapshot.pixels.len(), snapshot.slot, output);
+        }
+        ["info", input] => {
+            let snapshot = Snapshot::read(BufReader::new(File::open(input)?))?;
+            println!("program_id={}", snapshot.program_id);
+            println!("slot={}", snapshot.slot);
+            println!("pixels={}", snapshot.pixels.len());
+        }
+        _ => return Err("expected a command".into()),
+    }
+    Ok(())
+}
@@ -0,0 +1,376 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::process;
+use std::str::FromStr;
+
+use chikin_nft_canvas::allowlist::AllowlistTree;
+use chikin_nft_canvas::color::PixelColor;
+use chikin_nft_canvas::config;
+use chikin_nft_canvas::instruction::NftCanvasInstruction;
+use chikin_nft_canvas::packable::Packable;
+use chikin_nft_canvas::state::{CanvasConfig, CanvasStatsShard, LegacyPixel, Pixel, PixelBuyInfo};
+use chikin_nft_canvas_tools::render;
+use chikin_nft_canvas_tools::rpc::RpcClient;
+use serde_json::json;
+use solana_program::instruction::Instruction;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+use solana_program::sysvar;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+
+const USAGE: &str = "\
+Usage: nftcanvas [OPTIONS] <COMMAND>
+
+Commands:
+    mint <x,y> <COLOR> [--price <PRICE>] [--allowlist <FILE>]
+    paint <x,y> <COLOR>
+    list <x,y> <PRICE> [--sol]
+    unlist <x,y> [--sol]
+    buy <x,y> <MAX_PRICE> [--sol]       Buys at the listed price, if at most MAX_PRICE
+    bid <x,y> <PRICE> [--sol]           Buys instead if the listed price is at most PRICE
+    show <x,y>
+    owned-by <WALLET>
+    init-stats                          Creates the missing stats accounts, counting the pixels on the canvas
+
+COLOR is RRGGBB, RRGGBBAA on canvases in RGBA mode, or pINDEX on canvases in palette mode.
+Prices are in token base units, or in lamports with --sol. The allowlist file, needed to mint
+during the presale, lists one wallet per line. init-stats is signed by the admin.
+
+Options:
+    --url <URL>             JSON RPC URL (default http://127.0.0.1:8899)
+    --keypair <PATH>        Signer keypair (default ~/.config/solana/id.json)
+    --program-id <ID>       Canvas program id (default $NFTCANVAS_PROGRAM_ID)";
+
+const DEFAULT_URL: &str = "http://127.0.0.1:8899";
+// (Offset of `Pixel::owner_wallet`, after `index` and `color`)
+const PIXEL_OWNER_WALLET_OFFSET: usize = 8;
+// (Same for `LegacyPixel`, whose color is RGB)
+const LEGACY_PIXEL_OWNER_WALLET_OFFSET: usize = 7;
+
+struct Options {
+    url: String,
+    keypair: Option<String>,
+    program_id: Option<Pubkey>,
+    price: u64,
+    allowlist: Option<String>,
+    sol: bool,
+}
+
+fn main() {
+    if let Err(err) = run(env::args().skip(1).collect()) {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    }
+}
+
+fn run(args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let mut positional = Vec::new();
+    let mut options = Options {
+        url: DEFAULT_URL.to_string(),
+        keypair: None,
+        program_id: None,
+        price: 0,
+        allowlist: None,
+        sol: false,
+    };
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("missing value for {}", arg));
+        match arg.as_str() {
+            "--url" => options.url = value()?,
+            "--keypair" => options.keypair = Some(value()?),
+            "--program-id" => options.program_id = Some(Pubkey::from_str(&value()?)?),
+            "--price" => options.price = value()?.parse()?,
+            "--allowlist" => options.allowlist = Some(value()?),
+            "--sol" => options.sol = true,
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}\n\n{}", arg, USAGE).into()),
+            _ => positional.push(arg),
+        }
+    }
+
+    let cli = Cli {
+        rpc: RpcClient::new(&options.url),
+        program_id: match options.program_id {
+            Some(program_id) => program_id,
+            None => Pubkey::from_str(&env::var("NFTCANVAS_PROGRAM_ID")
+                .map_err(|_| "no --program-id given and NFTCANVAS_PROGRAM_ID is not set")?)?,
+        },
+        keypair_path: options.keypair.clone(),
+    };
+    let index = |text: &str| chikin_nft_canvas_tools::parse_coordinates(text);
+
+    match positional.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["mint", at, color] => cli.mint(index(at)?, render::parse_pixel_color(color)?, options.price, options.allowlist.as_deref()),
+        ["paint", at, color] => cli.paint(index(at)?, render::parse_pixel_color(color)?),
+        ["list", at, price] => cli.list(index(at)?, price.parse()?, options.sol),
+        ["unlist", at] => cli.unlist(index(at)?, options.sol),
+        ["buy", at, price] => cli.buy(index(at)?, price.parse()?, options.sol, true),
+        ["bid", at, price] => cli.buy(index(at)?, price.parse()?, options.sol, false),
+        ["show", at] => cli.show(index(at)?),
+        ["owned-by", wallet] => cli.owned_by(&Pubkey::from_str(wallet)?),
+        ["init-stats"] => cli.init_stats(),
+        _ => Err(format!("expected a command\n\n{}", USAGE).into()),
+    }
+}
+
+struct Cli {
+    rpc: RpcClient,
+    program_id: Pubkey,
+    keypair_path: Option<String>,
+}
+
+impl Cli {
+    fn signer(&self) -> Result<Keypair, Box<dyn Error>> {
+        chikin_nft_canvas_tools::read_keypair(self.keypair_path.as_deref())
+    }
+
+    fn send(&self, signer: &Keypair, instruction: Instruction) -> Result<(), Box<dyn Error>> {
+        let blockhash = self.rpc.get_latest_blockhash()?;
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction], Some(&signer.pubkey()), &[signer], blockhash);
+        let signature = self.rpc.send_and_confirm_transaction(&transaction)?;
+        println!("Confirmed {}", signature);
+        Ok(())
+    }
+
+    fn pixel(&self, index: u32) -> Result<Option<Pixel>, Box<dyn Error>> {
+        let pixel_account = config::get_pixel(&self.program_id, index).0;
+        match self.rpc.get_account(&pixel_account)? {
+            Some(account) => Ok(Some(Pixel::unpack_account(&self.program_id, &account.data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// The pixels on the canvas in either layout, only those of `owner_wallet` when given.
+    fn pixels(&self, owner_wallet: Option<&Pubkey>) -> Result<Vec<Pixel>, Box<dyn Error>> {
+        let mut pixels = Vec::new();
+        // (A filter matches a single data size, so each layout takes its own query)
+        for &(data_size, owner_wallet_offset) in [
+            (Pixel::PACKED_SIZE, PIXEL_OWNER_WALLET_OFFSET),
+            (LegacyPixel::PACKED_SIZE, LEGACY_PIXEL_OWNER_WALLET_OFFSET),
+        ].iter() {
+            let mut filters = vec![json!({ "dataSize": data_size })];
+            if let Some(wallet) = owner_wallet {
+                filters.push(json!({ "memcmp": { "offset": owner_wallet_offset, "bytes": wallet.to_string() } }));
+            }
+            let dump = self.rpc.get_program_accounts(&self.program_id, json!(filters))?;
+            pixels.extend(chikin_nft_canvas_tools::dump::pixels(&dump.accounts, Some(&self.program_id))?);
+        }
+        Ok(pixels)
+    }
+
+    fn minted_pixel(&self, index: u32) -> Result<Pixel, Box<dyn Error>> {
+        let (x, y) = chikin_nft_canvas_tools::coordinates(index);
+        self.pixel(index)?.ok_or_else(|| format!("pixel {},{} is not minted", x, y).into())
+    }
+
+    fn token_program(&self) -> Result<Pubkey, Box<dyn Error>> {
+        self.rpc.get_account(&config::token_mint::id())?
+            .and_then(|account| account.owner)
+            .ok_or_else(|| "token mint not found".into())
+    }
+
+    fn mint(&self, index: u32, color: PixelColor, sell_price: u64, allowlist: Option<&str>) -> Result<(), Box<dyn Error>> {
+        let signer = self.signer()?;
+        let canvas_config = config::get_canvas_config(&self.program_id).0;
+        let canvas_config_state = CanvasConfig::unpack(
+            &self.rpc.get_account(&canvas_config)?.ok_or("canvas config not found")?.data)?;
+        let accounts = (
+            self.program_id,
+            canvas_config,
+            config::get_canvas_stats(&self.program_id).0,
+            config::get_canvas_stats_shard(&self.program_id, config::stats_shard(index)).0,
+            system_program::id(),
+            sysvar::rent::id(),
+            config::get_fee_vault(&self.program_id).0,
+            config::get_pixel(&self.program_id, index).0,
+            signer.pubkey(),
+        );
+        let instruction = if canvas_config_state.presale {
+            let allowlist = allowlist.ok_or("minting is in presale, pass --allowlist")?;
+            let wallets = fs::read_to_string(allowlist)?.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(Pubkey::from_str)
+                .collect::<Result<Vec<_>, _>>()?;
+            let tree = AllowlistTree::new(&wallets);
+            if tree.root() != canvas_config_state.allowlist_root {
+                return Err("the allowlist file does not match the allowlist of the canvas".into());
+            }
+            let proof = tree.proof(&signer.pubkey()).ok_or("the signer is not on the allowlist")?;
+            NftCanvasInstruction::presale_mint_pixel(
+                accounts.0, accounts.1, accounts.2, accounts.3, accounts.4, accounts.5, accounts.6, accounts.7, accounts.8,
+                config::get_presale_receipt(&self.program_id, &signer.pubkey()).0,
+                index, color, sell_price, &proof)
+        } else {
+            NftCanvasInstruction::mint_pixel(
+                accounts.0, accounts.1, accounts.2, accounts.3, accounts.4, accounts.5, accounts.6, accounts.7, accounts.8,
+                index, color, sell_price)
+        };
+        self.send(&signer, instruction)
+    }
+
+    fn paint(&self, index: u32, color: PixelColor) -> Result<(), Box<dyn Error>> {
+        let signer = self.signer()?;
+        self.send(&signer, NftCanvasInstruction::update_pixel_color(
+            self.program_id,
+            config::get_canvas_config(&self.program_id).0,
+            config::get_pixel(&self.program_id, index).0,
+            signer.pubkey(),
+            index,
+            color,
+        ))
+    }
+
+    fn list(&self, index: u32, price: u64, sol: bool) -> Result<(), Box<dyn Error>> {
+        let signer = self.signer()?;
+        let build = if sol { NftCanvasInstruction::list_pixel_sol } else { NftCanvasInstruction::list_pixel };
+        self.send(&signer, build(
+            self.program_id,
+            config::get_canvas_config(&self.program_id).0,
+            config::get_canvas_stats_shard(&self.program_id, config::stats_shard(index)).0,
+            config::get_pixel(&self.program_id, index).0,
+            signer.pubkey(),
+            index,
+            price,
+        ))
+    }
+
+    fn unlist(&self, index: u32, sol: bool) -> Result<(), Box<dyn Error>> {
+        let signer = self.signer()?;
+        let build = if sol { NftCanvasInstruction::unlist_pixel_sol } else { NftCanvasInstruction::unlist_pixel };
+        self.send(&signer, build(
+            self.program_id,
+            config::get_canvas_config(&self.program_id).0,
+            config::get_canvas_stats_shard(&self.program_id, config::stats_shard(index)).0,
+            config::get_pixel(&self.program_id, index).0,
+            signer.pubkey(),
+            index,
+        ))
+    }
+
+    fn buy(&self, index: u32, price: u64, sol: bool, direct_only: bool) -> Result<(), Box<dyn Error>> {
+        let signer = self.signer()?;
+        let pixel = self.minted_pixel(index)?;
+        let instruction = if sol {
+            NftCanvasInstruction::buy_pixel_sol(
+                self.program_id,
+                config::get_canvas_config(&self.program_id).0,
+                config::get_canvas_stats_shard(&self.program_id, config::stats_shard(index)).0,
+                system_program::id(),
+                config::get_pixel(&self.program_id, index).0,
+                config::get_fee_vault(&self.program_id).0,
+                config::get_trade_pool(&self.program_id).0,
+                pixel.owner_wallet,
+                signer.pubkey(),
+                pixel.best_sol_buy_info.map(|buy_info| buy_info.buyer_wallet),
+                index,
+                price,
+                direct_only as u8,
+            )
+        } else {
+            let token_program = self.token_program()?;
+            let fee_vault = config::get_fee_vault(&self.program_id).0;
+            let trade_pool = config::get_trade_pool(&self.program_id).0;
+            NftCanvasInstruction::buy_pixel(
+                self.program_id,
+                config::get_canvas_config(&self.program_id).0,
+                config::get_canvas_stats_shard(&self.program_id, config::stats_shard(index)).0,
+                token_program,
+                config::token_mint::id(),
+                system_program::id(),
+                sysvar::rent::id(),
+                spl_associated_token_account::id(),
+                config::get_pixel(&self.program_id, index).0,
+                fee_vault,
+                config::get_token_account(&fee_vault, &token_program),
+                trade_pool,
+                config::get_token_account(&trade_pool, &token_program),
+                pixel.owner_wallet,
+                config::get_token_account(&pixel.owner_wallet, &token_program),
+                signer.pubkey(),
+                config::get_token_account(&signer.pubkey(), &token_program),
+                pixel.best_buy_info.map(|buy_info| buy_info.buyer_wallet),
+                index,
+                price,
+                direct_only as u8,
+            )
+        };
+        self.send(&signer, instruction)
+    }
+
+    fn show(&self, index: u32) -> Result<(), Box<dyn Error>> {
+        let (x, y) = chikin_nft_canvas_tools::coordinates(index);
+        println!("pixel {},{} (index {}, account {})", x, y, index, config::get_pixel(&self.program_id, index).0);
+        let pixel = match self.pixel(index)? {
+            Some(pixel) => pixel,
+            None => {
+                println!("not minted");
+                return Ok(());
+            }
+        };
+        let price = |price: u64| if price == 0 { "not listed".to_string() } else { price.to_string() };
+        let bid = |buy_info: Option<PixelBuyInfo>| buy_info
+            .map(|buy_info| format!("{} by {}", buy_info.price, buy_info.buyer_wallet))
+            .unwrap_or_else(|| "none".to_string());
+        println!("color: {}", render::format_color(pixel.color));
+        println!("owner: {}", pixel.owner_wallet);
+        println!("color delegate: {}", pixel.color_delegate.map(|delegate| delegate.to_string()).unwrap_or_else(|| "none".to_string()));
+        println!("token price: {}", price(pixel.sell_price));
+        println!("best token bid: {}", bid(pixel.best_buy_info));
+        println!("sol price: {}", price(pixel.sol_sell_price));
+        println!("best sol bid: {}", bid(pixel.best_sol_buy_info));
+        Ok(())
+    }
+
+    fn owned_by(&self, wallet: &Pubkey) -> Result<(), Box<dyn Error>> {
+        let mut pixels = self.pixels(Some(wallet))?;
+        pixels.sort_by_key(|pixel| pixel.index);
+        for pixel in &pixels {
+            let (x, y) = chikin_nft_canvas_tools::coordinates(pixel.index);
+            println!("{},{} {}", x, y, render::format_color(pixel.color));
+        }
+        println!("{} pixels owned by {}", pixels.len(), wallet);
+        Ok(())
+    }
+
+    fn init_stats(&self) -> Result<(), Box<dyn Error>> {
+        let signer = self.signer()?;
+        let canvas_config = config::get_canvas_config(&self.program_id).0;
+        let pixels = self.pixels(None)?;
+
+        let canvas_stats = config::get_canvas_stats(&self.program_id).0;
+        if self.rpc.get_account(&canvas_stats)?.is_none() {
+            println!("stats: {} minted", pixels.len());
+            self.send(&signer, NftCanvasInstruction::initialize_stats(
+                self.program_id, system_program::id(), sysvar::rent::id(), canvas_config, canvas_stats, signer.pubkey(),
+                pixels.len() as u32,
+            ))?;
+        }
+        // (Pixels of a shard cannot be minted, closed or traded before it exists, so its counts stay right)
+        for shard in 0..config::STATS_SHARDS as u8 {
+            let canvas_stats_shard = config::get_canvas_stats_shard(&self.program_id, shard).0;
+            if self.rpc.get_account(&canvas_stats_shard)?.is_some() {
+                continue;
+            }
+            let mut counts = CanvasStatsShard::new(shard);
+            pixels.iter()
+                .filter(|pixel| config::stats_shard(pixel.index) == shard)
+                .for_each(|pixel| counts.add_pixel(pixel));
+            println!("stats shard {}: {} listings, {} sol listings, {} bids, {} sol bids",
+                shard, counts.listings, counts.sol_listings, counts.open_bids, counts.open_sol_bids);
+            self.send(&signer, NftCanvasInstruction::initialize_stats_shard(
+                self.program_id, system_program::id(), sysvar::rent::id(), canvas_config, canvas_stats_shard, signer.pubkey(),
+                shard, counts.listings, counts.sol_listings, counts.open_bids, counts.open_sol_bids,
+            ))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,63 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::process;
+use std::str::FromStr;
+
+use chikin_nft_canvas_tools::render;
+use chikin_nft_canvas_tools::render::RenderOptions;
+use solana_program::pubkey::Pubkey;
+
+const USAGE: &str = "\
+Usage: render-canvas <INPUT> <OUTPUT_PNG> [OPTIONS]
+
+INPUT is a canvas snapshot, or a getProgramAccounts response with base64 account data.
+
+Options:
+    --unminted <RRGGBB>        Color of unminted pixels (default 000000)
+    --listed <RRGGBB>          Highlight listed pixels with this color
+    --owner <WALLET>           Highlight the pixels of this wallet
+    --owner-color <RRGGBB>     Highlight color for --owner (default ff00ff)
+    --program-id <PROGRAM_ID>  Program of the pixels, for legacy pixels in dumps without account owners";
+
+fn main() {
+    if let Err(err) = run(std::env::args().skip(1).collect()) {
+        eprintln!("error: {}", err);
+        eprintln!();
+        eprintln!("{}", USAGE);
+        process::exit(1);
+    }
+}
+
+fn run(args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let mut positional = Vec::new();
+    let mut options = RenderOptions::default();
+    let mut owner = None;
+    let mut owner_color = [255, 0, 255];
+    let mut program_id = None;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("missing value for {}", arg));
+        match arg.as_str() {
+            "--unminted" => options.unminted_color = render::parse_color(&value()?)?,
+            "--listed" => options.listed_overlay = Some(render::parse_color(&value()?)?),
+            "--owner" => owner = Some(Pubkey::from_str(&value()?)?),
+            "--owner-color" => owner_color = render::parse_color(&value()?)?,
+            "--program-id" => program_id = Some(Pubkey::from_str(&value()?)?),
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg).into()),
+            _ => positional.push(arg),
+        }
+    }
+    options.owner_overlay = owner.map(|owner| (owner, owner_color));
+    let (input, output) = match positional.as_slice() {
+        [input, output] => (input, output),
+        _ => return Err("expected INPUT and OUTPUT_PNG".into()),
+    };
+
+    let pixels = chikin_nft_canvas_tools::read_pixels_file(input, program_id.as_ref())?;
+    let image = render::render(&pixels, &options);
+    image.write_png(BufWriter::new(File::create(output)?))?;
+    println!("Rendered {} pixels to {}", pixels.len(), output);
+    Ok(())
+}
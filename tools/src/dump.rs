@@ -0,0 +1,88 @@
+//! Account dumps from JSON RPC
+
+use std::error::Error;
+use std::str::FromStr;
+
+use chikin_nft_canvas::packable::Packable;
+use chikin_nft_canvas::state::{LegacyPixel, Pixel};
+use serde_json::Value;
+use solana_program::pubkey::Pubkey;
+
+pub struct AccountDump {
+    /// Only known when the dump was requested with `withContext`.
+    pub slot: Option<u64>,
+    pub accounts: Vec<DumpedAccount>,
+}
+
+pub struct DumpedAccount {
+    pub pubkey: Pubkey,
+    pub owner: Option<Pubkey>,
+    pub data: Vec<u8>,
+}
+
+/// Parses the accounts of a `getProgramAccounts` response (or of its `result` array),
+/// or a single account as printed by `solana account --output json`.
+/// Account data must be base64 encoded.
+pub fn parse_dump(json: &str) -> Result<AccountDump, Box<dyn Error>> {
+    dump_from_value(&serde_json::from_str(json)?)
+}
+
+/// Same as `parse_dump`, for JSON that is already parsed.
+pub fn dump_from_value(value: &Value) -> Result<AccountDump, Box<dyn Error>> {
+    let mut value = value.get("result").unwrap_or(value);
+    let slot = value.get("context").and_then(|context| context.get("slot")).and_then(Value::as_u64);
+    if slot.is_some() {
+        value = value.get("value").ok_or("missing value next to context")?;
+    }
+    let accounts = match value {
+        Value::Array(items) => items.iter().map(parse_account).collect::<Result<_, _>>()?,
+        Value::Object(_) => vec![parse_account(value)?],
+        _ => return Err("expected an account or an array of accounts".into()),
+    };
+    Ok(AccountDump { slot, accounts })
+}
+
+fn parse_account(value: &Value) -> Result<DumpedAccount, Box<dyn Error>> {
+    let pubkey = value.get("pubkey").and_then(Value::as_str)
+        .ok_or("missing account pubkey")?;
+    let data = account_field(value, "data").ok_or("missing account data")?;
+    let encoded = match data {
+        Value::Array(parts) if parts.get(1).and_then(Value::as_str) == Some("base64") => parts[0].as_str(),
+        _ => None,
+    }.ok_or("account data is not base64 encoded")?;
+    let owner = match account_field(value, "owner").and_then(Value::as_str) {
+        Some(owner) => Some(Pubkey::from_str(owner)?),
+        None => None,
+    };
+    Ok(DumpedAccount {
+        pubkey: Pubkey::from_str(pubkey)?,
+        owner,
+        data: base64::decode(encoded)?,
+    })
+}
+
+fn account_field<'a>(value: &'a Value, field: &str) -> Option<&'a Value> {
+    value.get("account").and_then(|account| account.get(field))
+}
+
+/// Whether the account has the size of a pixel, in either layout. No other program account has these sizes.
+pub fn is_pixel_account(account: &DumpedAccount) -> bool {
+    account.data.len() == Pixel::PACKED_SIZE || account.data.len() == LegacyPixel::PACKED_SIZE
+}
+
+/// Decodes the pixel accounts of either layout, skipping the program's other accounts.
+/// Legacy pixels get their bump seed from `program_id`, or else from the account owner in the dump.
+pub fn pixels(accounts: &[DumpedAccount], program_id: Option<&Pubkey>) -> Result<Vec<Pixel>, Box<dyn Error>> {
+    accounts.iter()
+        .filter(|account| is_pixel_account(account))
+        .map(|account| {
+            let program_id = program_id.or(account.owner.as_ref());
+            let pixel = match program_id {
+                Some(program_id) => Pixel::unpack_account(program_id, &account.data),
+                None if account.data.len() == Pixel::PACKED_SIZE => Pixel::unpack(&account.data),
+                None => return Err(format!("legacy pixel account {} has no owner in the dump, pass a program id", account.pubkey).into()),
+            };
+            pixel.map_err(|err| format!("pixel account {}: {}", account.pubkey, err).into())
+        })
+        .collect()
+}
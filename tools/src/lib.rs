@@ -0,0 +1,63 @@
+//! Off-chain tools for the canvas: reading account dumps and snapshots, rendering the canvas,
+//! planning images onto it and talking to a cluster.
+
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use chikin_nft_canvas::config;
+use chikin_nft_canvas::error::NftCanvasError;
+use chikin_nft_canvas::state::Pixel;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, Keypair};
+
+pub mod dump;
+pub mod plan;
+pub mod render;
+pub mod rpc;
+pub mod snapshot;
+
+/// Parses `x,y` into a pixel index.
+pub fn parse_coordinates(text: &str) -> Result<u32, String> {
+    let invalid = || format!("invalid coordinates {}, expected x,y", text);
+    let mut parts = text.splitn(2, ',');
+    let x: u32 = parts.next().and_then(|x| x.trim().parse().ok()).ok_or_else(invalid)?;
+    let y: u32 = parts.next().and_then(|y| y.trim().parse().ok()).ok_or_else(invalid)?;
+    if x >= config::CANVAS_WIDTH || y >= config::CANVAS_HEIGHT {
+        return Err(format!("coordinates {} are outside of the {}x{} canvas", text, config::CANVAS_WIDTH, config::CANVAS_HEIGHT));
+    }
+    Ok(y * config::CANVAS_WIDTH + x)
+}
+
+pub fn coordinates(index: u32) -> (u32, u32) {
+    (index % config::CANVAS_WIDTH, index / config::CANVAS_WIDTH)
+}
+
+/// Name and message of the `NftCanvasError` behind a custom program error code.
+pub fn program_error_name(code: u32) -> String {
+    match NftCanvasError::from_code(code) {
+        Some(err) => format!("{:?}: {}", err, err),
+        None => format!("unknown program error {}", code),
+    }
+}
+
+/// Reads the pixels of a snapshot file or of a JSON account dump.
+/// `program_id` is only needed for legacy pixels in dumps without account owners.
+pub fn read_pixels_file<P: AsRef<Path>>(path: P, program_id: Option<&Pubkey>) -> Result<Vec<Pixel>, Box<dyn Error>> {
+    let bytes = fs::read(path)?;
+    if bytes.starts_with(snapshot::MAGIC) {
+        Ok(snapshot::Snapshot::read(&bytes[..])?.pixels)
+    } else {
+        dump::pixels(&dump::parse_dump(std::str::from_utf8(&bytes)?)?.accounts, program_id)
+    }
+}
+
+/// Reads a keypair file, `~/.config/solana/id.json` by default like the Solana CLI.
+pub fn read_keypair(path: Option<&str>) -> Result<Keypair, Box<dyn Error>> {
+    let path = match path {
+        Some(path) => path.to_string(),
+        None => format!("{}/.config/solana/id.json", env::var("HOME")?),
+    };
+    read_keypair_file(&path).map_err(|err| format!("could not read keypair {}: {}", path, err).into())
+}
@@ -0,0 +1,299 @@
+//! Plans painting an image onto the canvas
+//!
+//! The image is scaled to the target rectangle, each cell averaging the image pixels it covers,
+//! then diffed against the current pixels. Unminted pixels are minted in the image color, pixels
+//! the wallet may paint are painted, and the rest are reported as blocked. Colors follow the canvas
+//! color mode: alpha is dropped unless it is RGBA, and palette canvases get the nearest palette color.
+//! The instructions are packed into as few transactions as fit the packet size.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::Read;
+
+use chikin_nft_canvas::color::{ColorMode, PixelColor};
+use chikin_nft_canvas::config;
+use chikin_nft_canvas::instruction::NftCanvasInstruction;
+use chikin_nft_canvas::state::{CanvasConfig, Pixel};
+use solana_program::instruction::Instruction;
+use solana_program::message::Message;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+use solana_program::sysvar;
+use solana_sdk::packet::PACKET_DATA_SIZE;
+use solana_sdk::transaction::Transaction;
+
+/// Cells whose average alpha is below this are left alone.
+pub const ALPHA_THRESHOLD: u8 = 128;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn check_on_canvas(&self) -> Result<(), String> {
+        if self.width == 0 || self.height == 0 {
+            return Err("the target rectangle is empty".to_string());
+        }
+        if self.x as u64 + self.width as u64 > config::CANVAS_WIDTH as u64
+            || self.y as u64 + self.height as u64 > config::CANVAS_HEIGHT as u64 {
+            return Err(format!("the {}x{} rectangle at {},{} is outside of the {}x{} canvas",
+                self.width, self.height, self.x, self.y, config::CANVAS_WIDTH, config::CANVAS_HEIGHT));
+        }
+        Ok(())
+    }
+
+    /// Canvas pixel indexes, row-major.
+    pub fn indexes(&self) -> impl Iterator<Item = u32> + '_ {
+        (self.y..self.y + self.height)
+            .flat_map(move |y| (self.x..self.x + self.width).map(move |x| y * config::CANVAS_WIDTH + x))
+    }
+}
+
+/// Parses `WIDTHxHEIGHT`.
+pub fn parse_size(text: &str) -> Result<(u32, u32), String> {
+    let invalid = || format!("invalid size {}, expected WIDTHxHEIGHT", text);
+    let mut parts = text.splitn(2, 'x');
+    let width = parts.next().and_then(|width| width.trim().parse().ok()).ok_or_else(invalid)?;
+    let height = parts.next().and_then(|height| height.trim().parse().ok()).ok_or_else(invalid)?;
+    Ok((width, height))
+}
+
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl RgbaImage {
+    pub fn read_png<R: Read>(reader: R) -> Result<RgbaImage, Box<dyn Error>> {
+        let mut decoder = png::Decoder::new(reader);
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let mut reader = decoder.read_info()?;
+        let mut buffer = vec![0u8; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer)?;
+        let pixels = buffer[..info.buffer_size()].chunks_exact(info.color_type.samples());
+        let rgba = match info.color_type {
+            png::ColorType::Grayscale => pixels.flat_map(|p| [p[0], p[0], p[0], 255]).collect(),
+            png::ColorType::GrayscaleAlpha => pixels.flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+            png::ColorType::Rgb => pixels.flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+            png::ColorType::Rgba => pixels.flat_map(|p| [p[0], p[1], p[2], p[3]]).collect(),
+            color_type => return Err(format!("unsupported PNG color type {:?}", color_type).into()),
+        };
+        Ok(RgbaImage { width: info.width, height: info.height, rgba })
+    }
+
+    /// Scales the image to `width` x `height` cells of RGBA colors, row-major. `None` for transparent cells.
+    pub fn quantize(&self, width: u32, height: u32) -> Vec<Option<[u8; 4]>> {
+        let mut cells = Vec::with_capacity((width * height) as usize);
+        for cell_y in 0..height {
+            let (top, bottom) = span(cell_y, height, self.height);
+            for cell_x in 0..width {
+                let (left, right) = span(cell_x, width, self.width);
+                // (Colors are weighted by alpha, so transparent pixels do not darken the edges)
+                let mut sums = [0u64; 4];
+                for y in top..bottom {
+                    for x in left..right {
+                        let offset = ((y * self.width + x) * 4) as usize;
+                        let alpha = self.rgba[offset + 3] as u64;
+                        for (sum, value) in sums.iter_mut().zip(&self.rgba[offset..offset + 3]) {
+                            *sum += *value as u64 * alpha;
+                        }
+                        sums[3] += alpha;
+                    }
+                }
+                let count = ((bottom - top) * (right - left)) as u64;
+                if sums[3] < ALPHA_THRESHOLD as u64 * count {
+                    cells.push(None);
+                } else {
+                    let average = |channel: usize| ((sums[channel] + sums[3] / 2) / sums[3]) as u8;
+                    let alpha = ((sums[3] + count / 2) / count) as u8;
+                    cells.push(Some([average(0), average(1), average(2), alpha]));
+                }
+            }
+        }
+        cells
+    }
+}
+
+/// The image pixels covered by `cell` of `cells`, never empty.
+fn span(cell: u32, cells: u32, pixels: u32) -> (u32, u32) {
+    let start = (cell as u64 * pixels as u64 / cells as u64) as u32;
+    let end = ((cell as u64 + 1) * pixels as u64 / cells as u64) as u32;
+    (start.min(pixels - 1), end.max(start + 1).min(pixels))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PixelChange {
+    Mint { index: u32, color: PixelColor },
+    Paint { index: u32, color: PixelColor },
+}
+
+/// The color closest to `rgba` that the color mode of `canvas_config` allows.
+pub fn pixel_color(rgba: [u8; 4], canvas_config: &CanvasConfig) -> PixelColor {
+    match canvas_config.color_mode {
+        ColorMode::Rgb => PixelColor::Rgb([rgba[0], rgba[1], rgba[2]]),
+        ColorMode::Rgba => PixelColor::Rgba(rgba),
+        ColorMode::Palette => {
+            let distance = |rgb: &[u8; 3]| (0..3)
+                .map(|i| (rgb[i] as i32 - rgba[i] as i32).pow(2))
+                .sum::<i32>();
+            let index = canvas_config.palette().iter()
+                .enumerate()
+                .min_by_key(|(_, rgb)| distance(rgb))
+                .map_or(0, |(index, _)| index);
+            PixelColor::Palette(index as u8)
+        }
+    }
+}
+
+/// The changes needed to show `cells` in `rect`.
+#[derive(Debug, Default, PartialEq)]
+pub struct Diff {
+    pub changes: Vec<PixelChange>,
+    pub unchanged: usize,
+    pub transparent: usize,
+    /// Pixels in another color that `wallet` may not paint.
+    pub blocked: Vec<u32>,
+}
+
+pub fn diff(
+    cells: &[Option<[u8; 4]>],
+    rect: &Rect,
+    current: &HashMap<u32, Pixel>,
+    wallet: &Pubkey,
+    canvas_config: &CanvasConfig,
+) -> Diff {
+    let mut diff = Diff::default();
+    for (index, cell) in rect.indexes().zip(cells) {
+        let color = match cell {
+            Some(rgba) => pixel_color(*rgba, canvas_config),
+            None => {
+                diff.transparent += 1;
+                continue;
+            }
+        };
+        match current.get(&index) {
+            None => diff.changes.push(PixelChange::Mint { index, color }),
+            Some(pixel) if canvas_config.resolve_color(&color) == Ok(pixel.color) => diff.unchanged += 1,
+            Some(pixel) if pixel.is_color_authority(wallet) => diff.changes.push(PixelChange::Paint { index, color }),
+            Some(_) => diff.blocked.push(index),
+        }
+    }
+    diff
+}
+
+/// What the plan needs to know about the cluster.
+pub struct PlanContext {
+    pub program_id: Pubkey,
+    pub wallet: Pubkey,
+    pub canvas_config: CanvasConfig,
+    /// `CanvasStats::minted`, read from the canvas stats account, which must exist.
+    pub minted: u32,
+    pub pixel_rent: u64,
+    pub lamports_per_signature: u64,
+}
+
+/// In lamports.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PlanCost {
+    pub mint: u64,
+    pub rent: u64,
+    pub signature_fees: u64,
+}
+
+impl PlanCost {
+    pub fn total(&self) -> u64 {
+        self.mint + self.rent + self.signature_fees
+    }
+}
+
+pub struct Plan {
+    pub transactions: Vec<Vec<Instruction>>,
+    pub mints: usize,
+    pub paints: usize,
+    pub cost: PlanCost,
+}
+
+pub fn plan(changes: &[PixelChange], context: &PlanContext) -> Result<Plan, Box<dyn Error>> {
+    let canvas_config_state = &context.canvas_config;
+    let mints = changes.iter().filter(|change| matches!(change, PixelChange::Mint { .. })).count();
+    let paints = changes.len() - mints;
+    if mints > 0 {
+        canvas_config_state.check_not_paused()?;
+        if canvas_config_state.presale {
+            return Err("minting is in presale, which needs an allowlist proof per mint and is not planned".into());
+        }
+    }
+    if paints > 0 {
+        canvas_config_state.check_painting_not_paused()?;
+    }
+
+    let program_id = context.program_id;
+    let canvas_config = config::get_canvas_config(&program_id).0;
+    let canvas_stats = config::get_canvas_stats(&program_id).0;
+    let fee_vault = config::get_fee_vault(&program_id).0;
+    let instructions = changes.iter().map(|change| match *change {
+        PixelChange::Mint { index, color } => NftCanvasInstruction::mint_pixel(
+            program_id,
+            canvas_config,
+            canvas_stats,
+            config::get_canvas_stats_shard(&program_id, config::stats_shard(index)).0,
+            system_program::id(),
+            sysvar::rent::id(),
+            fee_vault,
+            config::get_pixel(&program_id, index).0,
+            context.wallet,
+            index,
+            color,
+            0,
+        ),
+        PixelChange::Paint { index, color } => NftCanvasInstruction::update_pixel_color(
+            program_id,
+            canvas_config,
+            config::get_pixel(&program_id, index).0,
+            context.wallet,
+            index,
+            color,
+        ),
+    });
+    let transactions = pack_transactions(instructions, &context.wallet)?;
+
+    let mut cost = PlanCost::default();
+    for minted in context.minted..context.minted.saturating_add(mints as u32) {
+        cost.mint = cost.mint.saturating_add(canvas_config_state.mint_price(minted));
+    }
+    cost.rent = context.pixel_rent * mints as u64;
+    // (The wallet is the only signer)
+    cost.signature_fees = context.lamports_per_signature * transactions.len() as u64;
+    Ok(Plan { transactions, mints, paints, cost })
+}
+
+/// Packs instructions, in order, into as few transactions paid by `payer` as fit the packet size.
+pub fn pack_transactions<I: IntoIterator<Item = Instruction>>(instructions: I, payer: &Pubkey) -> Result<Vec<Vec<Instruction>>, Box<dyn Error>> {
+    let mut transactions: Vec<Vec<Instruction>> = Vec::new();
+    let mut current = Vec::new();
+    for instruction in instructions {
+        current.push(instruction);
+        if transaction_size(&current, payer)? > PACKET_DATA_SIZE {
+            let instruction = current.pop().unwrap();
+            if current.is_empty() {
+                return Err("an instruction does not fit in a transaction".into());
+            }
+            transactions.push(current);
+            current = vec![instruction];
+        }
+    }
+    if !current.is_empty() {
+        transactions.push(current);
+    }
+    Ok(transactions)
+}
+
+pub fn transaction_size(instructions: &[Instruction], payer: &Pubkey) -> Result<usize, Box<dyn Error>> {
+    let transaction = Transaction::new_unsigned(Message::new(instructions, Some(payer)));
+    Ok(bincode::serialized_size(&transaction)? as usize)
+}
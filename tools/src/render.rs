@@ -0,0 +1,123 @@
+//! Canvas rendering
+
+use std::io::Write;
+
+use chikin_nft_canvas::color;
+use chikin_nft_canvas::color::PixelColor;
+use chikin_nft_canvas::config;
+use chikin_nft_canvas::state::Pixel;
+use solana_program::pubkey::Pubkey;
+
+#[derive(Default)]
+pub struct RenderOptions {
+    /// Black by default. Also shows through transparent pixels.
+    pub unminted_color: [u8; 3],
+    /// Blended over pixels listed for tokens or SOL.
+    pub listed_overlay: Option<[u8; 3]>,
+    /// Blended over the pixels of a wallet.
+    pub owner_overlay: Option<(Pubkey, [u8; 3])>,
+}
+
+/// RGB image of the canvas, one image pixel per canvas pixel.
+pub struct CanvasImage {
+    pub width: u32,
+    pub height: u32,
+    pub rgb: Vec<u8>,
+}
+
+impl CanvasImage {
+    pub fn color_at(&self, x: u32, y: u32) -> [u8; 3] {
+        let offset = (y * self.width + x) as usize * 3;
+        [self.rgb[offset], self.rgb[offset + 1], self.rgb[offset + 2]]
+    }
+
+    pub fn write_png<W: Write>(&self, writer: W) -> Result<(), png::EncodingError> {
+        let mut encoder = png::Encoder::new(writer, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.rgb)
+    }
+}
+
+pub fn render(pixels: &[Pixel], options: &RenderOptions) -> CanvasImage {
+    let mut rgb = options.unminted_color.repeat(config::PIXEL_COUNT as usize);
+    for pixel in pixels.iter().filter(|pixel| pixel.index < config::PIXEL_COUNT) {
+        let mut color = over(pixel.color, options.unminted_color);
+        if let Some(listed_overlay) = options.listed_overlay {
+            if pixel.sell_price != 0 || pixel.sol_sell_price != 0 {
+                color = blend(color, listed_overlay);
+            }
+        }
+        if let Some((owner, owner_overlay)) = options.owner_overlay.as_ref() {
+            if &pixel.owner_wallet == owner {
+                color = blend(color, *owner_overlay);
+            }
+        }
+        let offset = pixel.index as usize * 3;
+        rgb[offset..offset + 3].copy_from_slice(&color);
+    }
+    CanvasImage {
+        width: config::CANVAS_WIDTH,
+        height: config::CANVAS_HEIGHT,
+        rgb,
+    }
+}
+
+fn blend(color: [u8; 3], overlay: [u8; 3]) -> [u8; 3] {
+    let mut result = [0u8; 3];
+    for i in 0..3 {
+        result[i] = ((color[i] as u16 + overlay[i] as u16) / 2) as u8;
+    }
+    result
+}
+
+/// Composites the RGBA `color` over `background`.
+fn over(color: [u8; 4], background: [u8; 3]) -> [u8; 3] {
+    let alpha = color[3] as u16;
+    let mut result = [0u8; 3];
+    for i in 0..3 {
+        result[i] = ((color[i] as u16 * alpha + background[i] as u16 * (255 - alpha) + 127) / 255) as u8;
+    }
+    result
+}
+
+/// Parses `RRGGBB`, with or without a leading `#`.
+pub fn parse_color(text: &str) -> Result<[u8; 3], String> {
+    let mut color = [0u8; 3];
+    parse_hex(text, &mut color).map_err(|_| format!("invalid color {}, expected RRGGBB", text))?;
+    Ok(color)
+}
+
+/// Parses `RRGGBB`, `RRGGBBAA` (with or without a leading `#`), or `pINDEX` for a palette color.
+pub fn parse_pixel_color(text: &str) -> Result<PixelColor, String> {
+    let invalid = |_| format!("invalid color {}, expected RRGGBB, RRGGBBAA or pINDEX", text);
+    if let Some(index) = text.strip_prefix('p') {
+        return index.parse().map(PixelColor::Palette).map_err(|_| invalid(()));
+    }
+    if text.trim_start_matches('#').len() == 8 {
+        let mut rgba = [0u8; 4];
+        parse_hex(text, &mut rgba).map_err(invalid)?;
+        Ok(PixelColor::Rgba(rgba))
+    } else {
+        let mut rgb = [0u8; 3];
+        parse_hex(text, &mut rgb).map_err(invalid)?;
+        Ok(PixelColor::Rgb(rgb))
+    }
+}
+
+/// `#RRGGBB`, or `#RRGGBBAA` unless opaque.
+pub fn format_color(color: [u8; 4]) -> String {
+    let components = if color[3] == color::OPAQUE { &color[..3] } else { &color[..] };
+    components.iter().fold("#".to_string(), |text, component| format!("{}{:02x}", text, component))
+}
+
+fn parse_hex(text: &str, components: &mut [u8]) -> Result<(), ()> {
+    let hex = text.trim_start_matches('#');
+    if hex.len() != components.len() * 2 || !hex.is_ascii() {
+        return Err(());
+    }
+    for (i, component) in components.iter_mut().enumerate() {
+        *component = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| ())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,157 @@
+//! Minimal blocking JSON RPC client, covering what the tools need
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+use solana_program::hash::Hash;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::Transaction;
+
+use crate::dump;
+use crate::dump::{AccountDump, DumpedAccount};
+
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(60);
+const CONFIRM_POLL_INTERVAL: Duration = Duration::from_millis(500);
+// (Node limit for getMultipleAccounts)
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// An error returned by the node, with the program error name when a custom program error caused it.
+#[derive(Debug)]
+pub struct RpcError {
+    pub message: String,
+    pub program_error: Option<String>,
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.program_error {
+            Some(program_error) => write!(f, "{} ({})", self.message, program_error),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl Error for RpcError {}
+
+impl RpcError {
+    fn new(message: String, err: Option<&Value>) -> RpcError {
+        let program_error = err.and_then(custom_error_code).map(crate::program_error_name);
+        RpcError { message, program_error }
+    }
+}
+
+/// Finds `{"InstructionError": [_, {"Custom": code}]}` in a transaction error.
+fn custom_error_code(err: &Value) -> Option<u32> {
+    err.get("InstructionError")?.get(1)?.get("Custom")?.as_u64().map(|code| code as u32)
+}
+
+pub struct RpcClient {
+    url: String,
+}
+
+impl RpcClient {
+    pub fn new(url: &str) -> RpcClient {
+        RpcClient { url: url.to_string() }
+    }
+
+    fn call(&self, method: &str, params: Value) -> Result<Value, Box<dyn Error>> {
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let response: Value = ureq::post(&self.url).send_json(request)?.into_json()?;
+        if let Some(error) = response.get("error") {
+            let message = error.get("message").and_then(Value::as_str).unwrap_or("unknown error");
+            let err = error.get("data").and_then(|data| data.get("err"));
+            return Err(RpcError::new(format!("{} failed: {}", method, message), err).into());
+        }
+        response.get("result").cloned().ok_or_else(|| format!("{} returned no result", method).into())
+    }
+
+    /// `None` if the account does not exist.
+    pub fn get_account(&self, pubkey: &Pubkey) -> Result<Option<DumpedAccount>, Box<dyn Error>> {
+        let result = self.call("getAccountInfo", json!([pubkey.to_string(), { "encoding": "base64" }]))?;
+        let value = result.get("value").ok_or("getAccountInfo returned no value")?;
+        if value.is_null() {
+            return Ok(None);
+        }
+        let account = json!({ "pubkey": pubkey.to_string(), "account": value });
+        Ok(dump::dump_from_value(&account)?.accounts.pop())
+    }
+
+    /// In the order of `pubkeys`, `None` for the accounts that do not exist.
+    pub fn get_multiple_accounts(&self, pubkeys: &[Pubkey]) -> Result<Vec<Option<DumpedAccount>>, Box<dyn Error>> {
+        let mut accounts = Vec::with_capacity(pubkeys.len());
+        for chunk in pubkeys.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            let keys: Vec<String> = chunk.iter().map(Pubkey::to_string).collect();
+            let result = self.call("getMultipleAccounts", json!([keys, { "encoding": "base64" }]))?;
+            let values = result.get("value").and_then(Value::as_array)
+                .filter(|values| values.len() == chunk.len())
+                .ok_or("getMultipleAccounts returned no values")?;
+            for (pubkey, value) in chunk.iter().zip(values) {
+                if value.is_null() {
+                    accounts.push(None);
+                } else {
+                    let account = json!({ "pubkey": pubkey.to_string(), "account": value });
+                    accounts.push(dump::dump_from_value(&account)?.accounts.pop());
+                }
+            }
+        }
+        Ok(accounts)
+    }
+
+    pub fn get_minimum_balance_for_rent_exemption(&self, data_len: usize) -> Result<u64, Box<dyn Error>> {
+        let result = self.call("getMinimumBalanceForRentExemption", json!([data_len]))?;
+        result.as_u64().ok_or_else(|| "getMinimumBalanceForRentExemption returned no balance".into())
+    }
+
+    pub fn get_lamports_per_signature(&self) -> Result<u64, Box<dyn Error>> {
+        let result = self.call("getFees", json!([]))
+            .or_else(|_| self.call("getRecentBlockhash", json!([])))?;
+        result.get("value").and_then(|value| value.get("feeCalculator"))
+            .and_then(|fee_calculator| fee_calculator.get("lamportsPerSignature")).and_then(Value::as_u64)
+            .ok_or_else(|| "no fee calculator returned".into())
+    }
+
+    pub fn get_program_accounts(&self, program_id: &Pubkey, filters: Value) -> Result<AccountDump, Box<dyn Error>> {
+        let result = self.call("getProgramAccounts", json!([
+            program_id.to_string(),
+            { "encoding": "base64", "filters": filters, "withContext": true },
+        ]))?;
+        dump::dump_from_value(&result)
+    }
+
+    pub fn get_latest_blockhash(&self) -> Result<Hash, Box<dyn Error>> {
+        // (Older nodes only have getRecentBlockhash)
+        let result = self.call("getLatestBlockhash", json!([]))
+            .or_else(|_| self.call("getRecentBlockhash", json!([])))?;
+        let blockhash = result.get("value").and_then(|value| value.get("blockhash")).and_then(Value::as_str)
+            .ok_or("no blockhash returned")?;
+        Ok(Hash::from_str(blockhash)?)
+    }
+
+    pub fn send_and_confirm_transaction(&self, transaction: &Transaction) -> Result<Signature, Box<dyn Error>> {
+        let encoded = base64::encode(bincode::serialize(transaction)?);
+        let result = self.call("sendTransaction", json!([encoded, { "encoding": "base64" }]))?;
+        let signature = Signature::from_str(result.as_str().ok_or("sendTransaction returned no signature")?)?;
+
+        let started = Instant::now();
+        while started.elapsed() < CONFIRM_TIMEOUT {
+            let result = self.call("getSignatureStatuses", json!([[signature.to_string()]]))?;
+            let status = result.get("value").and_then(|value| value.get(0)).filter(|status| !status.is_null());
+            if let Some(status) = status {
+                if let Some(err) = status.get("err").filter(|err| !err.is_null()) {
+                    return Err(RpcError::new(format!("transaction {} failed: {}", signature, err), Some(err)).into());
+                }
+                let confirmation_status = status.get("confirmationStatus").and_then(Value::as_str);
+                if confirmation_status == Some("confirmed") || confirmation_status == Some("finalized") {
+                    return Ok(signature);
+                }
+            }
+            thread::sleep(CONFIRM_POLL_INTERVAL);
+        }
+        Err(format!("transaction {} was not confirmed in time", signature).into())
+    }
+}
@@ -0,0 +1,182 @@
+//! Canvas snapshot files
+//!
+//! Layout (little endian):
+//! - magic `NFTCSNAP`
+//! - version: u16
+//! - slot: u64
+//! - program id: 32 bytes
+//! - pixel record length: u16
+//! - pixel count: u32
+//! - zlib compressed pixel records, each a packed `Pixel`, sorted by index
+//!
+//! Older snapshots are upgraded when read: version 1 predates `Pixel::bump_seed`, which is derived
+//! again, and versions 1 and 2 predate the RGBA `Pixel::color`, which gets an opaque alpha.
+
+use std::error::Error;
+use std::io;
+use std::io::{Read, Write};
+
+use chikin_nft_canvas::color;
+use chikin_nft_canvas::config;
+use chikin_nft_canvas::packable::Packable;
+use chikin_nft_canvas::state::Pixel;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use solana_program::pubkey::Pubkey;
+use solana_program::rent::Rent;
+
+use crate::dump;
+use crate::dump::AccountDump;
+
+pub const MAGIC: &[u8; 8] = b"NFTCSNAP";
+pub const VERSION: u16 = 3;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    pub slot: u64,
+    pub program_id: Pubkey,
+    pub pixels: Vec<Pixel>,
+}
+
+/// A pixel account to preload, as the program would have created it.
+pub struct SnapshotAccount {
+    pub pubkey: Pubkey,
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub owner: Pubkey,
+}
+
+impl Snapshot {
+    /// `program_id` and `slot` override what the dump says, and are required when it does not say it.
+    pub fn from_dump(dump: &AccountDump, program_id: Option<Pubkey>, slot: Option<u64>) -> Result<Snapshot, Box<dyn Error>> {
+        let program_id = match program_id {
+            Some(program_id) => program_id,
+            None => {
+                let mut owners = dump.accounts.iter()
+                    .filter(|account| dump::is_pixel_account(account))
+                    .map(|account| account.owner);
+                let owner = owners.next().flatten();
+                if owner.is_none() || owners.any(|other| other != owner) {
+                    return Err("the dump does not name a single program owning the pixels, pass a program id".into());
+                }
+                owner.unwrap()
+            }
+        };
+        let pixels = dump::pixels(&dump.accounts, Some(&program_id))?;
+        let slot = slot.or(dump.slot)
+            .ok_or("the dump has no slot, pass one")?;
+        Ok(Snapshot::new(slot, program_id, pixels))
+    }
+
+    pub fn new(slot: u64, program_id: Pubkey, mut pixels: Vec<Pixel>) -> Snapshot {
+        pixels.sort_by_key(|pixel| pixel.index);
+        Snapshot { slot, program_id, pixels }
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&self.slot.to_le_bytes())?;
+        writer.write_all(self.program_id.as_ref())?;
+        writer.write_all(&(Pixel::PACKED_SIZE as u16).to_le_bytes())?;
+        writer.write_all(&(self.pixels.len() as u32).to_le_bytes())?;
+        let mut encoder = ZlibEncoder::new(writer, Compression::default());
+        for pixel in &self.pixels {
+            encoder.write_all(&pixel.pack())?;
+        }
+        encoder.finish()?.flush()
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Snapshot> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not a canvas snapshot".to_string()));
+        }
+        let version = u16::from_le_bytes(read_array(&mut reader)?);
+        let expected_record_len = record_len(version)
+            .ok_or_else(|| invalid_data(format!("unsupported snapshot version {}", version)))?;
+        let slot = u64::from_le_bytes(read_array(&mut reader)?);
+        let program_id = Pubkey::new_from_array(read_array(&mut reader)?);
+        let record_len = u16::from_le_bytes(read_array(&mut reader)?) as usize;
+        if record_len != expected_record_len {
+            return Err(invalid_data(format!("unsupported pixel record length {}", record_len)));
+        }
+        let pixel_count = u32::from_le_bytes(read_array(&mut reader)?);
+        if pixel_count > config::PIXEL_COUNT {
+            return Err(invalid_data(format!("too many pixels: {}", pixel_count)));
+        }
+
+        let mut decoder = ZlibDecoder::new(reader);
+        // (Older records are read into the start of the record, leaving the fields they lack zeroed)
+        let mut record = vec![0u8; Pixel::PACKED_SIZE];
+        let mut pixels = Vec::with_capacity(pixel_count as usize);
+        for _ in 0..pixel_count {
+            decoder.read_exact(&mut record[..record_len])?;
+            if version < 3 {
+                // (The RGB color is the 3 bytes after the index)
+                record.copy_within(7..record_len, 8);
+                record[7] = color::OPAQUE;
+            }
+            let mut pixel = Pixel::unpack(&record).map_err(|err| invalid_data(err.to_string()))?;
+            if version == 1 {
+                pixel.bump_seed = config::get_pixel(&program_id, pixel.index).1;
+            }
+            pixels.push(pixel);
+        }
+        // (Reading to the end also checks the zlib checksum)
+        if decoder.read(&mut record)? != 0 {
+            return Err(invalid_data("more pixel records than the pixel count".to_string()));
+        }
+        Ok(Snapshot { slot, program_id, pixels })
+    }
+
+    /// The pixel accounts of the snapshot, rent exempt under `rent`.
+    pub fn accounts(&self, rent: &Rent) -> Vec<SnapshotAccount> {
+        let lamports = rent.minimum_balance(Pixel::PACKED_SIZE).max(1);
+        self.pixels.iter()
+            .map(|pixel| SnapshotAccount {
+                pubkey: config::get_pixel(&self.program_id, pixel.index).0,
+                lamports,
+                data: pixel.pack(),
+                owner: self.program_id,
+            })
+            .collect()
+    }
+
+    /// Preloads the pixel accounts. `program_test` must run the program under the snapshot's program id,
+    /// since pixel addresses derive from it.
+    #[cfg(feature = "program-test")]
+    pub fn add_to_program_test(&self, program_test: &mut solana_program_test::ProgramTest) {
+        for account in self.accounts(&Rent::default()) {
+            program_test.add_account(account.pubkey, solana_sdk::account::Account {
+                lamports: account.lamports,
+                data: account.data,
+                owner: account.owner,
+                executable: false,
+                rent_epoch: 0,
+            });
+        }
+    }
+}
+
+/// Pixel record length of each snapshot version.
+fn record_len(version: u16) -> Option<usize> {
+    match version {
+        1 => Some(170),
+        2 => Some(171),
+        VERSION => Some(Pixel::PACKED_SIZE),
+        _ => None,
+    }
+}
+
+fn read_array<R: Read, const N: usize>(reader: &mut R) -> io::Result<[u8; N]> {
+    let mut array = [0u8; N];
+    reader.read_exact(&mut array)?;
+    Ok(array)
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,14 @@
+use chikin_nft_canvas::config;
+use chikin_nft_canvas_tools::{coordinates, parse_coordinates, program_error_name};
+
+#[test]
+fn test_cli_helpers() {
+    let index = parse_coordinates("12, 3").unwrap();
+    assert_eq!(index, 3 * config::CANVAS_WIDTH + 12);
+    assert_eq!(coordinates(index), (12, 3));
+    assert!(parse_coordinates("12").is_err());
+    assert!(parse_coordinates(&format!("{},0", config::CANVAS_WIDTH)).is_err());
+
+    assert_eq!(program_error_name(38), "NotOnAllowlist: Wallet is not on the presale allowlist");
+    assert_eq!(program_error_name(9999), "unknown program error 9999");
+}
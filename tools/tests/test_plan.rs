@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+
+use chikin_nft_canvas::color::{ColorMode, PixelColor};
+use chikin_nft_canvas::config;
+use chikin_nft_canvas::state::{CanvasConfig, Pixel};
+use chikin_nft_canvas_tools::plan;
+use chikin_nft_canvas_tools::plan::{PixelChange, PlanContext, Rect, RgbaImage};
+use solana_program::pubkey::Pubkey;
+use solana_sdk::packet::PACKET_DATA_SIZE;
+
+#[test]
+fn test_quantize() {
+    // 4x2: a red and a blue 2x2 block, the blue one half transparent
+    let mut rgba = Vec::new();
+    for _ in 0..2 {
+        rgba.extend_from_slice(&[255, 0, 0, 255, 255, 0, 0, 255, 0, 0, 255, 255, 0, 0, 255, 0]);
+    }
+    let image = RgbaImage { width: 4, height: 2, rgba };
+    assert_eq!(image.quantize(2, 1), vec![Some([255, 0, 0, 255]), None]);
+    assert_eq!(image.quantize(4, 1)[2], Some([0, 0, 255, 255]));
+    assert_eq!(image.quantize(4, 1)[3], None);
+    assert_eq!(image.quantize(1, 1), vec![Some([170, 0, 85, 191])]);
+    assert_eq!(image.quantize(8, 4).len(), 32);
+}
+
+#[test]
+fn test_plan() {
+    let wallet = Pubkey::new_unique();
+    let rect = Rect { x: 10, y: 20, width: 40, height: 1 };
+    rect.check_on_canvas().unwrap();
+    assert!(Rect { x: config::CANVAS_WIDTH - 1, y: 0, width: 2, height: 1 }.check_on_canvas().is_err());
+
+    let first = 20 * config::CANVAS_WIDTH + 10;
+    let mut cells = vec![Some([1, 2, 3, 200]); 40];
+    cells[1] = None;
+    let mut current = HashMap::new();
+    current.insert(first + 2, Pixel::new(first + 2, [1, 2, 3, 255], Pubkey::new_unique(), 0, 255));
+    current.insert(first + 3, Pixel::new(first + 3, [0, 0, 0, 255], wallet, 0, 255));
+    current.insert(first + 4, Pixel::new(first + 4, [0, 0, 0, 255], Pubkey::new_unique(), 0, 255));
+
+    let mut canvas_config = CanvasConfig::new(Pubkey::new_unique(), 255, 255);
+    let diff = plan::diff(&cells, &rect, &current, &wallet, &canvas_config);
+    assert_eq!((diff.unchanged, diff.transparent, diff.blocked.clone()), (1, 1, vec![first + 4]));
+    assert_eq!(diff.changes.len(), 37);
+    assert_eq!(diff.changes[0], PixelChange::Mint { index: first, color: PixelColor::Rgb([1, 2, 3]) });
+    assert_eq!(diff.changes[1], PixelChange::Paint { index: first + 3, color: PixelColor::Rgb([1, 2, 3]) });
+
+    canvas_config.mint_base_price = 100;
+    canvas_config.mint_price_increment = 10;
+    let context = PlanContext {
+        program_id: Pubkey::new_unique(),
+        wallet,
+        canvas_config,
+        minted: 5,
+        pixel_rent: 1000,
+        lamports_per_signature: 5000,
+    };
+    let plan = plan::plan(&diff.changes, &context).unwrap();
+    assert_eq!((plan.mints, plan.paints), (36, 1));
+    assert!(plan.transactions.len() > 1);
+    assert_eq!(plan.transactions.iter().map(Vec::len).sum::<usize>(), 37);
+    for instructions in &plan.transactions {
+        assert!(plan::transaction_size(instructions, &wallet).unwrap() <= PACKET_DATA_SIZE);
+    }
+    // (Every transaction but the last is full)
+    let first_transaction = &plan.transactions[0];
+    let mut overfull = first_transaction.clone();
+    overfull.push(plan.transactions[1][0].clone());
+    assert!(plan::transaction_size(&overfull, &wallet).unwrap() > PACKET_DATA_SIZE);
+
+    // 36 mints from the 6th: 36 * 100 + 10 * (5 + ... + 40)
+    assert_eq!(plan.cost.mint, 3600 + 10 * (5..41).sum::<u64>());
+    assert_eq!(plan.cost.rent, 36000);
+    assert_eq!(plan.cost.signature_fees, 5000 * plan.transactions.len() as u64);
+
+    let mut context = context;
+    context.canvas_config.painting_paused = true;
+    assert!(plan::plan(&diff.changes, &context).is_err());
+    assert!(plan::plan(&diff.changes[..1], &context).is_ok());
+}
+
+#[test]
+fn test_pixel_color() {
+    let mut canvas_config = CanvasConfig::new(Pubkey::new_unique(), 255, 255);
+    assert_eq!(plan::pixel_color([10, 20, 30, 128], &canvas_config), PixelColor::Rgb([10, 20, 30]));
+
+    canvas_config.set_color_mode(ColorMode::Rgba, &[]).unwrap();
+    assert_eq!(plan::pixel_color([10, 20, 30, 128], &canvas_config), PixelColor::Rgba([10, 20, 30, 128]));
+
+    canvas_config.set_color_mode(ColorMode::Palette, &[[0, 0, 0], [255, 255, 255], [250, 0, 0]]).unwrap();
+    assert_eq!(plan::pixel_color([200, 30, 40, 255], &canvas_config), PixelColor::Palette(2));
+    assert_eq!(plan::pixel_color([200, 200, 190, 255], &canvas_config), PixelColor::Palette(1));
+
+    // Pixels already in the nearest palette color are left alone
+    let wallet = Pubkey::new_unique();
+    let rect = Rect { x: 0, y: 0, width: 2, height: 1 };
+    let mut current = HashMap::new();
+    current.insert(0, Pixel::new(0, [250, 0, 0, 255], wallet, 0, 255));
+    current.insert(1, Pixel::new(1, [0, 0, 0, 255], wallet, 0, 255));
+    let diff = plan::diff(&[Some([200, 30, 40, 255]); 2], &rect, &current, &wallet, &canvas_config);
+    assert_eq!(diff.unchanged, 1);
+    assert_eq!(diff.changes, vec![PixelChange::Paint { index: 1, color: PixelColor::Palette(2) }]);
+}
@@ -0,0 +1,65 @@
+use chikin_nft_canvas::color::PixelColor;
+use chikin_nft_canvas::config;
+use chikin_nft_canvas::packable::Packable;
+use chikin_nft_canvas::state::{LegacyPixel, Pixel};
+use chikin_nft_canvas_tools::dump;
+use chikin_nft_canvas_tools::render;
+use chikin_nft_canvas_tools::render::RenderOptions;
+use solana_program::pubkey::Pubkey;
+
+#[test]
+fn test_render() {
+    let owner = Pubkey::new_unique();
+    let listed_pixel = Pixel::new(1, [200, 100, 0, 255], Pubkey::new_unique(), 5, 255);
+    let owned_pixel = Pixel::new(config::CANVAS_WIDTH + 2, [0, 0, 200, 255], owner, 0, 255);
+    let overlay_pixel = Pixel::new(3, [255, 255, 255, 51], Pubkey::new_unique(), 0, 255);
+    let program_id = Pubkey::new_unique();
+    let legacy_pixel = LegacyPixel { index: 4, color: [0, 200, 100], owner_wallet: owner, sell_price: 7, best_buy_info: None };
+    let json = format!(r#"{{"jsonrpc":"2.0","id":1,"result":[
+        {{"pubkey":"{}","account":{{"data":["{}","base64"]}}}},
+        {{"pubkey":"{}","account":{{"data":["{}","base64"]}}}},
+        {{"pubkey":"{}","account":{{"data":["AAAAAA==","base64"]}}}},
+        {{"pubkey":"{}","account":{{"data":["{}","base64"]}}}}
+    ]}}"#,
+        Pubkey::new_unique(), base64::encode(listed_pixel.pack()),
+        Pubkey::new_unique(), base64::encode(owned_pixel.pack()),
+        Pubkey::new_unique(),
+        config::get_pixel(&program_id, 4).0, base64::encode(legacy_pixel.pack()));
+    let accounts = dump::parse_dump(&json).unwrap().accounts;
+    assert_eq!(accounts.len(), 4);
+    // (Legacy pixels need the program id for their bump seed, and this dump has no account owners)
+    assert!(dump::pixels(&accounts, None).is_err());
+    let pixels = dump::pixels(&accounts, Some(&program_id)).unwrap();
+    assert_eq!(pixels, vec![listed_pixel, owned_pixel, legacy_pixel.upgrade(config::get_pixel(&program_id, 4).1)]);
+
+    let options = RenderOptions {
+        unminted_color: render::parse_color("#102030").unwrap(),
+        listed_overlay: Some([0, 0, 0]),
+        owner_overlay: Some((owner, [255, 255, 255])),
+    };
+    let mut pixels = pixels;
+    pixels.push(overlay_pixel);
+    let image = render::render(&pixels, &options);
+    assert_eq!((image.width, image.height), (config::CANVAS_WIDTH, config::CANVAS_HEIGHT));
+    assert_eq!(image.color_at(0, 0), [16, 32, 48]);
+    assert_eq!(image.color_at(1, 0), [100, 50, 0]);
+    assert_eq!(image.color_at(2, 1), [127, 127, 227]);
+    // (Transparent pixels show the unminted color through)
+    assert_eq!(image.color_at(3, 0), [64, 77, 89]);
+    // (Legacy pixels are opaque, and get the overlays like the others)
+    assert_eq!(image.color_at(4, 0), [127, 177, 152]);
+
+    let mut png = Vec::new();
+    image.write_png(&mut png).unwrap();
+    assert_eq!(&png[1..4], b"PNG");
+
+    assert!(render::parse_color("12345").is_err());
+    assert_eq!(render::parse_pixel_color("#0a0b0c"), Ok(PixelColor::Rgb([10, 11, 12])));
+    assert_eq!(render::parse_pixel_color("0a0b0c80"), Ok(PixelColor::Rgba([10, 11, 12, 128])));
+    assert_eq!(render::parse_pixel_color("p7"), Ok(PixelColor::Palette(7)));
+    assert!(render::parse_pixel_color("p256").is_err());
+    assert!(render::parse_pixel_color("0a0b0c8").is_err());
+    assert_eq!(render::format_color([10, 11, 12, 255]), "#0a0b0c");
+    assert_eq!(render::format_color([10, 11, 12, 128]), "#0a0b0c80");
+    assert!(dump::parse_dump(r#"{"result":[{"pubkey":"x"}]}"#).is_err());
+}
@@ -0,0 +1,109 @@
+use std::io::Write;
+
+use chikin_nft_canvas::config;
+use chikin_nft_canvas::packable::Packable;
+use chikin_nft_canvas::state::{LegacyPixel, Pixel, PixelBuyInfo};
+use chikin_nft_canvas_tools::dump;
+use chikin_nft_canvas_tools::snapshot::{Snapshot, MAGIC};
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use solana_program::pubkey::Pubkey;
+use solana_program::rent::Rent;
+
+#[test]
+fn test_snapshot() {
+    let program_id = Pubkey::new_unique();
+    let mut bid_pixel = Pixel::new(7, [1, 2, 3, 255], Pubkey::new_unique(), 0, config::get_pixel(&program_id, 7).1);
+    bid_pixel.best_buy_info = Some(PixelBuyInfo { price: 10, buyer_wallet: Pubkey::new_unique() });
+    let listed_pixel = Pixel::new(3, [4, 5, 6, 255], Pubkey::new_unique(), 20, config::get_pixel(&program_id, 3).1);
+    let json = format!(r#"{{"jsonrpc":"2.0","id":1,"result":{{"context":{{"slot":1234}},"value":[
+        {{"pubkey":"{}","account":{{"owner":"{}","data":["{}","base64"]}}}},
+        {{"pubkey":"{}","account":{{"owner":"{}","data":["{}","base64"]}}}}
+    ]}}}}"#,
+        config::get_pixel(&program_id, 7).0, program_id, base64::encode(bid_pixel.pack()),
+        config::get_pixel(&program_id, 3).0, program_id, base64::encode(listed_pixel.pack()));
+
+    let dump = dump::parse_dump(&json).unwrap();
+    assert_eq!(dump.slot, Some(1234));
+    let snapshot = Snapshot::from_dump(&dump, None, None).unwrap();
+    assert_eq!(snapshot, Snapshot { slot: 1234, program_id, pixels: vec![listed_pixel.clone(), bid_pixel] });
+
+    let mut bytes = Vec::new();
+    snapshot.write(&mut bytes).unwrap();
+    assert_eq!(Snapshot::read(&bytes[..]).unwrap(), snapshot);
+    assert!(Snapshot::read(&bytes[..bytes.len() - 4]).is_err());
+    let mut future_version = bytes.clone();
+    future_version[8] = 4;
+    assert!(Snapshot::read(&future_version[..]).is_err());
+
+    let accounts = snapshot.accounts(&Rent::default());
+    assert_eq!(accounts[0].pubkey, config::get_pixel(&program_id, 3).0);
+    assert_eq!(accounts[0].owner, program_id);
+    assert_eq!(accounts[0].data, listed_pixel.pack());
+    assert_eq!(accounts[0].lamports, Rent::default().minimum_balance(Pixel::PACKED_SIZE));
+
+    let other_program_id = Pubkey::new_unique();
+    assert_eq!(Snapshot::from_dump(&dump, Some(other_program_id), Some(1)).unwrap().program_id, other_program_id);
+}
+
+/// A snapshot of `pixel` in an older `version`, whose records are `record_len` bytes.
+fn legacy_snapshot(version: u16, record_len: usize, program_id: &Pubkey, record: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&version.to_le_bytes());
+    bytes.extend_from_slice(&99u64.to_le_bytes());
+    bytes.extend_from_slice(program_id.as_ref());
+    bytes.extend_from_slice(&(record_len as u16).to_le_bytes());
+    bytes.extend_from_slice(&1u32.to_le_bytes());
+    let mut encoder = ZlibEncoder::new(bytes, Compression::default());
+    encoder.write_all(&record[..record_len]).unwrap();
+    encoder.finish().unwrap()
+}
+
+#[test]
+fn test_snapshot_upgrade() {
+    let program_id = Pubkey::new_unique();
+    let pixel = Pixel::new(5, [7, 8, 9, 255], Pubkey::new_unique(), 30, config::get_pixel(&program_id, 5).1);
+    let expected = Snapshot { slot: 99, program_id, pixels: vec![pixel.clone()] };
+
+    // (Version 2 records are the packed pixel without the alpha)
+    let mut record = pixel.pack();
+    record.remove(7);
+    assert_eq!(Snapshot::read(&legacy_snapshot(2, 171, &program_id, &record)[..]).unwrap(), expected);
+
+    // (Version 1 records also lack the bump seed)
+    let mut record = Pixel { bump_seed: 0, ..pixel }.pack();
+    record.remove(7);
+    assert_eq!(Snapshot::read(&legacy_snapshot(1, 170, &program_id, &record)[..]).unwrap(), expected);
+
+    assert!(Snapshot::read(&legacy_snapshot(2, 170, &program_id, &record)[..]).is_err());
+}
+
+#[test]
+fn test_snapshot_legacy_pixel_accounts() {
+    let program_id = Pubkey::new_unique();
+    let legacy_pixel = LegacyPixel {
+        index: 9,
+        color: [10, 20, 30],
+        owner_wallet: Pubkey::new_unique(),
+        sell_price: 40,
+        best_buy_info: Some(PixelBuyInfo { price: 5, buyer_wallet: Pubkey::new_unique() }),
+    };
+    let pixel = Pixel::new(2, [1, 2, 3, 128], Pubkey::new_unique(), 0, config::get_pixel(&program_id, 2).1);
+    let json = format!(r#"{{"jsonrpc":"2.0","id":1,"result":{{"context":{{"slot":77}},"value":[
+        {{"pubkey":"{}","account":{{"owner":"{}","data":["{}","base64"]}}}},
+        {{"pubkey":"{}","account":{{"owner":"{}","data":["{}","base64"]}}}}
+    ]}}}}"#,
+        config::get_pixel(&program_id, 9).0, program_id, base64::encode(legacy_pixel.pack()),
+        config::get_pixel(&program_id, 2).0, program_id, base64::encode(pixel.pack()));
+
+    // Legacy pixels are kept, upgraded with the bump seed of their address
+    let snapshot = Snapshot::from_dump(&dump::parse_dump(&json).unwrap(), None, None).unwrap();
+    let upgraded = legacy_pixel.upgrade(config::get_pixel(&program_id, 9).1);
+    assert_eq!(upgraded.color, [10, 20, 30, 255]);
+    assert_eq!(snapshot, Snapshot { slot: 77, program_id, pixels: vec![pixel, upgraded] });
+
+    let mut bytes = Vec::new();
+    snapshot.write(&mut bytes).unwrap();
+    assert_eq!(Snapshot::read(&bytes[..]).unwrap(), snapshot);
+}